@@ -7,9 +7,11 @@
 // modified, or distributed except according to those terms.
 
 use std::collections::VecDeque;
+use std::time::Duration;
 
 use state::State;
 use context::base::BaseContext;
+use conditions::CloseReason;
 use Event;
 use Template;
 
@@ -17,5 +19,10 @@ pub use config::action::message::Alert;
 
 pub trait Action<E, T> where E: Event, T: Template<Event=E> {
     fn on_opened(&self, state: &State<E>, context: &BaseContext<E, T>, &mut VecDeque<Alert<E>>);
-    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, &mut VecDeque<Alert<E>>);
+    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, reason: CloseReason, &mut VecDeque<Alert<E>>);
+    fn on_threshold(&self, state: &State<E>, context: &BaseContext<E, T>, &mut VecDeque<Alert<E>>);
+    /// Called when the context accepts `event`, `state` already contains it.
+    fn on_message(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, &mut VecDeque<Alert<E>>);
+    /// Called once for every timer event, regardless of the number of states of the context.
+    fn on_timer(&self, event: &Duration, &mut VecDeque<Alert<E>>);
 }
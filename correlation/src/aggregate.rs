@@ -0,0 +1,313 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::BTreeSet;
+use std::str::{self, FromStr};
+
+use Event;
+
+/// The maximal number of different values kept by `distinct_count` and `distinct`.
+pub const MAX_DISTINCT_VALUES: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregateFunction {
+    /// The number of events (which have `field`, if it's set).
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+    /// The first value of `field`.
+    First,
+    /// The last value of `field`.
+    Last,
+    /// The number of different values of `field`.
+    DistinctCount,
+    /// The different values of `field` separated by commas.
+    Distinct,
+}
+
+/// A value computed incrementally from the events of a context.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    pub field: Option<String>,
+}
+
+impl Aggregate {
+    pub fn new(function: AggregateFunction, field: Option<String>) -> Aggregate {
+        Aggregate {
+            function: function,
+            field: field,
+        }
+    }
+
+    pub fn update<E: Event>(&self, accumulator: &mut Accumulator, event: &E) {
+        let value = match self.field {
+            Some(ref field) => {
+                match event.get(field.as_bytes()) {
+                    Some(value) => Some(value),
+                    None => return,
+                }
+            }
+            None => None,
+        };
+
+        accumulator.count += 1;
+
+        match self.function {
+            AggregateFunction::Count => (),
+            AggregateFunction::Sum | AggregateFunction::Min | AggregateFunction::Max | AggregateFunction::Avg => {
+                if let Some(number) = value.and_then(parse_number) {
+                    accumulator.numbers += 1;
+                    accumulator.sum += number;
+                    if accumulator.min.map_or(true, |min| number < min) {
+                        accumulator.min = Some(number);
+                    }
+                    if accumulator.max.map_or(true, |max| number > max) {
+                        accumulator.max = Some(number);
+                    }
+                }
+            }
+            AggregateFunction::First => {
+                if accumulator.first.is_none() {
+                    accumulator.first = value.map(|value| value.to_vec());
+                }
+            }
+            AggregateFunction::Last => accumulator.last = value.map(|value| value.to_vec()),
+            AggregateFunction::DistinctCount | AggregateFunction::Distinct => {
+                if let Some(value) = value {
+                    if !accumulator.distinct.contains(value) {
+                        if accumulator.distinct.len() < MAX_DISTINCT_VALUES {
+                            accumulator.distinct.insert(value.to_vec());
+                        } else {
+                            accumulator.capped = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renders the aggregated value. It's empty if no value was aggregated.
+    pub fn value(&self, accumulator: &Accumulator) -> String {
+        match self.function {
+            AggregateFunction::Count => accumulator.count.to_string(),
+            AggregateFunction::Sum => accumulator.sum.to_string(),
+            AggregateFunction::Min => accumulator.min.map_or_else(String::new, |min| min.to_string()),
+            AggregateFunction::Max => accumulator.max.map_or_else(String::new, |max| max.to_string()),
+            AggregateFunction::Avg => {
+                if accumulator.numbers > 0 {
+                    (accumulator.sum / accumulator.numbers as f64).to_string()
+                } else {
+                    String::new()
+                }
+            }
+            AggregateFunction::First => lossy(accumulator.first.as_ref()),
+            AggregateFunction::Last => lossy(accumulator.last.as_ref()),
+            AggregateFunction::DistinctCount if accumulator.capped => format!("{}+", accumulator.distinct.len()),
+            AggregateFunction::DistinctCount => accumulator.distinct.len().to_string(),
+            AggregateFunction::Distinct => {
+                accumulator.distinct
+                           .iter()
+                           .map(|value| String::from_utf8_lossy(value).into_owned())
+                           .collect::<Vec<String>>()
+                           .join(",")
+            }
+        }
+    }
+}
+
+fn lossy(value: Option<&Vec<u8>>) -> String {
+    value.map_or_else(String::new, |value| String::from_utf8_lossy(value).into_owned())
+}
+
+fn parse_number(value: &[u8]) -> Option<f64> {
+    str::from_utf8(value).ok().and_then(|value| f64::from_str(value.trim()).ok())
+}
+
+/// The intermediate result of an `Aggregate`, it's stored in the `State`.
+#[derive(Clone, Debug, Default)]
+pub struct Accumulator {
+    count: usize,
+    numbers: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    first: Option<Vec<u8>>,
+    last: Option<Vec<u8>>,
+    distinct: BTreeSet<Vec<u8>>,
+    /// `true` if a different value was ignored because of `MAX_DISTINCT_VALUES`.
+    capped: bool,
+}
+
+mod deser {
+    use super::{Aggregate, AggregateFunction};
+    use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+
+    impl Deserialize for Aggregate {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Aggregate, D::Error>
+            where D: Deserializer
+        {
+            deserializer.deserialize_struct("Aggregate", &[], AggregateVisitor)
+        }
+    }
+
+    enum Field {
+        Function,
+        Field,
+    }
+
+    impl Deserialize for Field {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+            where D: Deserializer
+        {
+            struct FieldVisitor;
+
+            impl Visitor for FieldVisitor {
+                type Value = Field;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                    where E: Error
+                {
+                    match value {
+                        "function" => Ok(Field::Function),
+                        "field" => Ok(Field::Field),
+                        _ => Err(E::custom(format!("Unexpected field: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(FieldVisitor)
+        }
+    }
+
+    struct AggregateVisitor;
+
+    impl Visitor for AggregateVisitor {
+        type Value = Aggregate;
+
+        fn visit_map<V>(&mut self, mut visitor: V) -> Result<Aggregate, V::Error>
+            where V: MapVisitor
+        {
+            let mut function = None;
+            let mut field = None;
+
+            while let Some(key) = try!(visitor.visit_key()) {
+                match key {
+                    Field::Function => function = Some(try!(visitor.visit_value())),
+                    Field::Field => field = Some(try!(visitor.visit_value())),
+                }
+            }
+
+            let function = match function {
+                Some(function) => function,
+                None => return visitor.missing_field("function"),
+            };
+
+            if function != AggregateFunction::Count && field.is_none() {
+                return visitor.missing_field("field");
+            }
+
+            try!(visitor.end());
+
+            Ok(Aggregate::new(function, field))
+        }
+    }
+
+    impl Deserialize for AggregateFunction {
+        fn deserialize<D>(deserializer: &mut D) -> Result<AggregateFunction, D::Error>
+            where D: Deserializer
+        {
+            struct AggregateFunctionVisitor;
+
+            impl Visitor for AggregateFunctionVisitor {
+                type Value = AggregateFunction;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<AggregateFunction, E>
+                    where E: Error
+                {
+                    match value {
+                        "count" => Ok(AggregateFunction::Count),
+                        "sum" => Ok(AggregateFunction::Sum),
+                        "min" => Ok(AggregateFunction::Min),
+                        "max" => Ok(AggregateFunction::Max),
+                        "avg" => Ok(AggregateFunction::Avg),
+                        "first" => Ok(AggregateFunction::First),
+                        "last" => Ok(AggregateFunction::Last),
+                        "distinct_count" => Ok(AggregateFunction::DistinctCount),
+                        "distinct" => Ok(AggregateFunction::Distinct),
+                        _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(AggregateFunctionVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Accumulator, Aggregate, AggregateFunction, MAX_DISTINCT_VALUES};
+    use message::MessageBuilder;
+    use serde_json::from_str;
+
+    fn aggregate(function: AggregateFunction, field: Option<&str>, values: &[&str]) -> String {
+        let aggregate = Aggregate::new(function, field.map(|field| field.to_owned()));
+        let mut accumulator = Accumulator::default();
+        for value in values {
+            let event = MessageBuilder::new("uuid", "message").pair(b"bytes", value.as_bytes()).build();
+            aggregate.update(&mut accumulator, &event);
+        }
+        aggregate.value(&accumulator)
+    }
+
+    #[test]
+    fn test_given_aggregates_when_events_are_added_then_the_values_are_computed_incrementally() {
+        let values = ["300", "1000", "abc", "300"];
+        assert_eq!("4", aggregate(AggregateFunction::Count, None, &values));
+        assert_eq!("0", aggregate(AggregateFunction::Count, Some("missing"), &values));
+        assert_eq!("1600", aggregate(AggregateFunction::Sum, Some("bytes"), &values));
+        assert_eq!("300", aggregate(AggregateFunction::Min, Some("bytes"), &values));
+        assert_eq!("1000", aggregate(AggregateFunction::Max, Some("bytes"), &values));
+        assert_eq!("533.3333333333334", aggregate(AggregateFunction::Avg, Some("bytes"), &values));
+        assert_eq!("300", aggregate(AggregateFunction::First, Some("bytes"), &values));
+        assert_eq!("300", aggregate(AggregateFunction::Last, Some("bytes"), &values));
+        assert_eq!("3", aggregate(AggregateFunction::DistinctCount, Some("bytes"), &values));
+        assert_eq!("1000,300,abc", aggregate(AggregateFunction::Distinct, Some("bytes"), &values));
+        assert_eq!("", aggregate(AggregateFunction::Max, Some("bytes"), &[]));
+    }
+
+    #[test]
+    fn test_given_aggregate_in_json_when_it_is_deserialized_then_we_get_the_expected_result() {
+        let aggregate = from_str::<Aggregate>(r#"{ "function": "distinct_count", "field": "src_ip" }"#);
+        assert_eq!(Aggregate::new(AggregateFunction::DistinctCount, Some("src_ip".to_owned())), aggregate.unwrap());
+        let aggregate = from_str::<Aggregate>(r#"{ "function": "count" }"#);
+        assert_eq!(Aggregate::new(AggregateFunction::Count, None), aggregate.unwrap());
+    }
+
+    #[test]
+    fn test_given_invalid_aggregate_in_json_when_it_is_deserialized_then_we_get_an_error() {
+        let _ = from_str::<Aggregate>(r#"{ "function": "sum" }"#).err().expect("Successfully deserialized a sum without field");
+        let _ = from_str::<Aggregate>(r#"{ "function": "median", "field": "bytes" }"#).err().expect("Successfully deserialized an unknown function");
+    }
+
+    #[test]
+    fn test_given_distinct_aggregate_when_there_are_too_many_different_values_then_they_are_capped() {
+        let values: Vec<String> = (0..MAX_DISTINCT_VALUES + 10).map(|value| value.to_string()).collect();
+        let values: Vec<&str> = values.iter().map(|value| &value[..]).collect();
+        assert_eq!(format!("{}+", MAX_DISTINCT_VALUES),
+                   aggregate(AggregateFunction::DistinctCount, Some("bytes"), &values));
+        assert_eq!(MAX_DISTINCT_VALUES.to_string(),
+                   aggregate(AggregateFunction::DistinctCount, Some("bytes"), &values[..MAX_DISTINCT_VALUES]));
+        let repeated: Vec<&str> = values[..MAX_DISTINCT_VALUES].iter().chain(&values[..10]).cloned().collect();
+        assert_eq!(MAX_DISTINCT_VALUES.to_string(),
+                   aggregate(AggregateFunction::DistinctCount, Some("bytes"), &repeated));
+    }
+}
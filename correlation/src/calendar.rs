@@ -0,0 +1,176 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::cmp;
+use std::time::Duration;
+
+/// Aligns the windows of a context to the wall clock: they are closed on
+/// every multiple of `interval` (e.g. every 5 minutes, on the hour or at
+/// midnight) in the timezone given by `utc_offset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calendar {
+    pub interval: Duration,
+    /// The offset of the timezone from UTC in seconds. It's fixed, so it
+    /// doesn't follow the daylight saving time.
+    pub utc_offset: i64,
+}
+
+impl Calendar {
+    pub fn new(interval: Duration) -> Calendar {
+        Calendar {
+            interval: interval,
+            utc_offset: 0,
+        }
+    }
+
+    pub fn utc_offset(mut self, utc_offset: i64) -> Calendar {
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// Returns `true` if there is a boundary after `from` but not after `to`.
+    /// Both of them are measured from the UNIX epoch.
+    pub fn is_boundary_crossed(&self, from: &Duration, to: &Duration) -> bool {
+        self.window(from) < self.window(to)
+    }
+
+    fn window(&self, time: &Duration) -> i64 {
+        let interval = cmp::max(to_millis(&self.interval), 1);
+        let local_time = to_millis(time) + self.utc_offset * 1000;
+        if local_time >= 0 {
+            local_time / interval
+        } else {
+            (local_time - interval + 1) / interval
+        }
+    }
+}
+
+fn to_millis(duration: &Duration) -> i64 {
+    duration.as_secs() as i64 * 1000 + (duration.subsec_nanos() / 1_000_000) as i64
+}
+
+mod deser {
+    use super::Calendar;
+    use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+    use std::time::Duration;
+    use duration::SerializableDuration;
+    use correlator::parse_utc_offset;
+
+    impl Deserialize for Calendar {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Calendar, D::Error>
+            where D: Deserializer
+        {
+            deserializer.deserialize_struct("Calendar", &[], CalendarVisitor)
+        }
+    }
+
+    enum Field {
+        Interval,
+        UtcOffset,
+    }
+
+    impl Deserialize for Field {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+            where D: Deserializer
+        {
+            struct FieldVisitor;
+
+            impl Visitor for FieldVisitor {
+                type Value = Field;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                    where E: Error
+                {
+                    match value {
+                        "interval" => Ok(Field::Interval),
+                        "utc_offset" => Ok(Field::UtcOffset),
+                        _ => Err(E::custom(format!("Unexpected field: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(FieldVisitor)
+        }
+    }
+
+    struct CalendarVisitor;
+
+    impl Visitor for CalendarVisitor {
+        type Value = Calendar;
+
+        fn visit_map<V>(&mut self, mut visitor: V) -> Result<Calendar, V::Error>
+            where V: MapVisitor
+        {
+            let mut interval: Option<SerializableDuration> = None;
+            let mut utc_offset = 0;
+
+            while let Some(field) = try!(visitor.visit_key()) {
+                match field {
+                    Field::Interval => interval = Some(try!(visitor.visit_value())),
+                    Field::UtcOffset => {
+                        let value: String = try!(visitor.visit_value());
+                        utc_offset = match parse_utc_offset(&value) {
+                            Some(utc_offset) => utc_offset,
+                            None if value == "Z" => 0,
+                            None => return Err(V::Error::custom(format!("Invalid UTC offset: {}", value))),
+                        };
+                    }
+                }
+            }
+
+            let interval = match interval {
+                Some(interval) if interval.0 > Duration::from_secs(0) => interval.0,
+                Some(_) => return Err(V::Error::custom("The interval of a calendar must be positive")),
+                None => return visitor.missing_field("interval"),
+            };
+
+            try!(visitor.end());
+
+            Ok(Calendar::new(interval).utc_offset(utc_offset))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Calendar;
+    use serde_json::from_str;
+    use std::time::Duration;
+
+    const HOUR: u64 = 3600;
+    const DAY: u64 = 24 * HOUR;
+
+    #[test]
+    fn test_given_daily_calendar_when_midnight_is_crossed_then_the_boundary_is_detected() {
+        let calendar = Calendar::new(Duration::from_secs(DAY));
+        let before_midnight = Duration::from_secs(10 * DAY - 1);
+        let midnight = Duration::from_secs(10 * DAY);
+        assert!(calendar.is_boundary_crossed(&before_midnight, &midnight));
+        assert!(!calendar.is_boundary_crossed(&midnight, &Duration::from_secs(11 * DAY - 1)));
+        assert!(!calendar.is_boundary_crossed(&before_midnight, &before_midnight));
+    }
+
+    #[test]
+    fn test_given_calendar_with_utc_offset_when_the_local_midnight_is_crossed_then_the_boundary_is_detected() {
+        let calendar = Calendar::new(Duration::from_secs(DAY)).utc_offset(2 * HOUR as i64);
+        // 22:00 UTC is midnight in UTC+02:00
+        let local_midnight = Duration::from_secs(10 * DAY - 2 * HOUR);
+        assert!(calendar.is_boundary_crossed(&(local_midnight - Duration::from_secs(1)), &local_midnight));
+        assert!(!calendar.is_boundary_crossed(&local_midnight, &Duration::from_secs(10 * DAY)));
+    }
+
+    #[test]
+    fn test_given_calendar_in_json_when_it_is_deserialized_then_we_get_the_expected_result() {
+        let calendar = from_str::<Calendar>(r#"{ "interval": 300000, "utc_offset": "-05:30" }"#);
+        assert_eq!(Calendar::new(Duration::from_secs(300)).utc_offset(-(5 * HOUR as i64 + 30 * 60)), calendar.unwrap());
+        let calendar = from_str::<Calendar>(r#"{ "interval": 3600000 }"#);
+        assert_eq!(Calendar::new(Duration::from_secs(HOUR)), calendar.unwrap());
+        let _ = from_str::<Calendar>(r#"{ "interval": 0 }"#).err().expect("Successfully deserialized a calendar with zero interval");
+        let _ = from_str::<Calendar>(r#"{ "interval": 1000, "utc_offset": "CET" }"#).err().expect("Successfully deserialized an invalid UTC offset");
+    }
+}
@@ -8,16 +8,118 @@
 
 use std::time::Duration;
 
+use Calendar;
+use Pattern;
+
 const FIRST_OPENS_DEFAULT: bool = false;
 const LAST_CLOSES_DEFAULT: bool = false;
+const ORDERED_DEFAULT: bool = false;
+const SLIDING_DEFAULT: bool = false;
+const SUPPRESS_DEFAULT: bool = false;
+
+/// Defines what happens when an ordered context receives an event out of order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutOfOrder {
+    /// The event is dropped, the context waits for the expected event.
+    Ignore,
+    /// The partial sequence is discarded without executing any actions.
+    Reset,
+    /// The context is closed and its actions are executed.
+    Close,
+}
+
+impl Default for OutOfOrder {
+    fn default() -> OutOfOrder {
+        OutOfOrder::Ignore
+    }
+}
+
+/// Defines what happens when a context reaches its `max_size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// The context is closed.
+    Close,
+    /// The events are counted, but only the first `max_size` ones are stored.
+    Discard,
+    /// The last `max_size` events are stored.
+    Ring,
+    /// The first `max_size` and the last `max_size` events are stored.
+    FirstLast,
+}
+
+impl Default for Overflow {
+    fn default() -> Overflow {
+        Overflow::Close
+    }
+}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Defines what happens when an opening event is received by an already open context.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reopen {
+    /// The event is added to the open context.
+    Append,
+    /// The open context is closed and a new one is opened with the event.
+    CloseAndReopen,
+    /// The event is dropped.
+    Ignore,
+}
+
+impl Default for Reopen {
+    fn default() -> Reopen {
+        Reopen::Append
+    }
+}
+
+/// The condition which caused a context to be closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The closing event was received (`closes`, `last_closes` or a completed `ordered` sequence).
+    Completed,
+    Timeout,
+    RenewTimeout,
+    MaxSize,
+    OutOfOrder,
+    /// The state was closed to make room for a new one.
+    Evicted,
+    /// An opening event was received and `on_reopen` is `close_and_reopen`.
+    Reopened,
+    /// A boundary of the `calendar` was crossed.
+    Boundary,
+}
+
+impl CloseReason {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CloseReason::Completed => "completed",
+            CloseReason::Timeout => "timeout",
+            CloseReason::RenewTimeout => "renew_timeout",
+            CloseReason::MaxSize => "max_size",
+            CloseReason::OutOfOrder => "out_of_order",
+            CloseReason::Evicted => "evicted",
+            CloseReason::Reopened => "reopened",
+            CloseReason::Boundary => "boundary",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Conditions {
     pub timeout: Duration,
     pub renew_timeout: Option<Duration>,
     pub first_opens: bool,
     pub last_closes: bool,
+    pub opens: Vec<Pattern>,
+    pub closes: Vec<Pattern>,
     pub max_size: Option<usize>,
+    pub overflow: Overflow,
+    pub min_size: Option<usize>,
+    pub ordered: bool,
+    pub out_of_order: OutOfOrder,
+    pub sliding: bool,
+    pub suppress: bool,
+    pub on_reopen: Reopen,
+    /// Aligns the windows to the wall clock, `timeout` is ignored if it's set.
+    pub calendar: Option<Calendar>,
 }
 
 impl Conditions {
@@ -27,7 +129,17 @@ impl Conditions {
             renew_timeout: None,
             first_opens: FIRST_OPENS_DEFAULT,
             last_closes: LAST_CLOSES_DEFAULT,
+            opens: Vec::new(),
+            closes: Vec::new(),
             max_size: None,
+            overflow: Overflow::default(),
+            min_size: None,
+            ordered: ORDERED_DEFAULT,
+            out_of_order: OutOfOrder::default(),
+            sliding: SLIDING_DEFAULT,
+            suppress: SUPPRESS_DEFAULT,
+            on_reopen: Reopen::default(),
+            calendar: None,
         }
     }
 }
@@ -55,11 +167,62 @@ impl ConditionsBuilder {
         self.conditions.last_closes = last_closes;
         self
     }
+
+    pub fn opens<P: Into<Pattern>>(&mut self, opens: Vec<P>) -> &mut ConditionsBuilder {
+        self.conditions.opens = opens.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn closes<P: Into<Pattern>>(&mut self, closes: Vec<P>) -> &mut ConditionsBuilder {
+        self.conditions.closes = closes.into_iter().map(Into::into).collect();
+        self
+    }
+
     pub fn max_size(&mut self, max_size: usize) -> &mut ConditionsBuilder {
         self.conditions.max_size = Some(max_size);
         self
     }
 
+    pub fn overflow(&mut self, overflow: Overflow) -> &mut ConditionsBuilder {
+        self.conditions.overflow = overflow;
+        self
+    }
+
+    pub fn min_size(&mut self, min_size: usize) -> &mut ConditionsBuilder {
+        self.conditions.min_size = Some(min_size);
+        self
+    }
+
+    pub fn ordered(&mut self, ordered: bool) -> &mut ConditionsBuilder {
+        self.conditions.ordered = ordered;
+        self
+    }
+
+    pub fn out_of_order(&mut self, out_of_order: OutOfOrder) -> &mut ConditionsBuilder {
+        self.conditions.out_of_order = out_of_order;
+        self
+    }
+
+    pub fn sliding(&mut self, sliding: bool) -> &mut ConditionsBuilder {
+        self.conditions.sliding = sliding;
+        self
+    }
+
+    pub fn suppress(&mut self, suppress: bool) -> &mut ConditionsBuilder {
+        self.conditions.suppress = suppress;
+        self
+    }
+
+    pub fn on_reopen(&mut self, on_reopen: Reopen) -> &mut ConditionsBuilder {
+        self.conditions.on_reopen = on_reopen;
+        self
+    }
+
+    pub fn calendar(&mut self, calendar: Calendar) -> &mut ConditionsBuilder {
+        self.conditions.calendar = Some(calendar);
+        self
+    }
+
     pub fn build(&mut self) -> Conditions {
         self.conditions.clone()
     }
@@ -68,7 +231,7 @@ impl ConditionsBuilder {
 #[cfg(test)]
 mod test {
     use serde_json::from_str;
-    use super::Conditions;
+    use super::{Conditions, OutOfOrder, Overflow, Reopen};
 
     use message::MessageBuilder;
     use state::State;
@@ -77,7 +240,9 @@ mod test {
     use std::time::Duration;
     use std::collections::VecDeque;
     use test_utils::{MockTemplate, BaseContextBuilder};
+    use Calendar;
     use Message;
+    use {Pattern, PatternKind};
 
     #[test]
     fn test_given_condition_when_an_opening_message_is_received_then_the_state_becomes_opened() {
@@ -145,7 +310,14 @@ mod test {
             "renew_timeout": 50,
             "first_opens": true,
             "last_closes": false,
-            "max_size": 42
+            "max_size": 42,
+            "overflow": "ring",
+            "min_size": 5,
+            "ordered": true,
+            "out_of_order": "reset",
+            "sliding": true,
+            "suppress": true,
+            "on_reopen": "close_and_reopen"
         }
         "#;
 
@@ -157,6 +329,111 @@ mod test {
         assert_eq!(conditions.first_opens, true);
         assert_eq!(conditions.last_closes, false);
         assert_eq!(conditions.max_size, Some(42));
+        assert_eq!(conditions.overflow, Overflow::Ring);
+        assert_eq!(conditions.min_size, Some(5));
+        assert_eq!(conditions.ordered, true);
+        assert_eq!(conditions.out_of_order, OutOfOrder::Reset);
+        assert_eq!(conditions.sliding, true);
+        assert_eq!(conditions.suppress, true);
+        assert_eq!(conditions.on_reopen, Reopen::CloseAndReopen);
+    }
+
+    #[test]
+    fn test_given_conditions_in_json_when_opens_and_closes_are_present_then_we_get_the_patterns() {
+        let json = r#"
+        {
+            "timeout": 100,
+            "opens": ["LOGIN", "SSO_LOGIN"],
+            "closes": ["LOGOUT", { "name": "SESSION_*", "match": "glob" }]
+        }
+        "#;
+
+        let conditions = from_str::<Conditions>(json).expect("Failed to deserialize a Conditions struct");
+        assert_eq!(conditions.opens, vec![Pattern::new("LOGIN"), Pattern::new("SSO_LOGIN")]);
+        assert_eq!(conditions.closes, vec![Pattern::new("LOGOUT"), Pattern::with_kind("SESSION_*", PatternKind::Glob).unwrap()]);
+    }
+
+    #[test]
+    fn test_given_condition_with_opens_and_closes_when_any_of_them_is_received_then_the_state_is_opened_or_closed() {
+        let mut responder = VecDeque::default();
+        let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                             .first_opens(true)
+                             .opens(vec!["SSO_LOGIN", "API_TOKEN_AUTH"])
+                             .closes(vec!["SESSION_EXPIRED", "KILLED"])
+                             .build();
+        let context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).patterns(vec!["LOGIN", "MAIL_READ"]).build();
+        let message = |name| MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").name(Some(name)).build();
+        assert_true!(context.is_opening(&message("LOGIN")));
+        assert_true!(context.is_opening(&message("API_TOKEN_AUTH")));
+        assert_false!(context.is_opening(&message("MAIL_READ")));
+        assert_false!(context.is_accepting(&message("LOGOUT")));
+
+        let mut state = State::new();
+        context.on_message(message("MAIL_READ"), &mut state, &mut responder);
+        assert_false!(state.is_open());
+        context.on_message(message("SSO_LOGIN"), &mut state, &mut responder);
+        assert_true!(state.is_open());
+        context.on_message(message("MAIL_READ"), &mut state, &mut responder);
+        assert_true!(state.is_open());
+        context.on_message(message("KILLED"), &mut state, &mut responder);
+        assert_false!(state.is_open());
+    }
+
+    #[test]
+    fn test_given_conditions_in_json_when_calendar_is_set_then_the_timeout_is_optional() {
+        let json = r#"
+        {
+            "calendar": { "interval": 86400000, "utc_offset": "+02:00" }
+        }
+        "#;
+
+        let conditions = from_str::<Conditions>(json).expect("Failed to deserialize a Conditions struct");
+        assert_eq!(conditions.calendar, Some(Calendar::new(Duration::from_secs(86400)).utc_offset(7200)));
+        let _ = from_str::<Conditions>("{}").err().expect("Successfully deserialized a Conditions struct without timeout");
+    }
+
+    #[test]
+    fn test_given_conditions_in_json_when_out_of_order_has_an_unknown_value_then_we_get_error() {
+        let json = r#"
+        {
+            "timeout": 100,
+            "out_of_order": "unknown"
+        }
+        "#;
+
+        let result = from_str::<Conditions>(json);
+        let _ = result.err().expect("Successfully deserialized an invalid out_of_order value");
+    }
+
+    #[test]
+    fn test_given_conditions_in_json_when_min_size_is_greater_than_max_size_then_we_get_error() {
+        let json = r#"
+        {
+            "timeout": 100,
+            "min_size": 5,
+            "max_size": 3
+        }
+        "#;
+
+        let result = from_str::<Conditions>(json);
+        let _ = result.err().expect("Successfully deserialized a min_size greater than max_size");
+    }
+
+    #[test]
+    fn test_given_conditions_in_json_when_min_size_is_greater_than_max_size_with_ring_overflow_then_they_are_deserialized() {
+        let json = r#"
+        {
+            "timeout": 100,
+            "min_size": 100,
+            "max_size": 10,
+            "overflow": "ring"
+        }
+        "#;
+
+        let conditions = from_str::<Conditions>(json).ok().expect("Failed to deserialize min_size greater than max_size with ring overflow");
+        assert_eq!(conditions.min_size, Some(100));
+        assert_eq!(conditions.max_size, Some(10));
+        assert_eq!(conditions.overflow, Overflow::Ring);
     }
 
     #[test]
@@ -222,7 +499,7 @@ mod test {
         let conditions = ConditionsBuilder::new(Duration::from_millis(100))
                              .first_opens(true)
                              .build();
-        let context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).patterns(Vec::new()).build();
+        let context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).patterns(Vec::<String>::new()).build();
         let mut state = State::new();
         context.on_message(msg, &mut state, &mut responder);
     }
@@ -244,7 +521,7 @@ mod test {
 }
 
 mod deser {
-    use super::{Conditions, FIRST_OPENS_DEFAULT, LAST_CLOSES_DEFAULT};
+    use super::{Calendar, Conditions, OutOfOrder, Overflow, Reopen, FIRST_OPENS_DEFAULT, LAST_CLOSES_DEFAULT, ORDERED_DEFAULT, SLIDING_DEFAULT, SUPPRESS_DEFAULT};
     use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
     use std::time::Duration;
     use duration::SerializableDuration;
@@ -262,7 +539,17 @@ mod deser {
         RenewTimeout,
         FirstOpens,
         LastCloses,
+        Opens,
+        Closes,
         MaxSize,
+        Overflow,
+        MinSize,
+        Ordered,
+        OutOfOrder,
+        Sliding,
+        Suppress,
+        OnReopen,
+        Calendar,
     }
 
     impl Deserialize for Field {
@@ -282,7 +569,17 @@ mod deser {
                         "renew_timeout" => Ok(Field::RenewTimeout),
                         "first_opens" => Ok(Field::FirstOpens),
                         "last_closes" => Ok(Field::LastCloses),
+                        "opens" => Ok(Field::Opens),
+                        "closes" => Ok(Field::Closes),
                         "max_size" => Ok(Field::MaxSize),
+                        "overflow" => Ok(Field::Overflow),
+                        "min_size" => Ok(Field::MinSize),
+                        "ordered" => Ok(Field::Ordered),
+                        "out_of_order" => Ok(Field::OutOfOrder),
+                        "sliding" => Ok(Field::Sliding),
+                        "suppress" => Ok(Field::Suppress),
+                        "on_reopen" => Ok(Field::OnReopen),
+                        "calendar" => Ok(Field::Calendar),
                         _ => Err(E::custom(format!("Unexpected field: {}", value))),
                     }
                 }
@@ -304,7 +601,17 @@ mod deser {
             let mut renew_timeout: Option<SerializableDuration> = None;
             let mut first_opens = FIRST_OPENS_DEFAULT;
             let mut last_closes = LAST_CLOSES_DEFAULT;
+            let mut opens = Vec::new();
+            let mut closes = Vec::new();
             let mut max_size = None;
+            let mut overflow = Overflow::default();
+            let mut min_size = None;
+            let mut ordered = ORDERED_DEFAULT;
+            let mut out_of_order = OutOfOrder::default();
+            let mut sliding = SLIDING_DEFAULT;
+            let mut suppress = SUPPRESS_DEFAULT;
+            let mut on_reopen = Reopen::default();
+            let mut calendar: Option<Calendar> = None;
 
             while let Some(field) = try!(visitor.visit_key()) {
                 match field {
@@ -312,17 +619,36 @@ mod deser {
                     Field::RenewTimeout => renew_timeout = Some(try!(visitor.visit_value())),
                     Field::FirstOpens => first_opens = try!(visitor.visit_value()),
                     Field::LastCloses => last_closes = try!(visitor.visit_value()),
+                    Field::Opens => opens = try!(visitor.visit_value()),
+                    Field::Closes => closes = try!(visitor.visit_value()),
                     Field::MaxSize => max_size = Some(try!(visitor.visit_value())),
+                    Field::Overflow => overflow = try!(visitor.visit_value()),
+                    Field::MinSize => min_size = Some(try!(visitor.visit_value())),
+                    Field::Ordered => ordered = try!(visitor.visit_value()),
+                    Field::OutOfOrder => out_of_order = try!(visitor.visit_value()),
+                    Field::Sliding => sliding = try!(visitor.visit_value()),
+                    Field::Suppress => suppress = try!(visitor.visit_value()),
+                    Field::OnReopen => on_reopen = try!(visitor.visit_value()),
+                    Field::Calendar => calendar = Some(try!(visitor.visit_value())),
                 }
             }
 
-            let timeout: Duration = match timeout {
-                Some(timeout) => timeout.0,
-                None => return visitor.missing_field("timeout"),
+            // the timeout is ignored by the calendar windows, so it's optional for them
+            let timeout: Duration = match (timeout, calendar) {
+                (Some(timeout), _) => timeout.0,
+                (None, Some(calendar)) => calendar.interval,
+                (None, None) => return visitor.missing_field("timeout"),
             };
 
             let renew_timeout = renew_timeout.map(|timeout| timeout.0);
 
+            // with the other overflow modes the event count keeps growing past max_size
+            if let (Some(min_size), Some(max_size), Overflow::Close) = (min_size, max_size, overflow) {
+                if min_size > max_size {
+                    return Err(V::Error::custom(format!("min_size ({}) is greater than max_size ({})", min_size, max_size)));
+                }
+            }
+
             try!(visitor.end());
 
             Ok(Conditions {
@@ -330,8 +656,94 @@ mod deser {
                 renew_timeout: renew_timeout,
                 first_opens: first_opens,
                 last_closes: last_closes,
+                opens: opens,
+                closes: closes,
                 max_size: max_size,
+                overflow: overflow,
+                min_size: min_size,
+                ordered: ordered,
+                out_of_order: out_of_order,
+                sliding: sliding,
+                suppress: suppress,
+                on_reopen: on_reopen,
+                calendar: calendar,
             })
         }
     }
+
+    impl Deserialize for OutOfOrder {
+        fn deserialize<D>(deserializer: &mut D) -> Result<OutOfOrder, D::Error>
+            where D: Deserializer
+        {
+            struct OutOfOrderVisitor;
+
+            impl Visitor for OutOfOrderVisitor {
+                type Value = OutOfOrder;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<OutOfOrder, E>
+                    where E: Error
+                {
+                    match value {
+                        "ignore" => Ok(OutOfOrder::Ignore),
+                        "reset" => Ok(OutOfOrder::Reset),
+                        "close" => Ok(OutOfOrder::Close),
+                        _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(OutOfOrderVisitor)
+        }
+    }
+
+    impl Deserialize for Overflow {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Overflow, D::Error>
+            where D: Deserializer
+        {
+            struct OverflowVisitor;
+
+            impl Visitor for OverflowVisitor {
+                type Value = Overflow;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<Overflow, E>
+                    where E: Error
+                {
+                    match value {
+                        "close" => Ok(Overflow::Close),
+                        "discard" => Ok(Overflow::Discard),
+                        "ring" => Ok(Overflow::Ring),
+                        "first_last" => Ok(Overflow::FirstLast),
+                        _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(OverflowVisitor)
+        }
+    }
+
+    impl Deserialize for Reopen {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Reopen, D::Error>
+            where D: Deserializer
+        {
+            struct ReopenVisitor;
+
+            impl Visitor for ReopenVisitor {
+                type Value = Reopen;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<Reopen, E>
+                    where E: Error
+                {
+                    match value {
+                        "append" => Ok(Reopen::Append),
+                        "close_and_reopen" => Ok(Reopen::CloseAndReopen),
+                        "ignore" => Ok(Reopen::Ignore),
+                        _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(ReopenVisitor)
+        }
+    }
 }
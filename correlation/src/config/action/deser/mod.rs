@@ -1,5 +1,6 @@
 use serde;
 use super::{ActionType, ExecCondition};
+use pattern::Pattern;
 use std::marker::PhantomData;
 
 #[cfg(test)]
@@ -11,6 +12,10 @@ impl<T> serde::de::Deserialize for ActionType<T> where T: serde::de::Deserialize
     {
         enum Field {
             Message,
+            Exec,
+            File,
+            Webhook,
+            Tag,
         }
 
         impl serde::de::Deserialize for Field {
@@ -28,6 +33,10 @@ impl<T> serde::de::Deserialize for ActionType<T> where T: serde::de::Deserialize
                     {
                         match value {
                             "message" => Ok(Field::Message),
+                            "exec" => Ok(Field::Exec),
+                            "file" => Ok(Field::File),
+                            "webhook" => Ok(Field::Webhook),
+                            "tag" => Ok(Field::Tag),
                             _ => Err(serde::de::Error::unknown_field(value)),
                         }
                     }
@@ -50,11 +59,27 @@ impl<T> serde::de::Deserialize for ActionType<T> where T: serde::de::Deserialize
                         let value = try!(visitor.visit_newtype());
                         Ok(ActionType::Message(value))
                     }
+                    Field::Exec => {
+                        let value = try!(visitor.visit_newtype());
+                        Ok(ActionType::Exec(value))
+                    }
+                    Field::File => {
+                        let value = try!(visitor.visit_newtype());
+                        Ok(ActionType::File(value))
+                    }
+                    Field::Webhook => {
+                        let value = try!(visitor.visit_newtype());
+                        Ok(ActionType::Webhook(value))
+                    }
+                    Field::Tag => {
+                        let value = try!(visitor.visit_newtype());
+                        Ok(ActionType::Tag(value))
+                    }
                 }
             }
         }
 
-        const VARIANTS: &'static [&'static str] = &["message"];
+        const VARIANTS: &'static [&'static str] = &["message", "exec", "file", "webhook", "tag"];
 
         deserializer.deserialize_enum("ActionType", VARIANTS, Visitor(PhantomData))
     }
@@ -69,6 +94,17 @@ impl serde::de::Deserialize for ExecCondition {
         enum Field {
             OnOpened,
             OnClosed,
+            OnCompleted,
+            OnTimeout,
+            OnRenewTimeout,
+            OnMaxSize,
+            OnOutOfOrder,
+            OnEvicted,
+            OnReopened,
+            OnBoundary,
+            OnThreshold,
+            OnMessage,
+            OnMessagePatterns,
         }
 
         impl serde::de::Deserialize for Field {
@@ -86,6 +122,17 @@ impl serde::de::Deserialize for ExecCondition {
                         match value {
                             "on_opened" => Ok(Field::OnOpened),
                             "on_closed" => Ok(Field::OnClosed),
+                            "on_completed" => Ok(Field::OnCompleted),
+                            "on_timeout" => Ok(Field::OnTimeout),
+                            "on_renew_timeout" => Ok(Field::OnRenewTimeout),
+                            "on_max_size" => Ok(Field::OnMaxSize),
+                            "on_out_of_order" => Ok(Field::OnOutOfOrder),
+                            "on_evicted" => Ok(Field::OnEvicted),
+                            "on_reopened" => Ok(Field::OnReopened),
+                            "on_boundary" => Ok(Field::OnBoundary),
+                            "on_threshold" => Ok(Field::OnThreshold),
+                            "on_message" => Ok(Field::OnMessage),
+                            "on_message_patterns" => Ok(Field::OnMessagePatterns),
                             _ => {
                                 Err(E::custom(format!("Unexpected field: {}",
                                                                       value)))
@@ -107,16 +154,44 @@ impl serde::de::Deserialize for ExecCondition {
                 where V: serde::de::MapVisitor
             {
                 let mut condition: ExecCondition = Default::default();
+                let mut on_closed = None;
+                let mut on_message = None;
 
                 while let Some(field) = try!(visitor.visit_key()) {
                     match field {
                         Field::OnOpened => condition.on_opened = try!(visitor.visit_value()),
-                        Field::OnClosed => condition.on_closed = try!(visitor.visit_value()),
+                        Field::OnClosed => on_closed = Some(try!(visitor.visit_value())),
+                        Field::OnCompleted => condition.on_completed = try!(visitor.visit_value()),
+                        Field::OnTimeout => condition.on_timeout = try!(visitor.visit_value()),
+                        Field::OnRenewTimeout => condition.on_renew_timeout = try!(visitor.visit_value()),
+                        Field::OnMaxSize => condition.on_max_size = try!(visitor.visit_value()),
+                        Field::OnOutOfOrder => condition.on_out_of_order = try!(visitor.visit_value()),
+                        Field::OnEvicted => condition.on_evicted = try!(visitor.visit_value()),
+                        Field::OnReopened => condition.on_reopened = try!(visitor.visit_value()),
+                        Field::OnBoundary => condition.on_boundary = try!(visitor.visit_value()),
+                        Field::OnThreshold => condition.on_threshold = try!(visitor.visit_value()),
+                        Field::OnMessage => on_message = Some(try!(visitor.visit_value())),
+                        Field::OnMessagePatterns => {
+                            let names: Vec<String> = try!(visitor.visit_value());
+                            condition.on_message_patterns = names.into_iter().map(Pattern::new).collect();
+                        }
                     }
                 }
 
                 try!(visitor.end());
 
+                // the pattern filter implies the message trigger
+                condition.on_message = on_message.unwrap_or(!condition.on_message_patterns.is_empty());
+
+                // if the action is bound to specific close reasons or to the threshold,
+                // it shouldn't be executed on every close unless it's explicitly requested
+                let has_specific_trigger = condition.on_completed || condition.on_timeout ||
+                                           condition.on_renew_timeout || condition.on_max_size ||
+                                           condition.on_out_of_order || condition.on_evicted ||
+                                           condition.on_reopened || condition.on_boundary ||
+                                           condition.on_threshold || condition.on_message;
+                condition.on_closed = on_closed.unwrap_or(!has_specific_trigger);
+
                 Ok(condition)
             }
         }
@@ -8,6 +8,10 @@
 
 use serde_json::from_str;
 use config::action::{ActionType, ExecCondition};
+use config::action::file::Fsync;
+use conditions::CloseReason;
+use pattern::Pattern;
+use std::time::Duration;
 
 #[test]
 fn test_given_action_when_it_is_deserialized_then_we_get_the_right_result() {
@@ -26,6 +30,7 @@ fn test_given_action_when_it_is_deserialized_then_we_get_the_right_result() {
         ActionType::Message(message) => {
             assert_eq!("uuid1", message.uuid());
         }
+        _ => panic!("Deserialized a message action into an other action type"),
     }
 }
 
@@ -49,6 +54,7 @@ fn test_given_filled_exec_condition_when_it_is_deserialized_then_it_is_populated
     let expected = ExecCondition {
         on_closed: false,
         on_opened: true,
+        ..ExecCondition::default()
     };
     let result = from_str::<ExecCondition>(text);
     println!("{:?}", &result);
@@ -78,3 +84,171 @@ fn test_given_filled_exec_condition_when_it_is_deserialized_then_its_missing_fie
     let cond = result.expect("Failed to deserialize a valid ExecCondition");
     assert_eq!(expected, cond);
 }
+
+#[test]
+fn test_given_exec_condition_when_only_a_close_reason_is_set_then_it_is_not_executed_on_every_close() {
+    let text = r#"
+        {
+            "on_timeout": true,
+            "on_renew_timeout": true
+        }
+    "#;
+
+    let expected = ExecCondition {
+        on_closed: false,
+        on_timeout: true,
+        on_renew_timeout: true,
+        ..ExecCondition::default()
+    };
+    let result = from_str::<ExecCondition>(text);
+    let cond = result.expect("Failed to deserialize a valid ExecCondition");
+    assert_eq!(expected, cond);
+    assert_eq!(true, cond.is_closing_trigger(CloseReason::Timeout));
+    assert_eq!(false, cond.is_closing_trigger(CloseReason::Completed));
+}
+
+#[test]
+fn test_given_exec_condition_when_only_message_patterns_are_set_then_it_is_executed_on_those_messages() {
+    let text = r#"
+        {
+            "on_message_patterns": ["login", "logout"]
+        }
+    "#;
+
+    let expected = ExecCondition {
+        on_closed: false,
+        on_message: true,
+        on_message_patterns: vec![Pattern::new("login"), Pattern::new("logout")],
+        ..ExecCondition::default()
+    };
+    let result = from_str::<ExecCondition>(text);
+    let cond = result.expect("Failed to deserialize a valid ExecCondition");
+    assert_eq!(expected, cond);
+}
+
+#[test]
+fn test_given_exec_condition_when_on_closed_and_a_close_reason_are_set_then_on_closed_is_kept() {
+    let text = r#"
+        {
+            "on_closed": true,
+            "on_completed": true
+        }
+    "#;
+
+    let result = from_str::<ExecCondition>(text);
+    let cond = result.expect("Failed to deserialize a valid ExecCondition");
+    assert_eq!(true, cond.on_closed);
+    assert_eq!(true, cond.is_closing_trigger(CloseReason::MaxSize));
+}
+
+#[test]
+fn test_given_exec_action_when_it_is_deserialized_then_we_get_the_right_result() {
+    let text = r#"
+        {
+            "exec": {
+                "uuid": "uuid1",
+                "program": "/usr/local/bin/block-ip",
+                "args": ["--ip", "${SRC_IP}"],
+                "env": { "REASON": "${.classifier.rule_id}" },
+                "timeout": 5000,
+                "max_concurrency": 2
+            }
+        }
+    "#;
+
+    let result = from_str::<ActionType<String>>(text);
+    let action = result.expect("Failed to deserialize a valid ActionType");
+    match action {
+        ActionType::Exec(exec) => {
+            assert_eq!("uuid1", exec.uuid());
+            assert_eq!("/usr/local/bin/block-ip", exec.program());
+            assert_eq!(&["--ip".to_owned(), "${SRC_IP}".to_owned()], exec.args());
+            assert_eq!(Some(&"${.classifier.rule_id}".to_owned()), exec.env().get("REASON"));
+            assert_eq!(Duration::from_millis(5000), exec.timeout);
+            assert_eq!(2, exec.max_concurrency);
+        }
+        _ => panic!("Deserialized an exec action into an other action type"),
+    }
+    let _ = from_str::<ActionType<String>>(r#"{ "exec": { "uuid": "uuid1" } }"#).err().expect("Successfully deserialized an exec action without program");
+}
+
+#[test]
+fn test_given_file_action_when_it_is_deserialized_then_we_get_the_right_result() {
+    let text = r#"
+        {
+            "file": {
+                "uuid": "uuid1",
+                "path": "/var/log/alerts.json",
+                "message": "${context_len} logins",
+                "fsync": "timer"
+            }
+        }
+    "#;
+
+    let result = from_str::<ActionType<String>>(text);
+    let action = result.expect("Failed to deserialize a valid ActionType");
+    match action {
+        ActionType::File(file) => {
+            assert_eq!("uuid1", file.uuid());
+            assert_eq!("/var/log/alerts.json", file.path());
+            assert_eq!(Some("${context_len} logins".to_owned()), file.message);
+            assert_eq!(None, file.template);
+            assert_eq!(Fsync::Timer, file.fsync);
+        }
+        _ => panic!("Deserialized a file action into an other action type"),
+    }
+    let _ = from_str::<ActionType<String>>(r#"{ "file": { "uuid": "uuid1" } }"#).err().expect("Successfully deserialized a file action without path");
+    let _ = from_str::<ActionType<String>>(r#"{ "file": { "uuid": "uuid1", "path": "a", "fsync": "sometimes" } }"#).err().expect("Successfully deserialized an invalid fsync");
+}
+
+#[test]
+fn test_given_webhook_action_when_it_is_deserialized_then_we_get_the_right_result() {
+    let text = r#"
+        {
+            "webhook": {
+                "uuid": "uuid1",
+                "url": "http://localhost:8080/alerts",
+                "message": "${context_len} logins",
+                "retries": 0
+            }
+        }
+    "#;
+
+    let result = from_str::<ActionType<String>>(text);
+    let action = result.expect("Failed to deserialize a valid ActionType");
+    match action {
+        ActionType::Webhook(webhook) => {
+            assert_eq!("uuid1", webhook.uuid());
+            assert_eq!("http://localhost:8080/alerts", webhook.url().to_string());
+            assert_eq!(Some("${context_len} logins".to_owned()), webhook.message);
+            assert_eq!(0, webhook.endpoint.retries);
+        }
+        _ => panic!("Deserialized a webhook action into an other action type"),
+    }
+    let _ = from_str::<ActionType<String>>(r#"{ "webhook": { "uuid": "uuid1" } }"#).err().expect("Successfully deserialized a webhook action without url");
+}
+
+#[test]
+fn test_given_tag_action_when_it_is_deserialized_then_we_get_the_right_result() {
+    let text = r#"
+        {
+            "tag": {
+                "uuid": "uuid1",
+                "values": {
+                    "user": "${USER}"
+                }
+            }
+        }
+    "#;
+
+    let result = from_str::<ActionType<String>>(text);
+    let action = result.expect("Failed to deserialize a valid ActionType");
+    match action {
+        ActionType::Tag(tag) => {
+            assert_eq!("uuid1", tag.uuid());
+            assert_eq!(Some(&"${USER}".to_owned()), tag.values().get("user"));
+        }
+        _ => panic!("Deserialized a tag action into an other action type"),
+    }
+    let _ = from_str::<ActionType<String>>(r#"{ "tag": { "values": {} } }"#).err().expect("Successfully deserialized a tag action without uuid");
+}
@@ -0,0 +1,98 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::{ExecAction, MAX_CONCURRENCY_DEFAULT, TIMEOUT_DEFAULT};
+use super::runner::Runner;
+use config::action::ExecCondition;
+use config::action::message::InjectMode;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub struct ExecActionBuilder<T> {
+    uuid: String,
+    name: Option<String>,
+    program: String,
+    args: Vec<T>,
+    env: BTreeMap<String, T>,
+    timeout: Duration,
+    max_concurrency: usize,
+    when: ExecCondition,
+    inject_mode: InjectMode,
+}
+
+impl<T> ExecActionBuilder<T> {
+    pub fn new<U: Into<String>, P: Into<String>>(uuid: U, program: P) -> ExecActionBuilder<T> {
+        ExecActionBuilder {
+            uuid: uuid.into(),
+            name: None,
+            program: program.into(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            timeout: Duration::from_millis(TIMEOUT_DEFAULT),
+            max_concurrency: MAX_CONCURRENCY_DEFAULT,
+            when: ExecCondition::default(),
+            inject_mode: InjectMode::default(),
+        }
+    }
+
+    pub fn name<S: Into<String>>(mut self, name: Option<S>) -> ExecActionBuilder<T> {
+        self.name = name.map(|name| name.into());
+        self
+    }
+
+    pub fn args(mut self, args: Vec<T>) -> ExecActionBuilder<T> {
+        self.args = args;
+        self
+    }
+
+    pub fn arg<A: Into<T>>(mut self, arg: A) -> ExecActionBuilder<T> {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn env(mut self, env: BTreeMap<String, T>) -> ExecActionBuilder<T> {
+        self.env = env;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> ExecActionBuilder<T> {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> ExecActionBuilder<T> {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    pub fn when(mut self, when: ExecCondition) -> ExecActionBuilder<T> {
+        self.when = when;
+        self
+    }
+
+    pub fn inject_mode(mut self, mode: InjectMode) -> ExecActionBuilder<T> {
+        self.inject_mode = mode;
+        self
+    }
+
+    pub fn build(self) -> ExecAction<T> {
+        ExecAction {
+            uuid: self.uuid,
+            name: self.name,
+            program: self.program,
+            args: self.args,
+            env: self.env,
+            timeout: self.timeout,
+            max_concurrency: self.max_concurrency,
+            when: self.when,
+            inject_mode: self.inject_mode,
+            runner: Runner::default(),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::{ExecAction, ExecActionBuilder};
+use config::action::ExecCondition;
+use duration::SerializableDuration;
+
+use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+impl<T> Deserialize for ExecAction<T> where T: Deserialize {
+    fn deserialize<D>(deserializer: &mut D) -> Result<ExecAction<T>, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize_struct("ExecAction", &[], ExecActionVisitor(PhantomData))
+    }
+}
+
+enum Field {
+    Uuid,
+    Name,
+    Program,
+    Args,
+    Env,
+    Timeout,
+    MaxConcurrency,
+    When,
+    InjectMode,
+}
+
+impl Deserialize for Field {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+        where D: Deserializer
+    {
+        struct FieldVisitor;
+
+        impl Visitor for FieldVisitor {
+            type Value = Field;
+
+            fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                where E: Error
+            {
+                match value {
+                    "uuid" => Ok(Field::Uuid),
+                    "name" => Ok(Field::Name),
+                    "program" => Ok(Field::Program),
+                    "args" => Ok(Field::Args),
+                    "env" => Ok(Field::Env),
+                    "timeout" => Ok(Field::Timeout),
+                    "max_concurrency" => Ok(Field::MaxConcurrency),
+                    "when" => Ok(Field::When),
+                    "inject_mode" => Ok(Field::InjectMode),
+                    _ => Err(Error::custom(format!("Unexpected field: {}", value))),
+                }
+            }
+        }
+
+        deserializer.deserialize(FieldVisitor)
+    }
+}
+
+struct ExecActionVisitor<T>(PhantomData<T>);
+
+impl<T> Visitor for ExecActionVisitor<T> where T: Deserialize {
+    type Value = ExecAction<T>;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> Result<ExecAction<T>, V::Error>
+        where V: MapVisitor
+    {
+        let mut uuid: Option<String> = None;
+        let mut name: Option<String> = None;
+        let mut program: Option<String> = None;
+        let mut args: Vec<T> = Vec::new();
+        let mut env: BTreeMap<String, T> = BTreeMap::new();
+        let mut timeout: Option<SerializableDuration> = None;
+        let mut max_concurrency: Option<usize> = None;
+        let mut when: ExecCondition = ExecCondition::new();
+        let mut inject_mode = Default::default();
+
+        while let Some(field) = try!(visitor.visit_key()) {
+            match field {
+                Field::Uuid => uuid = Some(try!(visitor.visit_value())),
+                Field::Name => name = Some(try!(visitor.visit_value())),
+                Field::Program => program = Some(try!(visitor.visit_value())),
+                Field::Args => args = try!(visitor.visit_value()),
+                Field::Env => env = try!(visitor.visit_value()),
+                Field::Timeout => timeout = Some(try!(visitor.visit_value())),
+                Field::MaxConcurrency => max_concurrency = Some(try!(visitor.visit_value())),
+                Field::When => when = try!(visitor.visit_value()),
+                Field::InjectMode => inject_mode = try!(visitor.visit_value()),
+            }
+        }
+
+        let uuid = match uuid {
+            Some(uuid) => uuid,
+            None => return visitor.missing_field("uuid"),
+        };
+
+        let program = match program {
+            Some(program) => program,
+            None => {
+                error!("Missing 'program' field: uuid={}", &uuid);
+                return visitor.missing_field("program");
+            }
+        };
+
+        try!(visitor.end());
+
+        let mut builder = ExecActionBuilder::new(uuid, program)
+                              .name(name)
+                              .args(args)
+                              .env(env)
+                              .when(when)
+                              .inject_mode(inject_mode);
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout.0);
+        }
+        if let Some(max_concurrency) = max_concurrency {
+            builder = builder.max_concurrency(max_concurrency);
+        }
+        Ok(builder.build())
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use action::Action;
+use conditions::CloseReason;
+use config::action::message::{InjectMode, LoopbackChain, CONTEXT_UUID};
+use context::base::BaseContext;
+use state::State;
+use Alert;
+use Event;
+use Template;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use super::ExecCondition;
+
+pub use self::builder::ExecActionBuilder;
+use self::runner::{Command, ExecResult, ExecStatus, Runner};
+
+mod builder;
+mod deser;
+mod runner;
+#[cfg(test)]
+mod test;
+
+pub const PROGRAM: &'static str = "program";
+pub const EXEC_STATUS: &'static str = "exec_status";
+pub const EXIT_CODE: &'static str = "exit_code";
+pub const EXEC_ERROR: &'static str = "exec_error";
+
+pub const TIMEOUT_DEFAULT: u64 = 30000;
+pub const MAX_CONCURRENCY_DEFAULT: usize = 4;
+
+/// Runs a local program with arguments and environment variables rendered
+/// from the templates. The program runs on a separate thread, its exit status
+/// is reported by an alert on a later timer event.
+pub struct ExecAction<T> {
+    /// The uuid of the alerts which report the exit statuses.
+    pub uuid: String,
+    pub name: Option<String>,
+    /// The path of the program, it isn't a template, so the events can't choose what is executed.
+    pub program: String,
+    pub args: Vec<T>,
+    pub env: BTreeMap<String, T>,
+    pub timeout: Duration,
+    pub max_concurrency: usize,
+    pub when: ExecCondition,
+    pub inject_mode: InjectMode,
+    runner: Runner,
+}
+
+impl<T> ExecAction<T> {
+    pub fn uuid(&self) -> &String {
+        &self.uuid
+    }
+    pub fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+    pub fn program(&self) -> &String {
+        &self.program
+    }
+    pub fn args(&self) -> &[T] {
+        &self.args
+    }
+    pub fn env(&self) -> &BTreeMap<String, T> {
+        &self.env
+    }
+    pub fn when(&self) -> &ExecCondition {
+        &self.when
+    }
+    /// The number of programs which are still running.
+    pub fn running(&self) -> usize {
+        self.runner.running()
+    }
+
+    fn execute<E>(&self, state: &State<E>, context: &BaseContext<E, T>, reason: Option<CloseReason>, event: Option<&E>, responder: &mut VecDeque<Alert<E>>) where E: Event, T: Template<Event=E> {
+        let template_context = context.template_context(state, reason, event);
+
+        let mut command = Command::new(&self.program, template_context.context_id, self.timeout);
+        if self.inject_mode == InjectMode::Loopback {
+            command.loopback = Some(LoopbackChain::new(state, event, template_context.context_id));
+        }
+        let mut value = Vec::new();
+        for arg in &self.args {
+            arg.format(&template_context, &mut value);
+            command.args.push(String::from_utf8_lossy(&value).into_owned());
+            value.clear();
+        }
+        for (name, template) in &self.env {
+            template.format(&template_context, &mut value);
+            command.env.push((name.clone(), String::from_utf8_lossy(&value).into_owned()));
+            value.clear();
+        }
+
+        if let Err(result) = self.runner.spawn(command, self.max_concurrency) {
+            warn!("ExecAction: failed to execute the program; program={} status={}", &self.program, result.status.as_str());
+            responder.push_back(self.report(result));
+        }
+    }
+
+    fn report<E: Event>(&self, result: ExecResult) -> Alert<E> {
+        let message = format!("{} {}", &self.program, result.status.as_str());
+        let mut event = E::new(self.uuid.as_bytes(), message.as_bytes());
+        event.set_name(self.name.as_ref().map(|name| name.as_bytes()));
+        event.set(PROGRAM.as_bytes(), self.program.as_bytes());
+        event.set(EXEC_STATUS.as_bytes(), result.status.as_str().as_bytes());
+        event.set(CONTEXT_UUID.as_bytes(), result.context_uuid.as_bytes());
+        if let Some(exit_code) = result.exit_code {
+            event.set(EXIT_CODE.as_bytes(), exit_code.to_string().as_bytes());
+        }
+        if let Some(ref error) = result.error {
+            event.set(EXEC_ERROR.as_bytes(), error.as_bytes());
+        }
+        if let Some(ref loopback) = result.loopback {
+            loopback.set(&mut event);
+        }
+        Alert {
+            message: event,
+            inject_mode: self.inject_mode.clone(),
+            close_reason: None,
+        }
+    }
+}
+
+impl<T> From<ExecAction<T>> for super::ActionType<T> {
+    fn from(action: ExecAction<T>) -> super::ActionType<T> {
+        super::ActionType::Exec(action)
+    }
+}
+
+impl<E, T> Action<E, T> for ExecAction<T> where E: Event, T: Template<Event=E> {
+    fn on_opened(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        if self.when.on_opened {
+            trace!("ExecAction: on_opened()");
+            self.execute(state, context, None, None, responder);
+        }
+    }
+
+    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, reason: CloseReason, responder: &mut VecDeque<Alert<E>>) {
+        if self.when.is_closing_trigger(reason) {
+            trace!("ExecAction: on_closed(); reason={}", reason.as_str());
+            self.execute(state, context, Some(reason), None, responder);
+        }
+    }
+
+    fn on_threshold(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        if self.when.on_threshold {
+            trace!("ExecAction: on_threshold()");
+            self.execute(state, context, None, None, responder);
+        }
+    }
+
+    fn on_message(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        if self.when.is_message_trigger(event) {
+            trace!("ExecAction: on_message()");
+            self.execute(state, context, None, Some(event), responder);
+        }
+    }
+
+    fn on_timer(&self, _: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        for result in self.runner.finished() {
+            if result.status != ExecStatus::Success {
+                warn!("ExecAction: the program didn't succeed; program={} status={}", &self.program, result.status.as_str());
+            }
+            responder.push_back(self.report(result));
+        }
+    }
+}
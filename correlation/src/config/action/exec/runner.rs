@@ -0,0 +1,193 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use config::action::message::LoopbackChain;
+
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL_MS: u64 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecStatus {
+    /// The program exited with zero.
+    Success,
+    /// The program exited with a non-zero code or it was killed by a signal.
+    Failure,
+    /// The program was killed because it didn't exit in time.
+    Timeout,
+    /// The program couldn't be started.
+    Error,
+    /// The program wasn't started because too many programs were running.
+    Rejected,
+}
+
+impl ExecStatus {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ExecStatus::Success => "success",
+            ExecStatus::Failure => "failure",
+            ExecStatus::Timeout => "timeout",
+            ExecStatus::Error => "error",
+            ExecStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// A rendered command line.
+#[derive(Debug)]
+pub struct Command {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub timeout: Duration,
+    pub context_uuid: String,
+    /// Set if the result is sent back to the correlator.
+    pub loopback: Option<LoopbackChain>,
+}
+
+impl Command {
+    pub fn new(program: &str, context_uuid: &str, timeout: Duration) -> Command {
+        Command {
+            program: program.to_owned(),
+            args: Vec::new(),
+            env: Vec::new(),
+            timeout: timeout,
+            context_uuid: context_uuid.to_owned(),
+            loopback: None,
+        }
+    }
+
+    fn run(self) -> ExecResult {
+        let mut command = process::Command::new(&self.program);
+        command.args(&self.args)
+               .stdin(process::Stdio::null())
+               .stdout(process::Stdio::null())
+               .stderr(process::Stdio::null());
+        for &(ref name, ref value) in &self.env {
+            command.env(name, value);
+        }
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(error) => return ExecResult::new(self.context_uuid, self.loopback, ExecStatus::Error).error(error.to_string()),
+        };
+
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    let result = if status.success() {
+                        ExecResult::new(self.context_uuid, self.loopback, ExecStatus::Success)
+                    } else {
+                        ExecResult::new(self.context_uuid, self.loopback, ExecStatus::Failure)
+                    };
+                    return result.exit_code(status.code());
+                }
+                Ok(None) if started.elapsed() >= self.timeout => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return ExecResult::new(self.context_uuid, self.loopback, ExecStatus::Timeout);
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(POLL_INTERVAL_MS)),
+                Err(error) => return ExecResult::new(self.context_uuid, self.loopback, ExecStatus::Error).error(error.to_string()),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ExecResult {
+    pub status: ExecStatus,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub context_uuid: String,
+    pub loopback: Option<LoopbackChain>,
+}
+
+impl ExecResult {
+    fn new(context_uuid: String, loopback: Option<LoopbackChain>, status: ExecStatus) -> ExecResult {
+        ExecResult {
+            status: status,
+            exit_code: None,
+            error: None,
+            context_uuid: context_uuid,
+            loopback: loopback,
+        }
+    }
+
+    fn exit_code(mut self, exit_code: Option<i32>) -> ExecResult {
+        self.exit_code = exit_code;
+        self
+    }
+
+    fn error(mut self, error: String) -> ExecResult {
+        self.error = Some(error);
+        self
+    }
+}
+
+/// Runs the commands on their own threads and collects their results.
+pub struct Runner {
+    running: Arc<AtomicUsize>,
+    sender: Sender<ExecResult>,
+    receiver: Receiver<ExecResult>,
+}
+
+impl Default for Runner {
+    fn default() -> Runner {
+        let (sender, receiver) = channel();
+        Runner {
+            running: Arc::new(AtomicUsize::new(0)),
+            sender: sender,
+            receiver: receiver,
+        }
+    }
+}
+
+impl Runner {
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Starts `command` without waiting for it. The result is returned
+    /// immediately if it can't be started.
+    pub fn spawn(&self, command: Command, max_concurrency: usize) -> Result<(), ExecResult> {
+        if self.running.fetch_add(1, Ordering::SeqCst) >= max_concurrency {
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            return Err(ExecResult::new(command.context_uuid, command.loopback, ExecStatus::Rejected));
+        }
+
+        let running = self.running.clone();
+        let sender = self.sender.clone();
+        let context_uuid = command.context_uuid.clone();
+        let loopback = command.loopback.clone();
+        let spawned = thread::Builder::new().name("exec-action".to_owned()).spawn(move || {
+            let result = command.run();
+            let _ = sender.send(result);
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+
+        spawned.map(|_| ()).map_err(|error| {
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            ExecResult::new(context_uuid, loopback, ExecStatus::Error).error(error.to_string())
+        })
+    }
+
+    /// Returns the results of the finished commands.
+    pub fn finished(&self) -> Vec<ExecResult> {
+        let mut results = Vec::new();
+        while let Ok(result) = self.receiver.try_recv() {
+            results.push(result);
+        }
+        results
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use config::action::exec::{ExecAction, ExecActionBuilder, EXEC_STATUS, EXIT_CODE};
+use config::action::message::{InjectMode, LOOPBACK_CHAIN, LOOPBACK_DEPTH};
+use conditions::CloseReason;
+use message::MessageBuilder;
+use state::State;
+use action::Action;
+
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Duration;
+use Alert;
+use Event;
+use Message;
+use test_utils::{MockTemplate, base_context, close};
+
+fn wait_for_results(action: &ExecAction<MockTemplate>, responder: &mut VecDeque<Alert<Message>>) {
+    for _ in 0..500 {
+        Action::<Message, MockTemplate>::on_timer(action, &Duration::from_millis(10), responder);
+        if action.running() == 0 && !responder.is_empty() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_given_exec_action_when_the_program_exits_then_its_exit_status_is_reported() {
+    let mut responder = VecDeque::default();
+    let action = ExecActionBuilder::<MockTemplate>::new("uuid", "sh")
+                     .arg(MockTemplate::literal(b"-c"))
+                     .arg(MockTemplate::literal(b"exit 3"))
+                     .build();
+
+    responder.extend(close(&action));
+    wait_for_results(&action, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"failure"[..]), responder[0].message.get(EXEC_STATUS.as_bytes()));
+    assert_eq!(Some(&b"3"[..]), responder[0].message.get(EXIT_CODE.as_bytes()));
+}
+
+#[test]
+fn test_given_exec_action_when_it_is_executed_then_the_arguments_and_the_environment_are_rendered() {
+    let mut responder = VecDeque::default();
+    let env = btreemap!{
+        "CONTEXT_ID".to_owned() => MockTemplate::context_id(),
+    };
+    let action = ExecActionBuilder::<MockTemplate>::new("uuid", "sh")
+                     .arg(MockTemplate::literal(b"-c"))
+                     .arg(MockTemplate::literal(br#"test "$1" = "$CONTEXT_ID""#))
+                     .arg(MockTemplate::literal(b"sh"))
+                     .arg(MockTemplate::context_id())
+                     .env(env)
+                     .build();
+
+    responder.extend(close(&action));
+    wait_for_results(&action, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"success"[..]), responder[0].message.get(EXEC_STATUS.as_bytes()));
+}
+
+#[test]
+fn test_given_exec_action_when_the_program_runs_too_long_then_it_is_killed() {
+    let mut responder = VecDeque::default();
+    let action = ExecActionBuilder::<MockTemplate>::new("uuid", "sleep")
+                     .arg(MockTemplate::literal(b"10"))
+                     .timeout(Duration::from_millis(50))
+                     .build();
+
+    responder.extend(close(&action));
+    assert_eq!(0, responder.len());
+    wait_for_results(&action, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"timeout"[..]), responder[0].message.get(EXEC_STATUS.as_bytes()));
+}
+
+#[test]
+fn test_given_exec_action_when_too_many_programs_are_running_then_the_execution_is_rejected() {
+    let mut responder = VecDeque::default();
+    let action = ExecActionBuilder::<MockTemplate>::new("uuid", "sleep")
+                     .arg(MockTemplate::literal(b"10"))
+                     .timeout(Duration::from_millis(100))
+                     .max_concurrency(1)
+                     .build();
+
+    responder.extend(close(&action));
+    responder.extend(close(&action));
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"rejected"[..]), responder[0].message.get(EXEC_STATUS.as_bytes()));
+    responder.clear();
+    wait_for_results(&action, &mut responder);
+    assert_eq!(Some(&b"timeout"[..]), responder[0].message.get(EXEC_STATUS.as_bytes()));
+}
+
+#[test]
+fn test_given_exec_action_when_the_program_does_not_exist_then_an_error_is_reported() {
+    let mut responder = VecDeque::default();
+    let action = ExecActionBuilder::<MockTemplate>::new("uuid", "/nonexistent/program").build();
+
+    responder.extend(close(&action));
+    wait_for_results(&action, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"error"[..]), responder[0].message.get(EXEC_STATUS.as_bytes()));
+}
+
+#[test]
+fn test_given_exec_action_with_loopback_inject_mode_when_the_program_exits_then_the_report_carries_the_loopback_depth() {
+    let mut responder = VecDeque::default();
+    let action = ExecActionBuilder::<MockTemplate>::new("uuid", "true")
+                     .inject_mode(InjectMode::Loopback)
+                     .build();
+    let state = State::with_messages(vec![MessageBuilder::new("uuid1", "message1")
+                                              .pair(LOOPBACK_DEPTH.as_bytes(), b"2")
+                                              .pair(LOOPBACK_CHAIN.as_bytes(), b"a,b")
+                                              .build()]);
+    let base_context = base_context();
+
+    action.on_closed(&state, &base_context, CloseReason::Timeout, &mut responder);
+    wait_for_results(&action, &mut responder);
+    assert_eq!(1, responder.len());
+    let chain = format!("a,b,{}", base_context.uuid().to_hyphenated_string());
+    assert_eq!(Some(&b"3"[..]), responder[0].message.get(LOOPBACK_DEPTH.as_bytes()));
+    assert_eq!(Some(chain.as_bytes()), responder[0].message.get(LOOPBACK_CHAIN.as_bytes()));
+}
@@ -0,0 +1,89 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::{FileAction, Fsync};
+use super::writer::Writer;
+use config::action::ExecCondition;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+pub struct FileActionBuilder<T> {
+    uuid: String,
+    name: Option<String>,
+    path: String,
+    message: Option<T>,
+    values: BTreeMap<String, T>,
+    template: Option<T>,
+    fsync: Fsync,
+    when: ExecCondition,
+}
+
+impl<T> FileActionBuilder<T> {
+    pub fn new<U: Into<String>, P: Into<String>>(uuid: U, path: P) -> FileActionBuilder<T> {
+        FileActionBuilder {
+            uuid: uuid.into(),
+            name: None,
+            path: path.into(),
+            message: None,
+            values: BTreeMap::new(),
+            template: None,
+            fsync: Fsync::default(),
+            when: ExecCondition::default(),
+        }
+    }
+
+    pub fn name<S: Into<String>>(mut self, name: Option<S>) -> FileActionBuilder<T> {
+        self.name = name.map(|name| name.into());
+        self
+    }
+
+    pub fn message<M: Into<T>>(mut self, message: M) -> FileActionBuilder<T> {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn values(mut self, values: BTreeMap<String, T>) -> FileActionBuilder<T> {
+        self.values = values;
+        self
+    }
+
+    pub fn pair<K: Into<String>, V: Into<T>>(mut self, key: K, value: V) -> FileActionBuilder<T> {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn template(mut self, template: Option<T>) -> FileActionBuilder<T> {
+        self.template = template;
+        self
+    }
+
+    pub fn fsync(mut self, fsync: Fsync) -> FileActionBuilder<T> {
+        self.fsync = fsync;
+        self
+    }
+
+    pub fn when(mut self, when: ExecCondition) -> FileActionBuilder<T> {
+        self.when = when;
+        self
+    }
+
+    pub fn build(self) -> FileAction<T> {
+        FileAction {
+            writer: RefCell::new(Writer::new(self.path.clone())),
+            uuid: self.uuid,
+            name: self.name,
+            path: self.path,
+            message: self.message,
+            values: self.values,
+            template: self.template,
+            fsync: self.fsync,
+            when: self.when,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::{FileAction, FileActionBuilder, Fsync};
+use config::action::ExecCondition;
+
+use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+impl<T> Deserialize for FileAction<T> where T: Deserialize {
+    fn deserialize<D>(deserializer: &mut D) -> Result<FileAction<T>, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize_struct("FileAction", &[], FileActionVisitor(PhantomData))
+    }
+}
+
+enum Field {
+    Uuid,
+    Name,
+    Path,
+    Message,
+    Values,
+    Template,
+    Fsync,
+    When,
+}
+
+impl Deserialize for Field {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+        where D: Deserializer
+    {
+        struct FieldVisitor;
+
+        impl Visitor for FieldVisitor {
+            type Value = Field;
+
+            fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                where E: Error
+            {
+                match value {
+                    "uuid" => Ok(Field::Uuid),
+                    "name" => Ok(Field::Name),
+                    "path" => Ok(Field::Path),
+                    "message" => Ok(Field::Message),
+                    "values" => Ok(Field::Values),
+                    "template" => Ok(Field::Template),
+                    "fsync" => Ok(Field::Fsync),
+                    "when" => Ok(Field::When),
+                    _ => Err(Error::custom(format!("Unexpected field: {}", value))),
+                }
+            }
+        }
+
+        deserializer.deserialize(FieldVisitor)
+    }
+}
+
+struct FileActionVisitor<T>(PhantomData<T>);
+
+impl<T> Visitor for FileActionVisitor<T> where T: Deserialize {
+    type Value = FileAction<T>;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> Result<FileAction<T>, V::Error>
+        where V: MapVisitor
+    {
+        let mut uuid: Option<String> = None;
+        let mut name: Option<String> = None;
+        let mut path: Option<String> = None;
+        let mut message: Option<T> = None;
+        let mut values: BTreeMap<String, T> = BTreeMap::new();
+        let mut template: Option<T> = None;
+        let mut fsync = Fsync::default();
+        let mut when: ExecCondition = ExecCondition::new();
+
+        while let Some(field) = try!(visitor.visit_key()) {
+            match field {
+                Field::Uuid => uuid = Some(try!(visitor.visit_value())),
+                Field::Name => name = Some(try!(visitor.visit_value())),
+                Field::Path => path = Some(try!(visitor.visit_value())),
+                Field::Message => message = Some(try!(visitor.visit_value())),
+                Field::Values => values = try!(visitor.visit_value()),
+                Field::Template => template = Some(try!(visitor.visit_value())),
+                Field::Fsync => fsync = try!(visitor.visit_value()),
+                Field::When => when = try!(visitor.visit_value()),
+            }
+        }
+
+        let uuid = match uuid {
+            Some(uuid) => uuid,
+            None => return visitor.missing_field("uuid"),
+        };
+
+        let path = match path {
+            Some(path) => path,
+            None => {
+                error!("Missing 'path' field: uuid={}", &uuid);
+                return visitor.missing_field("path");
+            }
+        };
+
+        try!(visitor.end());
+
+        let mut builder = FileActionBuilder::new(uuid, path)
+                              .name(name)
+                              .values(values)
+                              .template(template)
+                              .fsync(fsync)
+                              .when(when);
+        if let Some(message) = message {
+            builder = builder.message(message);
+        }
+        Ok(builder.build())
+    }
+}
+
+impl Deserialize for Fsync {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Fsync, D::Error>
+        where D: Deserializer
+    {
+        struct FsyncVisitor;
+
+        impl Visitor for FsyncVisitor {
+            type Value = Fsync;
+
+            fn visit_str<E>(&mut self, value: &str) -> Result<Fsync, E>
+                where E: Error
+            {
+                match value {
+                    "never" => Ok(Fsync::Never),
+                    "always" => Ok(Fsync::Always),
+                    "timer" => Ok(Fsync::Timer),
+                    _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                }
+            }
+        }
+
+        deserializer.deserialize(FsyncVisitor)
+    }
+}
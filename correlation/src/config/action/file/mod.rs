@@ -0,0 +1,158 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use action::Action;
+use conditions::CloseReason;
+use config::action::json::JsonAlert;
+use context::base::BaseContext;
+use state::State;
+use Alert;
+use Event;
+use Template;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use super::ExecCondition;
+
+pub use self::builder::FileActionBuilder;
+pub use self::writer::reopen_files;
+use self::writer::Writer;
+
+mod builder;
+mod deser;
+mod writer;
+#[cfg(test)]
+mod test;
+
+/// Defines when the written alerts are synchronized to the disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fsync {
+    /// The operating system decides.
+    Never,
+    /// After every alert.
+    Always,
+    /// On every timer event.
+    Timer,
+}
+
+impl Default for Fsync {
+    fn default() -> Fsync {
+        Fsync::Never
+    }
+}
+
+/// Appends the alerts to a file as JSON objects or as lines rendered by a template.
+pub struct FileAction<T> {
+    pub uuid: String,
+    pub name: Option<String>,
+    pub path: String,
+    pub message: Option<T>,
+    pub values: BTreeMap<String, T>,
+    /// Renders the lines instead of the JSON objects if it's set.
+    pub template: Option<T>,
+    pub fsync: Fsync,
+    pub when: ExecCondition,
+    writer: RefCell<Writer>,
+}
+
+impl<T> FileAction<T> {
+    pub fn uuid(&self) -> &String {
+        &self.uuid
+    }
+    pub fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+    pub fn when(&self) -> &ExecCondition {
+        &self.when
+    }
+
+    fn execute<E>(&self, state: &State<E>, context: &BaseContext<E, T>, reason: Option<CloseReason>, event: Option<&E>) where E: Event, T: Template<Event=E> {
+        let template_context = context.template_context(state, reason, event);
+
+        let line = match self.template {
+            Some(ref template) => {
+                let mut line = Vec::new();
+                template.format(&template_context, &mut line);
+                line
+            }
+            None => {
+                let alert = JsonAlert {
+                    uuid: &self.uuid,
+                    name: self.name.as_ref(),
+                    message: self.message.as_ref(),
+                    values: &self.values,
+                };
+                alert.render(&template_context, context)
+            }
+        };
+
+        let mut writer = self.writer.borrow_mut();
+        writer.check_reopen();
+        let mut result = writer.write_line(line);
+        if self.fsync == Fsync::Always {
+            result = result.and_then(|_| writer.sync());
+        }
+        if let Err(error) = result {
+            error!("FileAction: failed to write alert; path={} error={}", &self.path, error);
+            writer.close();
+        }
+    }
+}
+
+impl<T> From<FileAction<T>> for super::ActionType<T> {
+    fn from(action: FileAction<T>) -> super::ActionType<T> {
+        super::ActionType::File(action)
+    }
+}
+
+impl<E, T> Action<E, T> for FileAction<T> where E: Event, T: Template<Event=E> {
+    fn on_opened(&self, state: &State<E>, context: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {
+        if self.when.on_opened {
+            trace!("FileAction: on_opened()");
+            self.execute(state, context, None, None);
+        }
+    }
+
+    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, reason: CloseReason, _: &mut VecDeque<Alert<E>>) {
+        if self.when.is_closing_trigger(reason) {
+            trace!("FileAction: on_closed(); reason={}", reason.as_str());
+            self.execute(state, context, Some(reason), None);
+        }
+    }
+
+    fn on_threshold(&self, state: &State<E>, context: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {
+        if self.when.on_threshold {
+            trace!("FileAction: on_threshold()");
+            self.execute(state, context, None, None);
+        }
+    }
+
+    fn on_message(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {
+        if self.when.is_message_trigger(event) {
+            trace!("FileAction: on_message()");
+            self.execute(state, context, None, Some(event));
+        }
+    }
+
+    fn on_timer(&self, _: &Duration, _: &mut VecDeque<Alert<E>>) {
+        let mut writer = self.writer.borrow_mut();
+        let result = match self.fsync {
+            Fsync::Timer => writer.sync(),
+            _ => writer.flush(),
+        };
+        if let Err(error) = result {
+            error!("FileAction: failed to flush file; path={} error={}", &self.path, error);
+            writer.close();
+        }
+        writer.check_rotated();
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use config::action::file::{FileAction, FileActionBuilder, Fsync, reopen_files};
+use action::Action;
+
+use serde_json::from_str;
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::time::Duration;
+use uuid::Uuid;
+use Message;
+use test_utils::{MockTemplate, close};
+
+fn temp_path() -> String {
+    env::temp_dir().join(format!("correlation-{}.log", Uuid::new_v4().to_hyphenated_string())).to_string_lossy().into_owned()
+}
+
+fn read(path: &str) -> String {
+    let mut content = String::new();
+    let _ = File::open(path).and_then(|mut file| file.read_to_string(&mut content));
+    content
+}
+
+fn flush(action: &FileAction<MockTemplate>) {
+    Action::<Message, MockTemplate>::on_timer(action, &Duration::from_millis(100), &mut VecDeque::default());
+}
+
+#[test]
+fn test_given_file_action_when_it_is_executed_then_the_alert_is_appended_as_a_json_object() {
+    let path = temp_path();
+    let action = FileActionBuilder::<MockTemplate>::new("uuid", path.clone())
+                     .name(Some("ALERT"))
+                     .message(MockTemplate::literal(b"message"))
+                     .pair("reason", MockTemplate::close_reason())
+                     .build();
+
+    assert_true!(close(&action).is_empty());
+    assert_eq!("", read(&path));
+    flush(&action);
+    assert_true!(close(&action).is_empty());
+    flush(&action);
+    let content = read(&path);
+    let lines = content.lines().collect::<Vec<&str>>();
+    assert_eq!(2, lines.len());
+    let alert = from_str::<BTreeMap<String, String>>(lines[0]).expect("Failed to parse a written alert");
+    let expected = btreemap!{
+        "uuid".to_owned() => "uuid".to_owned(),
+        "name".to_owned() => "ALERT".to_owned(),
+        "message".to_owned() => "message".to_owned(),
+        "reason".to_owned() => "timeout".to_owned(),
+        "close_reason".to_owned() => "timeout".to_owned(),
+        "context_uuid".to_owned() => "2f34112c-6fc8-406b-a6f0-78158ca724b6".to_owned(),
+        "context_name".to_owned() => "LOGIN".to_owned(),
+    };
+    assert_eq!(expected, alert);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_given_file_action_with_template_and_fsync_when_it_is_executed_then_the_line_is_written_immediately() {
+    let path = temp_path();
+    let action = FileActionBuilder::<MockTemplate>::new("uuid", path.clone())
+                     .template(Some(MockTemplate::context_len()))
+                     .fsync(Fsync::Always)
+                     .build();
+
+    assert_true!(close(&action).is_empty());
+    assert_eq!("1\n", read(&path));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_given_file_action_when_the_file_is_rotated_then_it_is_reopened() {
+    let path = temp_path();
+    let rotated = format!("{}.1", &path);
+    let action = FileActionBuilder::<MockTemplate>::new("uuid", path.clone())
+                     .template(Some(MockTemplate::literal(b"alert")))
+                     .build();
+
+    assert_true!(close(&action).is_empty());
+    flush(&action);
+    fs::rename(&path, &rotated).unwrap();
+    flush(&action);
+    assert_true!(close(&action).is_empty());
+    flush(&action);
+    assert_eq!("alert\n", read(&rotated));
+    assert_eq!("alert\n", read(&path));
+    fs::remove_file(&path).unwrap();
+    reopen_files();
+    assert_true!(close(&action).is_empty());
+    flush(&action);
+    assert_eq!("alert\n", read(&path));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&rotated);
+}
+
+#[test]
+fn test_given_file_action_when_the_file_is_rotated_then_it_is_reopened_only_after_the_next_timer_event() {
+    let path = temp_path();
+    let rotated = format!("{}.1", &path);
+    let action = FileActionBuilder::<MockTemplate>::new("uuid", path.clone())
+                     .template(Some(MockTemplate::literal(b"alert")))
+                     .build();
+
+    assert_true!(close(&action).is_empty());
+    fs::rename(&path, &rotated).unwrap();
+    assert_true!(close(&action).is_empty());
+    flush(&action);
+    assert_eq!("alert\nalert\n", read(&rotated));
+    assert_true!(close(&action).is_empty());
+    flush(&action);
+    assert_eq!("alert\n", read(&path));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&rotated);
+}
+
+#[test]
+fn test_given_file_actions_with_the_same_path_when_they_write_long_lines_then_the_lines_are_not_interleaved() {
+    let path = temp_path();
+    let a = vec![b'a'; 10000];
+    let b = vec![b'b'; 10000];
+    let first = FileActionBuilder::<MockTemplate>::new("first", path.clone())
+                    .template(Some(MockTemplate::literal(&a)))
+                    .build();
+    let second = FileActionBuilder::<MockTemplate>::new("second", path.clone())
+                     .template(Some(MockTemplate::literal(&b)))
+                     .build();
+
+    assert_true!(close(&first).is_empty());
+    assert_true!(close(&second).is_empty());
+    assert_true!(close(&first).is_empty());
+    flush(&second);
+    flush(&first);
+    let content = read(&path);
+    let lines = content.lines().collect::<Vec<&str>>();
+    assert_eq!(3, lines.len());
+    assert_eq!(2, lines.iter().filter(|line| line.as_bytes() == &a[..]).count());
+    assert_eq!(1, lines.iter().filter(|line| line.as_bytes() == &b[..]).count());
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,154 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+/// Bumped by `reopen_files()`, the writers reopen their files when they see a new value.
+static REOPEN_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// The buffered lines are written when they exceed this many bytes.
+const BUFFER_SIZE: usize = 8192;
+
+/// Makes every file action reopen its file before the next write. It only
+/// stores an atomic, so it can be called from a signal handler (e.g. on `SIGHUP`).
+pub fn reopen_files() {
+    REOPEN_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// An append-only buffered file which follows the rotations of its path.
+///
+/// The lines are buffered in memory and every line is written by one append
+/// to an unbuffered file, so the writers (or other processes) appending to
+/// the same path don't interleave their lines.
+pub struct Writer {
+    path: String,
+    file: Option<File>,
+    lines: Vec<Vec<u8>>,
+    buffered: usize,
+    generation: usize,
+}
+
+impl Writer {
+    pub fn new(path: String) -> Writer {
+        Writer {
+            path: path,
+            file: None,
+            lines: Vec::new(),
+            buffered: 0,
+            generation: REOPEN_GENERATION.load(Ordering::SeqCst),
+        }
+    }
+
+    pub fn write_line(&mut self, mut line: Vec<u8>) -> io::Result<()> {
+        let _ = try!(self.open());
+        line.push(b'\n');
+        self.buffered += line.len();
+        self.lines.push(line);
+        if self.buffered >= BUFFER_SIZE {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Appends the buffered lines to the file. The lines which couldn't be written are dropped.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.lines.is_empty() {
+            return Ok(());
+        }
+        let lines = mem::replace(&mut self.lines, Vec::new());
+        self.buffered = 0;
+        let file = try!(self.open());
+        for line in &lines {
+            try!(file.write_all(line));
+        }
+        Ok(())
+    }
+
+    /// Flushes the buffer and waits until the data reaches the disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        try!(self.flush());
+        match self.file {
+            Some(ref file) => file.sync_data(),
+            None => Ok(()),
+        }
+    }
+
+    /// Closes the file if a reopen was requested, so it's reopened by the next write.
+    pub fn check_reopen(&mut self) {
+        let generation = REOPEN_GENERATION.load(Ordering::SeqCst);
+        if generation != self.generation {
+            self.generation = generation;
+            self.close();
+        }
+    }
+
+    /// Closes the file if it was moved away (e.g. by logrotate), so it's
+    /// reopened by the next write. It stats the path, so it's called only
+    /// on the timer events.
+    pub fn check_rotated(&mut self) {
+        if self.is_rotated() {
+            trace!("FileAction: file was rotated; path={}", &self.path);
+            self.close();
+        }
+    }
+
+    pub fn close(&mut self) {
+        if self.file.is_some() {
+            if let Err(error) = self.flush() {
+                error!("FileAction: failed to flush file; path={} error={}", &self.path, error);
+            }
+            trace!("FileAction: closing file; path={}", &self.path);
+            self.file = None;
+        }
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            trace!("FileAction: opening file; path={}", &self.path);
+            let file = try!(OpenOptions::new().append(true).create(true).open(&self.path));
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("Failed to get an opened file"))
+    }
+
+    fn is_rotated(&self) -> bool {
+        match self.file {
+            Some(ref file) => {
+                match (fs::metadata(&self.path), file.metadata()) {
+                    (Ok(path), Ok(opened)) => !is_same_file(&path, &opened),
+                    _ => true,
+                }
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush() {
+            error!("FileAction: failed to flush file; path={} error={}", &self.path, error);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    // a truncated or a recreated file is smaller than the opened one
+    a.len() >= b.len()
+}
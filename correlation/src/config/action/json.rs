@@ -0,0 +1,64 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use config::action::message::{CONTEXT_NAME, CONTEXT_UUID};
+use context::base::BaseContext;
+use Event;
+use Template;
+use TemplateContext;
+
+use serde_json;
+use std::collections::BTreeMap;
+
+pub const UUID: &'static str = "uuid";
+pub const NAME: &'static str = "name";
+pub const MESSAGE: &'static str = "message";
+pub const CLOSE_REASON: &'static str = "close_reason";
+
+/// The parts of an alert which are rendered into a JSON object.
+pub struct JsonAlert<'a, T: 'a> {
+    pub uuid: &'a str,
+    pub name: Option<&'a String>,
+    pub message: Option<&'a T>,
+    pub values: &'a BTreeMap<String, T>,
+}
+
+impl<'a, T> JsonAlert<'a, T> {
+    /// Renders the alert as a JSON object. The aggregates are overridden
+    /// by the values, the values by the fixed keys.
+    pub fn render<E>(&self, template_context: &TemplateContext<E>, context: &BaseContext<E, T>) -> Vec<u8>
+        where E: Event, T: Template<Event=E>
+    {
+        let mut object = BTreeMap::new();
+        for (name, value) in &template_context.aggregates {
+            object.insert(name.to_string(), value.clone());
+        }
+        let mut value = Vec::new();
+        for (name, template) in self.values {
+            template.format(template_context, &mut value);
+            object.insert(name.clone(), String::from_utf8_lossy(&value).into_owned());
+            value.clear();
+        }
+        if let Some(message) = self.message {
+            message.format(template_context, &mut value);
+            object.insert(MESSAGE.to_owned(), String::from_utf8_lossy(&value).into_owned());
+        }
+        object.insert(UUID.to_owned(), self.uuid.to_owned());
+        if let Some(name) = self.name {
+            object.insert(NAME.to_owned(), name.clone());
+        }
+        object.insert(CONTEXT_UUID.to_owned(), template_context.context_id.to_owned());
+        if let Some(name) = context.name() {
+            object.insert(CONTEXT_NAME.to_owned(), name.clone());
+        }
+        if let Some(reason) = template_context.close_reason {
+            object.insert(CLOSE_REASON.to_owned(), reason.as_str().to_owned());
+        }
+        serde_json::to_string(&object).map(String::into_bytes).unwrap_or_default()
+    }
+}
@@ -8,6 +8,7 @@
 
 use super::MessageAction;
 use super::InjectMode;
+use super::RateLimit;
 use config::action::ExecCondition;
 
 use std::collections::BTreeMap;
@@ -19,6 +20,7 @@ pub struct MessageActionBuilder<T> {
     values: BTreeMap<String, T>,
     when: ExecCondition,
     inject_mode: InjectMode,
+    rate_limit: Option<RateLimit>,
 }
 
 impl<T> MessageActionBuilder<T> {
@@ -30,6 +32,7 @@ impl<T> MessageActionBuilder<T> {
             values: BTreeMap::default(),
             when: ExecCondition::default(),
             inject_mode: InjectMode::default(),
+            rate_limit: None,
         }
     }
 
@@ -58,6 +61,11 @@ impl<T> MessageActionBuilder<T> {
         self
     }
 
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> MessageActionBuilder<T> {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
     pub fn build(self) -> MessageAction<T> {
         MessageAction {
             uuid: self.uuid,
@@ -66,6 +74,7 @@ impl<T> MessageActionBuilder<T> {
             values: self.values,
             when: self.when,
             inject_mode: self.inject_mode,
+            rate_limit: self.rate_limit,
         }
     }
 }
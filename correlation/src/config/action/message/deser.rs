@@ -29,6 +29,7 @@ enum Field {
     Values,
     When,
     InjectMode,
+    RateLimit,
 }
 
 impl Deserialize for Field {
@@ -50,6 +51,7 @@ impl Deserialize for Field {
                     "message" => Ok(Field::Message),
                     "when" => Ok(Field::When),
                     "inject_mode" => Ok(Field::InjectMode),
+                    "rate_limit" => Ok(Field::RateLimit),
                     _ => Err(Error::custom(format!("Unexpected field: {}", value))),
                 }
             }
@@ -73,6 +75,7 @@ impl<T> Visitor for MessageActionVisitor<T> where T: Deserialize {
         let mut values: Option<BTreeMap<String, T>> = None;
         let mut when: ExecCondition = ExecCondition::new();
         let mut inject_mode = Default::default();
+        let mut rate_limit = None;
 
         while let Some(field) = try!(visitor.visit_key()) {
             match field {
@@ -82,6 +85,7 @@ impl<T> Visitor for MessageActionVisitor<T> where T: Deserialize {
                 Field::Values => values = Some(try!(visitor.visit_value())),
                 Field::When => when = try!(visitor.visit_value()),
                 Field::InjectMode => inject_mode = try!(visitor.visit_value()),
+                Field::RateLimit => rate_limit = Some(try!(visitor.visit_value())),
             }
         }
 
@@ -107,6 +111,7 @@ impl<T> Visitor for MessageActionVisitor<T> where T: Deserialize {
             values: values.unwrap_or_default(),
             when: when,
             inject_mode: inject_mode,
+            rate_limit: rate_limit,
         })
     }
 }
@@ -8,18 +8,23 @@
 
 use action::Action;
 use context::base::BaseContext;
+use conditions::CloseReason;
+use correlator::loopback_depth;
 use Event;
 use Template;
 
 use std::collections::BTreeMap;
 use std::collections::VecDeque;
+use std::time::Duration;
 use state::State;
 use super::ExecCondition;
 
 pub use self::builder::MessageActionBuilder;
+pub use self::rate_limit::{RateLimit, RateLimitScope};
 
 mod deser;
 mod builder;
+mod rate_limit;
 #[cfg(test)]
 mod test;
 
@@ -27,6 +32,14 @@ pub const CONTEXT_UUID: &'static str = "context_uuid";
 pub const CONTEXT_NAME: &'static str = "context_name";
 pub const CONTEXT_LEN: &'static str = "context_len";
 pub const MESSAGES: &'static str = "messages";
+/// The number of events received by the context, including the suppressed ones.
+pub const EVENT_COUNT: &'static str = "event_count";
+/// The number of executions suppressed by the rate limit since the previous alert.
+pub const SUPPRESSED_ALERTS: &'static str = "suppressed_alerts";
+/// How many times the message was sent back to the correlator in a row.
+pub const LOOPBACK_DEPTH: &'static str = "loopback_depth";
+/// The uuids of the contexts which sent the message back, separated by commas.
+pub const LOOPBACK_CHAIN: &'static str = "loopback_chain";
 
 pub struct MessageAction<T> {
     pub uuid: String,
@@ -35,6 +48,7 @@ pub struct MessageAction<T> {
     pub values: BTreeMap<String, T>,
     pub when: ExecCondition,
     pub inject_mode: InjectMode,
+    pub rate_limit: Option<RateLimit>,
 }
 
 impl<T> MessageAction<T> {
@@ -53,27 +67,86 @@ impl<T> MessageAction<T> {
     pub fn inject_mode(&self) -> &InjectMode {
         &self.inject_mode
     }
+    pub fn rate_limit(&self) -> Option<&RateLimit> {
+        self.rate_limit.as_ref()
+    }
 
-    fn execute<E>(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) where E: Event, T: Template<Event=E> {
-        let context_id = context.uuid.to_hyphenated_string();
+    fn execute<E>(&self, state: &State<E>, context: &BaseContext<E, T>, reason: Option<CloseReason>, event: Option<&E>, responder: &mut VecDeque<Alert<E>>) where E: Event, T: Template<Event=E> {
+        let suppressed_alerts = match self.rate_limit {
+            Some(ref rate_limit) => {
+                match rate_limit.acquire(state.key()) {
+                    Some(suppressed_alerts) => Some(suppressed_alerts),
+                    None => {
+                        trace!("MessageAction: execution is suppressed by the rate limit; uuid={}", &self.uuid);
+                        return;
+                    }
+                }
+            }
+            None => None,
+        };
+        let template_context = context.template_context(state, reason, event);
         let mut message = Vec::new();
-        self.message.format_with_context(state.messages(), &context_id, &mut message);
+        self.message.format(&template_context, &mut message);
         let mut event = E::new(&self.uuid.as_bytes(), &message);
         event.set_name(self.name.as_ref().map(|name| name.as_bytes()));
+        for (name, value) in &template_context.aggregates {
+            event.set(name.as_bytes(), value.as_bytes());
+        }
+        event.set(EVENT_COUNT.as_bytes(), template_context.event_count.to_string().as_bytes());
+        if let Some(suppressed_alerts) = suppressed_alerts {
+            event.set(SUPPRESSED_ALERTS.as_bytes(), suppressed_alerts.to_string().as_bytes());
+        }
         let mut value = Vec::new();
         for (k, v) in &self.values {
-            v.format_with_context(state.messages(), &context_id, &mut value);
+            v.format(&template_context, &mut value);
             event.set(k.as_bytes(), &value);
             value.clear();
         }
+        if self.inject_mode == InjectMode::Loopback {
+            LoopbackChain::new(state, template_context.event, template_context.context_id).set(&mut event);
+        }
         let response = Alert {
             message: event,
             inject_mode: self.inject_mode.clone(),
+            close_reason: reason,
         };
         responder.push_back(response);
     }
 }
 
+/// The depth and the chain of the contexts of a message sent back to the correlator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopbackChain {
+    pub depth: usize,
+    /// The uuids of the contexts separated by commas.
+    pub chain: Vec<u8>,
+}
+
+impl LoopbackChain {
+    /// The depth of a loopback message is one more than the deepest message of the
+    /// state or the triggering event (which may not be stored), its chain is the
+    /// chain of that message and the generating context.
+    pub fn new<E: Event>(state: &State<E>, event: Option<&E>, context_id: &str) -> LoopbackChain {
+        let deepest = state.messages().iter().chain(event).max_by_key(|message| loopback_depth(*message));
+        let depth = deepest.map_or(0, |message| loopback_depth(message)) + 1;
+        let mut chain = deepest.and_then(|message| message.get(LOOPBACK_CHAIN.as_bytes()))
+                               .map_or(Vec::new(), |chain| chain.to_vec());
+        if !chain.is_empty() {
+            chain.push(b',');
+        }
+        chain.extend_from_slice(context_id.as_bytes());
+        LoopbackChain {
+            depth: depth,
+            chain: chain,
+        }
+    }
+
+    pub fn set<E: Event>(&self, event: &mut E) {
+        event.set(LOOPBACK_DEPTH.as_bytes(), self.depth.to_string().as_bytes());
+        event.set(LOOPBACK_CHAIN.as_bytes(), &self.chain);
+    }
+}
+
 impl<T> From<MessageAction<T>> for super::ActionType<T> {
     fn from(action: MessageAction<T>) -> super::ActionType<T> {
         super::ActionType::Message(action)
@@ -85,6 +158,8 @@ pub enum InjectMode {
     Log,
     Forward,
     Loopback,
+    /// The values of the alert are written into the event which triggered it.
+    Tag,
 }
 
 impl Default for InjectMode {
@@ -97,20 +172,42 @@ impl Default for InjectMode {
 pub struct Alert<E: Event> {
     pub message: E,
     pub inject_mode: InjectMode,
+    /// The reason of closing the context, `None` if the alert wasn't generated on close.
+    pub close_reason: Option<CloseReason>,
 }
 
 impl<E, T> Action<E, T> for MessageAction<T> where E: Event, T: Template<Event=E> {
     fn on_opened(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
         if self.when.on_opened {
             trace!("MessageAction: on_opened()");
-            self.execute(state, context, responder);
+            self.execute(state, context, None, None, responder);
+        }
+    }
+
+    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, reason: CloseReason, responder: &mut VecDeque<Alert<E>>) {
+        if self.when.is_closing_trigger(reason) {
+            trace!("MessageAction: on_closed(); reason={}", reason.as_str());
+            self.execute(state, context, Some(reason), None, responder);
+        }
+    }
+
+    fn on_threshold(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        if self.when.on_threshold {
+            trace!("MessageAction: on_threshold()");
+            self.execute(state, context, None, None, responder);
+        }
+    }
+
+    fn on_message(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        if self.when.is_message_trigger(event) {
+            trace!("MessageAction: on_message()");
+            self.execute(state, context, None, Some(event), responder);
         }
     }
 
-    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
-        if self.when.on_closed {
-            trace!("MessageAction: on_closed()");
-            self.execute(state, context, responder);
+    fn on_timer(&self, event: &Duration, _: &mut VecDeque<Alert<E>>) {
+        if let Some(ref rate_limit) = self.rate_limit {
+            rate_limit.on_timer(event);
         }
     }
 }
@@ -0,0 +1,322 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use context::map::map::ContextKey;
+
+/// Defines whether the executions of an action are limited together or
+/// separately for every `context_id` key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitScope {
+    Global,
+    Key,
+}
+
+impl Default for RateLimitScope {
+    fn default() -> RateLimitScope {
+        RateLimitScope::Global
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Limiter {
+    last_execution: Option<Duration>,
+    interval_start: Duration,
+    executions: usize,
+    suppressed: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Limiters {
+    now: Duration,
+    limiters: BTreeMap<ContextKey, Limiter>,
+}
+
+/// Limits how often an action can be executed.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    /// The minimal time between two executions.
+    pub cooldown: Option<Duration>,
+    /// The maximal number of executions in an interval.
+    pub max_per_interval: Option<(usize, Duration)>,
+    pub scope: RateLimitScope,
+    limiters: RefCell<Limiters>,
+}
+
+impl PartialEq for RateLimit {
+    fn eq(&self, other: &RateLimit) -> bool {
+        self.cooldown == other.cooldown && self.max_per_interval == other.max_per_interval && self.scope == other.scope
+    }
+}
+
+impl RateLimit {
+    pub fn new(scope: RateLimitScope) -> RateLimit {
+        RateLimit {
+            cooldown: None,
+            max_per_interval: None,
+            scope: scope,
+            limiters: RefCell::new(Limiters::default()),
+        }
+    }
+
+    pub fn cooldown(mut self, cooldown: Duration) -> RateLimit {
+        self.cooldown = Some(cooldown);
+        self
+    }
+
+    pub fn max_per_interval(mut self, max_per_interval: usize, interval: Duration) -> RateLimit {
+        self.max_per_interval = Some((max_per_interval, interval));
+        self
+    }
+
+    pub fn on_timer(&self, event: &Duration) {
+        let mut limiters = self.limiters.borrow_mut();
+        limiters.now = limiters.now + *event;
+        let now = limiters.now;
+        // the limiters which don't limit anything and don't have anything to report are dropped,
+        // the suppressed executions are kept until the next execution reports them
+        let idle = limiters.limiters
+                           .iter()
+                           .filter(|&(_, limiter)| limiter.suppressed == 0 && self.is_idle(limiter, now))
+                           .map(|(key, _)| key.clone())
+                           .collect::<Vec<ContextKey>>();
+        for key in idle {
+            let _ = limiters.limiters.remove(&key);
+        }
+    }
+
+    /// Returns `true` if the limiter doesn't limit anything anymore.
+    fn is_idle(&self, limiter: &Limiter, now: Duration) -> bool {
+        let cooldown_end = match (self.cooldown, limiter.last_execution) {
+            (Some(cooldown), Some(last_execution)) => last_execution + cooldown,
+            _ => Duration::from_secs(0),
+        };
+        let interval_end = self.max_per_interval
+                               .map_or(Duration::from_secs(0), |(_, interval)| limiter.interval_start + interval);
+        now >= cmp::max(cooldown_end, interval_end)
+    }
+
+    /// Returns the number of executions suppressed since the last one if the
+    /// action can be executed, `None` otherwise.
+    pub fn acquire(&self, key: &ContextKey) -> Option<usize> {
+        let mut limiters = self.limiters.borrow_mut();
+        let now = limiters.now;
+        let global = ContextKey::new();
+        let key = match self.scope {
+            RateLimitScope::Global => &global,
+            RateLimitScope::Key => key,
+        };
+        if !limiters.limiters.contains_key(key) {
+            let limiter = Limiter {
+                interval_start: now,
+                ..Limiter::default()
+            };
+            let _ = limiters.limiters.insert(key.clone(), limiter);
+        }
+        let limiter = limiters.limiters.get_mut(key).expect("Failed to get an existing limiter");
+
+        if let Some((_, interval)) = self.max_per_interval {
+            if now - limiter.interval_start >= interval {
+                limiter.interval_start = now;
+                limiter.executions = 0;
+            }
+        }
+
+        let in_cooldown = match (self.cooldown, limiter.last_execution) {
+            (Some(cooldown), Some(last_execution)) => now - last_execution < cooldown,
+            _ => false,
+        };
+        let over_limit = self.max_per_interval.map_or(false, |(max, _)| limiter.executions >= max);
+
+        if in_cooldown || over_limit {
+            limiter.suppressed += 1;
+            None
+        } else {
+            let suppressed = limiter.suppressed;
+            limiter.suppressed = 0;
+            limiter.executions += 1;
+            limiter.last_execution = Some(now);
+            Some(suppressed)
+        }
+    }
+}
+
+mod deser {
+    use super::{RateLimit, RateLimitScope};
+    use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+    use duration::SerializableDuration;
+
+    impl Deserialize for RateLimit {
+        fn deserialize<D>(deserializer: &mut D) -> Result<RateLimit, D::Error>
+            where D: Deserializer
+        {
+            deserializer.deserialize_struct("RateLimit", &[], RateLimitVisitor)
+        }
+    }
+
+    enum Field {
+        Cooldown,
+        MaxPerInterval,
+        Interval,
+        Scope,
+    }
+
+    impl Deserialize for Field {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+            where D: Deserializer
+        {
+            struct FieldVisitor;
+
+            impl Visitor for FieldVisitor {
+                type Value = Field;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                    where E: Error
+                {
+                    match value {
+                        "cooldown" => Ok(Field::Cooldown),
+                        "max_per_interval" => Ok(Field::MaxPerInterval),
+                        "interval" => Ok(Field::Interval),
+                        "scope" => Ok(Field::Scope),
+                        _ => Err(E::custom(format!("Unexpected field: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(FieldVisitor)
+        }
+    }
+
+    struct RateLimitVisitor;
+
+    impl Visitor for RateLimitVisitor {
+        type Value = RateLimit;
+
+        fn visit_map<V>(&mut self, mut visitor: V) -> Result<RateLimit, V::Error>
+            where V: MapVisitor
+        {
+            let mut cooldown: Option<SerializableDuration> = None;
+            let mut max_per_interval: Option<usize> = None;
+            let mut interval: Option<SerializableDuration> = None;
+            let mut scope = RateLimitScope::default();
+
+            while let Some(field) = try!(visitor.visit_key()) {
+                match field {
+                    Field::Cooldown => cooldown = Some(try!(visitor.visit_value())),
+                    Field::MaxPerInterval => max_per_interval = Some(try!(visitor.visit_value())),
+                    Field::Interval => interval = Some(try!(visitor.visit_value())),
+                    Field::Scope => scope = try!(visitor.visit_value()),
+                }
+            }
+
+            let mut rate_limit = RateLimit::new(scope);
+
+            if let Some(cooldown) = cooldown {
+                rate_limit = rate_limit.cooldown(cooldown.0);
+            }
+
+            if let Some(max_per_interval) = max_per_interval {
+                match interval {
+                    Some(interval) => rate_limit = rate_limit.max_per_interval(max_per_interval, interval.0),
+                    None => return visitor.missing_field("interval"),
+                }
+            }
+
+            try!(visitor.end());
+
+            Ok(rate_limit)
+        }
+    }
+
+    impl Deserialize for RateLimitScope {
+        fn deserialize<D>(deserializer: &mut D) -> Result<RateLimitScope, D::Error>
+            where D: Deserializer
+        {
+            struct RateLimitScopeVisitor;
+
+            impl Visitor for RateLimitScopeVisitor {
+                type Value = RateLimitScope;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<RateLimitScope, E>
+                    where E: Error
+                {
+                    match value {
+                        "global" => Ok(RateLimitScope::Global),
+                        "key" => Ok(RateLimitScope::Key),
+                        _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(RateLimitScopeVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RateLimit, RateLimitScope};
+    use serde_json::from_str;
+    use std::time::Duration;
+
+    fn key(value: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        vec![(b"HOST".to_vec(), value.to_vec())]
+    }
+
+    #[test]
+    fn test_given_rate_limit_with_cooldown_when_the_action_is_executed_again_too_early_then_it_is_suppressed() {
+        let rate_limit = RateLimit::new(RateLimitScope::Global).cooldown(Duration::from_millis(100));
+        assert_eq!(Some(0), rate_limit.acquire(&key(b"host1")));
+        rate_limit.on_timer(&Duration::from_millis(50));
+        assert_eq!(None, rate_limit.acquire(&key(b"host1")));
+        assert_eq!(None, rate_limit.acquire(&key(b"host2")));
+        rate_limit.on_timer(&Duration::from_millis(50));
+        assert_eq!(Some(2), rate_limit.acquire(&key(b"host1")));
+    }
+
+    #[test]
+    fn test_given_rate_limit_with_max_per_interval_and_key_scope_when_a_key_exceeds_the_limit_then_only_that_key_is_suppressed() {
+        let rate_limit = RateLimit::new(RateLimitScope::Key).max_per_interval(2, Duration::from_millis(100));
+        assert_eq!(Some(0), rate_limit.acquire(&key(b"host1")));
+        assert_eq!(Some(0), rate_limit.acquire(&key(b"host1")));
+        assert_eq!(None, rate_limit.acquire(&key(b"host1")));
+        assert_eq!(Some(0), rate_limit.acquire(&key(b"host2")));
+        rate_limit.on_timer(&Duration::from_millis(100));
+        assert_eq!(Some(1), rate_limit.acquire(&key(b"host1")));
+    }
+
+    #[test]
+    fn test_given_rate_limit_with_key_scope_when_a_key_is_idle_for_a_whole_period_then_its_suppressed_executions_are_reported_by_its_next_execution() {
+        let rate_limit = RateLimit::new(RateLimitScope::Key).cooldown(Duration::from_millis(100));
+        assert_eq!(Some(0), rate_limit.acquire(&key(b"host1")));
+        assert_eq!(None, rate_limit.acquire(&key(b"host1")));
+        assert_eq!(Some(0), rate_limit.acquire(&key(b"host2")));
+        assert_eq!(None, rate_limit.acquire(&key(b"host2")));
+        rate_limit.on_timer(&Duration::from_millis(150));
+        assert_eq!(Some(1), rate_limit.acquire(&key(b"host1")));
+        rate_limit.on_timer(&Duration::from_millis(250));
+        assert_eq!(Some(1), rate_limit.acquire(&key(b"host2")));
+        assert_eq!(None, rate_limit.acquire(&key(b"host2")));
+        rate_limit.on_timer(&Duration::from_millis(100));
+        assert_eq!(Some(1), rate_limit.acquire(&key(b"host2")));
+    }
+
+    #[test]
+    fn test_given_rate_limit_in_json_when_it_is_deserialized_then_we_get_the_expected_result() {
+        let rate_limit = from_str::<RateLimit>(r#"{ "cooldown": 1000, "max_per_interval": 5, "interval": 60000, "scope": "key" }"#);
+        let expected = RateLimit::new(RateLimitScope::Key)
+                           .cooldown(Duration::from_millis(1000))
+                           .max_per_interval(5, Duration::from_millis(60000));
+        assert_eq!(expected, rate_limit.unwrap());
+        let _ = from_str::<RateLimit>(r#"{ "max_per_interval": 5 }"#).err().expect("Successfully deserialized a max_per_interval without interval");
+    }
+}
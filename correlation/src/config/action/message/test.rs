@@ -7,9 +7,11 @@
 // modified, or distributed except according to those terms.
 
 use config::action::message::MessageActionBuilder;
+use config::action::ExecCondition;
 use message::MessageBuilder;
 
-use conditions::ConditionsBuilder;
+use conditions::{CloseReason, ConditionsBuilder};
+use pattern::Pattern;
 use state::State;
 use action::Action;
 
@@ -19,6 +21,8 @@ use std::collections::VecDeque;
 use uuid::Uuid;
 use Event;
 use Message;
+use {Aggregate, AggregateFunction};
+use config::action::message::{InjectMode, RateLimit, RateLimitScope, EVENT_COUNT, LOOPBACK_DEPTH, SUPPRESSED_ALERTS};
 use test_utils::{MockTemplate, BaseContextBuilder};
 
 #[test]
@@ -41,7 +45,7 @@ fn test_given_message_action_when_it_is_executed_then_the_additional_values_are_
                                               .pair("key2", MockTemplate::literal(b"value2"))
                                               .build();
 
-    message_action.on_closed(&state, &base_context, &mut responder);
+    message_action.on_closed(&state, &base_context, CloseReason::Completed, &mut responder);
     assert_eq!(1, responder.len());
     let response = responder.get(0).unwrap();
     let message = &response.message;
@@ -72,7 +76,7 @@ fn test_executed_message_action_uses_the_templates() {
                                               .pair("context_len", MockTemplate::context_len())
                                               .build();
 
-    message_action.on_closed(&state, &base_context, &mut responder);
+    message_action.on_closed(&state, &base_context, CloseReason::Completed, &mut responder);
     assert_eq!(1, responder.len());
     let response = responder.get(0).unwrap();
     let message = &response.message;
@@ -84,3 +88,166 @@ fn test_executed_message_action_uses_the_templates() {
     assert_eq!(uuid_as_str.as_bytes(), message.get(b"context_id").unwrap());
     assert_eq!(b"2", message.get(b"context_len").unwrap());
 }
+
+#[test]
+fn test_given_message_action_when_it_is_executed_on_close_then_the_close_reason_is_available() {
+    let mut responder = VecDeque::default();
+    let base_context = {
+        let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+        BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build()
+    };
+    let state = State::with_messages(vec![MessageBuilder::new("uuid1", "message1").build()]);
+    let message_action = MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::close_reason())
+                                              .pair("reason", MockTemplate::close_reason())
+                                              .build();
+
+    message_action.on_closed(&state, &base_context, CloseReason::Timeout, &mut responder);
+    let response = responder.get(0).unwrap();
+    assert_eq!(Some(CloseReason::Timeout), response.close_reason);
+    assert_eq!(b"timeout", response.message.message());
+    assert_eq!(b"timeout", response.message.get(b"reason").unwrap());
+}
+
+#[test]
+fn test_given_message_action_with_a_close_reason_when_the_context_is_closed_for_an_other_reason_then_it_is_not_executed() {
+    let mut responder = VecDeque::default();
+    let base_context = {
+        let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+        BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build()
+    };
+    let state = State::with_messages(vec![MessageBuilder::new("uuid1", "message1").build()]);
+    let when = ExecCondition {
+        on_closed: false,
+        on_timeout: true,
+        ..ExecCondition::default()
+    };
+    let message_action = MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::literal(b"message"))
+                                              .when(when)
+                                              .build();
+
+    message_action.on_closed(&state, &base_context, CloseReason::Completed, &mut responder);
+    assert_eq!(0, responder.len());
+    message_action.on_closed(&state, &base_context, CloseReason::Timeout, &mut responder);
+    assert_eq!(1, responder.len());
+}
+
+#[test]
+fn test_given_context_with_aggregates_when_the_action_is_executed_then_the_aggregates_are_available() {
+    let mut responder = VecDeque::default();
+    let aggregates = btreemap!{
+        "bytes".to_owned() => Aggregate::new(AggregateFunction::Sum, Some("bytes".to_owned())),
+        "ips".to_owned() => Aggregate::new(AggregateFunction::DistinctCount, Some("src_ip".to_owned())),
+        "requests".to_owned() => Aggregate::new(AggregateFunction::Count, None),
+    };
+    let action = MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::aggregate("bytes")).build();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                           .aggregates(aggregates)
+                           .actions(vec![action.into()])
+                           .build();
+    let mut state = State::new();
+    for &(bytes, src_ip) in &[(b"1000", b"10.0.0.1"), (b"2000", b"10.0.0.2"), (b"3000", b"10.0.0.1")] {
+        let message = MessageBuilder::new("uuid", "message").pair(b"bytes", bytes).pair(b"src_ip", src_ip).build();
+        base_context.on_message(message, &mut state, &mut responder);
+    }
+    base_context.on_timer(&Duration::from_millis(100), &mut state, &mut responder);
+    assert_eq!(1, responder.len());
+    let alert = &responder[0].message;
+    assert_eq!(b"6000", alert.message());
+    assert_eq!(Some(&b"6000"[..]), alert.get(b"bytes"));
+    assert_eq!(Some(&b"2"[..]), alert.get(b"ips"));
+    assert_eq!(Some(&b"3"[..]), alert.get(b"requests"));
+}
+
+#[test]
+fn test_given_message_action_with_rate_limit_when_it_is_executed_during_the_cooldown_then_the_suppressed_alerts_are_reported_later() {
+    let mut responder = VecDeque::default();
+    let base_context = {
+        let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+        BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build()
+    };
+    let state = State::with_messages(vec![MessageBuilder::new("uuid1", "message1").build()]);
+    let rate_limit = RateLimit::new(RateLimitScope::Global).cooldown(Duration::from_millis(100));
+    let message_action = MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::literal(b"message"))
+                                              .rate_limit(rate_limit)
+                                              .build();
+
+    for _ in 0..3 {
+        message_action.on_closed(&state, &base_context, CloseReason::Timeout, &mut responder);
+    }
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"0"[..]), responder[0].message.get(SUPPRESSED_ALERTS.as_bytes()));
+    Action::<Message, MockTemplate>::on_timer(&message_action, &Duration::from_millis(100), &mut responder);
+    message_action.on_closed(&state, &base_context, CloseReason::Timeout, &mut responder);
+    assert_eq!(2, responder.len());
+    assert_eq!(Some(&b"2"[..]), responder[1].message.get(SUPPRESSED_ALERTS.as_bytes()));
+}
+
+#[test]
+fn test_given_message_action_with_on_message_trigger_when_a_matching_message_is_received_then_it_is_executed_with_the_message() {
+    let mut responder = VecDeque::default();
+    let when = ExecCondition {
+        on_closed: false,
+        on_message: true,
+        on_message_patterns: vec![Pattern::new("login")],
+        ..ExecCondition::default()
+    };
+    let action = MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::event_value(b"USER"))
+                     .pair("context_len", MockTemplate::context_len())
+                     .when(when)
+                     .build();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                           .patterns(vec!["login", "logout"])
+                           .actions(vec![action.into()])
+                           .build();
+    let mut state = State::new();
+
+    base_context.on_message(MessageBuilder::new("login", "message").pair(b"USER", b"alice").build(), &mut state, &mut responder);
+    base_context.on_message(MessageBuilder::new("login", "message").pair(b"USER", b"bob").build(), &mut state, &mut responder);
+    base_context.on_message(MessageBuilder::new("logout", "message").pair(b"USER", b"alice").build(), &mut state, &mut responder);
+
+    assert_eq!(2, responder.len());
+    assert_eq!(b"alice", responder[0].message.message());
+    assert_eq!(b"bob", responder[1].message.message());
+    assert_eq!(Some(&b"2"[..]), responder[1].message.get(b"context_len"));
+}
+
+#[test]
+fn test_given_suppressing_context_when_it_is_closed_then_the_alert_contains_the_number_of_events() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).suppress(true).build();
+    let action = MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::literal(b"summary")).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                           .actions(vec![action.into()])
+                           .build();
+    let mut state = State::new();
+    for _ in 0..3 {
+        let _ = base_context.on_message(MessageBuilder::new("uuid1", "message").build(), &mut state, &mut responder);
+    }
+    base_context.on_timer(&Duration::from_millis(100), &mut state, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"3"[..]), responder[0].message.get(EVENT_COUNT.as_bytes()));
+}
+
+#[test]
+fn test_given_loopback_message_action_when_the_triggering_event_is_not_stored_then_the_depth_is_derived_from_it() {
+    let mut responder = VecDeque::default();
+    let when = ExecCondition {
+        on_closed: false,
+        on_message: true,
+        ..ExecCondition::default()
+    };
+    let action = MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::literal(b"message"))
+                     .inject_mode(InjectMode::Loopback)
+                     .when(when)
+                     .build();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build();
+    let state = State::with_messages(vec![MessageBuilder::new("uuid1", "stored").build()]);
+    let event = MessageBuilder::new("uuid1", "discarded").pair(LOOPBACK_DEPTH.as_bytes(), b"2").build();
+
+    action.on_message(&event, &state, &base_context, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"3"[..]), responder[0].message.get(LOOPBACK_DEPTH.as_bytes()));
+}
@@ -9,29 +9,105 @@
 use action::Action;
 use state::State;
 use context::BaseContext;
+use conditions::CloseReason;
+use pattern::Pattern;
+use self::exec::ExecAction;
+use self::file::FileAction;
+use self::webhook::WebhookAction;
 use self::message::MessageAction;
+use self::tag::TagAction;
 use Event;
 use Template;
 use Alert;
 
 use std::collections::VecDeque;
+use std::time::Duration;
 
+pub mod exec;
+pub mod file;
+mod json;
+pub mod webhook;
 pub mod message;
+pub mod tag;
 mod deser;
 
 pub enum ActionType<T> {
     Message(MessageAction<T>),
+    Exec(ExecAction<T>),
+    File(FileAction<T>),
+    Webhook(WebhookAction<T>),
+    Tag(TagAction<T>),
 }
 
 impl<E, T> Action<E, T> for ActionType<T> where E: Event, T: Template<Event=E> {
     fn on_opened(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
         match *self {
             ActionType::Message(ref action) => action.on_opened(state, context, responder),
+            ActionType::Exec(ref action) => action.on_opened(state, context, responder),
+            ActionType::File(ref action) => action.on_opened(state, context, responder),
+            ActionType::Webhook(ref action) => action.on_opened(state, context, responder),
+            ActionType::Tag(ref action) => action.on_opened(state, context, responder),
         }
     }
-    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, reason: CloseReason, responder: &mut VecDeque<Alert<E>>) {
         match *self {
-            ActionType::Message(ref action) => action.on_closed(state, context, responder),
+            ActionType::Message(ref action) => action.on_closed(state, context, reason, responder),
+            ActionType::Exec(ref action) => action.on_closed(state, context, reason, responder),
+            ActionType::File(ref action) => action.on_closed(state, context, reason, responder),
+            ActionType::Webhook(ref action) => action.on_closed(state, context, reason, responder),
+            ActionType::Tag(ref action) => action.on_closed(state, context, reason, responder),
+        }
+    }
+    fn on_threshold(&self, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        match *self {
+            ActionType::Message(ref action) => action.on_threshold(state, context, responder),
+            ActionType::Exec(ref action) => action.on_threshold(state, context, responder),
+            ActionType::File(ref action) => action.on_threshold(state, context, responder),
+            ActionType::Webhook(ref action) => action.on_threshold(state, context, responder),
+            ActionType::Tag(ref action) => action.on_threshold(state, context, responder),
+        }
+    }
+    fn on_message(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        match *self {
+            ActionType::Message(ref action) => action.on_message(event, state, context, responder),
+            ActionType::Exec(ref action) => action.on_message(event, state, context, responder),
+            ActionType::File(ref action) => action.on_message(event, state, context, responder),
+            ActionType::Webhook(ref action) => action.on_message(event, state, context, responder),
+            ActionType::Tag(ref action) => action.on_message(event, state, context, responder),
+        }
+    }
+    fn on_timer(&self, event: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        match *self {
+            ActionType::Message(ref action) => action.on_timer(event, responder),
+            ActionType::Exec(ref action) => action.on_timer(event, responder),
+            ActionType::File(ref action) => action.on_timer(event, responder),
+            ActionType::Webhook(ref action) => action.on_timer(event, responder),
+            ActionType::Tag(ref action) => action.on_timer(event, responder),
+        }
+    }
+}
+
+impl<T> ActionType<T> {
+    /// Returns the triggers of the action. The tag action doesn't have any.
+    pub fn when_mut(&mut self) -> Option<&mut ExecCondition> {
+        match *self {
+            ActionType::Message(ref mut action) => Some(&mut action.when),
+            ActionType::Exec(ref mut action) => Some(&mut action.when),
+            ActionType::File(ref mut action) => Some(&mut action.when),
+            ActionType::Webhook(ref mut action) => Some(&mut action.when),
+            ActionType::Tag(_) => None,
+        }
+    }
+
+    /// Returns `true` if the action is executed when `event` is accepted by
+    /// the context. The tag action is executed for every accepted event.
+    pub fn is_message_trigger<E: Event>(&self, event: &E) -> bool {
+        match *self {
+            ActionType::Message(ref action) => action.when.is_message_trigger(event),
+            ActionType::Exec(ref action) => action.when.is_message_trigger(event),
+            ActionType::File(ref action) => action.when.is_message_trigger(event),
+            ActionType::Webhook(ref action) => action.when.is_message_trigger(event),
+            ActionType::Tag(_) => true,
         }
     }
 }
@@ -40,12 +116,66 @@ impl<E, T> Action<E, T> for ActionType<T> where E: Event, T: Template<Event=E> {
 pub struct ExecCondition {
     pub on_opened: bool,
     pub on_closed: bool,
+    pub on_completed: bool,
+    pub on_timeout: bool,
+    pub on_renew_timeout: bool,
+    pub on_max_size: bool,
+    pub on_out_of_order: bool,
+    pub on_evicted: bool,
+    pub on_reopened: bool,
+    pub on_boundary: bool,
+    pub on_threshold: bool,
+    pub on_message: bool,
+    /// Limits `on_message` to the events matching one of these patterns of
+    /// the context. Every accepted event triggers it if it's empty. They are
+    /// deserialized by name and resolved to the patterns of the context on load.
+    pub on_message_patterns: Vec<Pattern>,
 }
 
 impl ExecCondition {
     pub fn new() -> ExecCondition {
         Default::default()
     }
+
+    /// Returns `true` if the action should be executed when a context is closed
+    /// for the given reason. `on_closed` matches every reason.
+    pub fn is_closing_trigger(&self, reason: CloseReason) -> bool {
+        self.on_closed ||
+        match reason {
+            CloseReason::Completed => self.on_completed,
+            CloseReason::Timeout => self.on_timeout,
+            CloseReason::RenewTimeout => self.on_renew_timeout,
+            CloseReason::MaxSize => self.on_max_size,
+            CloseReason::OutOfOrder => self.on_out_of_order,
+            CloseReason::Evicted => self.on_evicted,
+            CloseReason::Reopened => self.on_reopened,
+            CloseReason::Boundary => self.on_boundary,
+        }
+    }
+
+    /// Returns `true` if the action should be executed when `event` is accepted by the context.
+    pub fn is_message_trigger<E: Event>(&self, event: &E) -> bool {
+        self.on_message &&
+        (self.on_message_patterns.is_empty() ||
+         self.on_message_patterns.iter().any(|pattern| pattern.is_matching(event)))
+    }
+
+    /// Replaces the names in `on_message_patterns` with the patterns of the
+    /// context having the same name. Returns the first unknown name.
+    pub fn resolve_message_patterns<'a, I>(&mut self, subscriptions: I) -> Result<(), String>
+        where I: Iterator<Item=&'a Pattern> + Clone
+    {
+        let mut resolved = Vec::new();
+        for name in &self.on_message_patterns {
+            let len = resolved.len();
+            resolved.extend(subscriptions.clone().filter(|pattern| pattern.name() == name.name()).cloned());
+            if resolved.len() == len {
+                return Err(name.name().to_owned());
+            }
+        }
+        self.on_message_patterns = resolved;
+        Ok(())
+    }
 }
 
 impl Default for ExecCondition {
@@ -53,6 +183,17 @@ impl Default for ExecCondition {
         ExecCondition {
             on_opened: false,
             on_closed: true,
+            on_completed: false,
+            on_timeout: false,
+            on_renew_timeout: false,
+            on_max_size: false,
+            on_out_of_order: false,
+            on_evicted: false,
+            on_reopened: false,
+            on_boundary: false,
+            on_threshold: false,
+            on_message: false,
+            on_message_patterns: Vec::new(),
         }
     }
 }
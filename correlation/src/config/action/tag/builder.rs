@@ -0,0 +1,42 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::TagAction;
+
+use std::collections::BTreeMap;
+
+pub struct TagActionBuilder<T> {
+    uuid: String,
+    values: BTreeMap<String, T>,
+}
+
+impl<T> TagActionBuilder<T> {
+    pub fn new<U: Into<String>>(uuid: U) -> TagActionBuilder<T> {
+        TagActionBuilder {
+            uuid: uuid.into(),
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn values(mut self, values: BTreeMap<String, T>) -> TagActionBuilder<T> {
+        self.values = values;
+        self
+    }
+
+    pub fn pair<K: Into<String>, V: Into<T>>(mut self, key: K, value: V) -> TagActionBuilder<T> {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> TagAction<T> {
+        TagAction {
+            uuid: self.uuid,
+            values: self.values,
+        }
+    }
+}
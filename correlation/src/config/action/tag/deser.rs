@@ -0,0 +1,79 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::{TagAction, TagActionBuilder};
+
+use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+impl<T> Deserialize for TagAction<T> where T: Deserialize {
+    fn deserialize<D>(deserializer: &mut D) -> Result<TagAction<T>, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize_struct("TagAction", &[], TagActionVisitor(PhantomData))
+    }
+}
+
+enum Field {
+    Uuid,
+    Values,
+}
+
+impl Deserialize for Field {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+        where D: Deserializer
+    {
+        struct FieldVisitor;
+
+        impl Visitor for FieldVisitor {
+            type Value = Field;
+
+            fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                where E: Error
+            {
+                match value {
+                    "uuid" => Ok(Field::Uuid),
+                    "values" => Ok(Field::Values),
+                    _ => Err(Error::custom(format!("Unexpected field: {}", value))),
+                }
+            }
+        }
+
+        deserializer.deserialize(FieldVisitor)
+    }
+}
+
+struct TagActionVisitor<T>(PhantomData<T>);
+
+impl<T> Visitor for TagActionVisitor<T> where T: Deserialize {
+    type Value = TagAction<T>;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> Result<TagAction<T>, V::Error>
+        where V: MapVisitor
+    {
+        let mut uuid: Option<String> = None;
+        let mut values: BTreeMap<String, T> = BTreeMap::new();
+
+        while let Some(field) = try!(visitor.visit_key()) {
+            match field {
+                Field::Uuid => uuid = Some(try!(visitor.visit_value())),
+                Field::Values => values = try!(visitor.visit_value()),
+            }
+        }
+
+        let uuid = match uuid {
+            Some(uuid) => uuid,
+            None => return visitor.missing_field("uuid"),
+        };
+
+        try!(visitor.end());
+
+        Ok(TagActionBuilder::new(uuid).values(values).build())
+    }
+}
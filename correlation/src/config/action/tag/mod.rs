@@ -0,0 +1,104 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use action::Action;
+use conditions::CloseReason;
+use config::action::message::{InjectMode, CONTEXT_NAME, CONTEXT_UUID};
+use context::base::BaseContext;
+use state::State;
+use Alert;
+use Event;
+use Template;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+pub use self::builder::TagActionBuilder;
+
+mod builder;
+mod deser;
+#[cfg(test)]
+mod test;
+
+pub const CONTEXT_KEY: &'static str = "context_key";
+
+/// Annotates the events accepted by a context with the context they belong
+/// to. The tags are returned as alerts with `InjectMode::Tag`, their values
+/// should be written into the event which triggered them.
+pub struct TagAction<T> {
+    pub uuid: String,
+    pub values: BTreeMap<String, T>,
+}
+
+impl<T> TagAction<T> {
+    pub fn uuid(&self) -> &String {
+        &self.uuid
+    }
+    pub fn values(&self) -> &BTreeMap<String, T> {
+        &self.values
+    }
+
+    fn execute<E>(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) where E: Event, T: Template<Event=E> {
+        let template_context = context.template_context(state, None, Some(event));
+
+        let mut event = E::new(self.uuid.as_bytes(), b"");
+        event.set(CONTEXT_UUID.as_bytes(), template_context.context_id.as_bytes());
+        if let Some(name) = context.name() {
+            event.set(CONTEXT_NAME.as_bytes(), name.as_bytes());
+        }
+        if !state.key().is_empty() {
+            event.set(CONTEXT_KEY.as_bytes(), &format_key(state.key()));
+        }
+        let mut value = Vec::new();
+        for (k, v) in &self.values {
+            v.format(&template_context, &mut value);
+            event.set(k.as_bytes(), &value);
+            value.clear();
+        }
+        responder.push_back(Alert {
+            message: event,
+            inject_mode: InjectMode::Tag,
+            close_reason: None,
+        });
+    }
+}
+
+/// Renders the key of a state like `HOST=host1,USER=bob`.
+fn format_key(key: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut formatted = Vec::new();
+    for (i, &(ref name, ref value)) in key.iter().enumerate() {
+        if i > 0 {
+            formatted.push(b',');
+        }
+        formatted.extend_from_slice(name);
+        formatted.push(b'=');
+        formatted.extend_from_slice(value);
+    }
+    formatted
+}
+
+impl<T> From<TagAction<T>> for super::ActionType<T> {
+    fn from(action: TagAction<T>) -> super::ActionType<T> {
+        super::ActionType::Tag(action)
+    }
+}
+
+impl<E, T> Action<E, T> for TagAction<T> where E: Event, T: Template<Event=E> {
+    fn on_opened(&self, _: &State<E>, _: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {}
+
+    fn on_closed(&self, _: &State<E>, _: &BaseContext<E, T>, _: CloseReason, _: &mut VecDeque<Alert<E>>) {}
+
+    fn on_threshold(&self, _: &State<E>, _: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {}
+
+    fn on_message(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, responder: &mut VecDeque<Alert<E>>) {
+        trace!("TagAction: on_message()");
+        self.execute(event, state, context, responder);
+    }
+
+    fn on_timer(&self, _: &Duration, _: &mut VecDeque<Alert<E>>) {}
+}
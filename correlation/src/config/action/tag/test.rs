@@ -0,0 +1,79 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use config::action::message::{InjectMode, CONTEXT_NAME, CONTEXT_UUID};
+use config::action::tag::{TagActionBuilder, CONTEXT_KEY};
+use conditions::{ConditionsBuilder, Reopen};
+use context::BaseContext;
+use message::MessageBuilder;
+use state::State;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use uuid::Uuid;
+use Event;
+use Message;
+use test_utils::{MockTemplate, BaseContextBuilder, login_context_builder};
+
+fn base_context() -> BaseContext<Message, MockTemplate> {
+    let action = TagActionBuilder::<MockTemplate>::new("uuid")
+                     .pair("len", MockTemplate::context_len())
+                     .build();
+    login_context_builder()
+        .patterns(vec!["login"])
+        .actions(vec![action.into()])
+        .build()
+}
+
+#[test]
+fn test_given_context_with_tag_action_when_it_accepts_a_message_then_the_message_is_tagged() {
+    let mut responder = VecDeque::default();
+    let base_context = base_context();
+    let mut state = State::with_key(vec![(b"HOST".to_vec(), b"host1".to_vec()), (b"USER".to_vec(), b"bob".to_vec())]);
+
+    base_context.on_message(MessageBuilder::new("login", "message").build(), &mut state, &mut responder);
+    base_context.on_message(MessageBuilder::new("login", "message").build(), &mut state, &mut responder);
+
+    assert_eq!(2, responder.len());
+    let tag = &responder[1];
+    assert_eq!(InjectMode::Tag, tag.inject_mode);
+    assert_eq!(Some(&b"LOGIN"[..]), tag.message.get(CONTEXT_NAME.as_bytes()));
+    assert_eq!(Some(&b"2f34112c-6fc8-406b-a6f0-78158ca724b6"[..]), tag.message.get(CONTEXT_UUID.as_bytes()));
+    assert_eq!(Some(&b"HOST=host1,USER=bob"[..]), tag.message.get(CONTEXT_KEY.as_bytes()));
+    assert_eq!(Some(&b"2"[..]), tag.message.get(b"len"));
+}
+
+#[test]
+fn test_given_context_with_tag_action_when_it_does_not_accept_a_message_then_the_message_is_not_tagged() {
+    let mut responder = VecDeque::default();
+    let base_context = base_context();
+    let mut state = State::new();
+
+    base_context.on_message(MessageBuilder::new("logout", "message").build(), &mut state, &mut responder);
+
+    assert_eq!(0, responder.len());
+}
+
+#[test]
+fn test_given_context_with_tag_action_when_a_reopening_message_is_ignored_then_the_message_is_not_tagged() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .opens(vec!["login"])
+                         .on_reopen(Reopen::Ignore)
+                         .build();
+    let action = TagActionBuilder::<MockTemplate>::new("uuid").build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                           .actions(vec![action.into()])
+                           .build();
+    let mut state = State::new();
+
+    base_context.on_message(MessageBuilder::new("login", "message").build(), &mut state, &mut responder);
+    base_context.on_message(MessageBuilder::new("login", "message").build(), &mut state, &mut responder);
+
+    assert_eq!(1, responder.len());
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::{WebhookAction, BACKOFF_DEFAULT, QUEUE_SIZE_DEFAULT, RETRIES_DEFAULT, TIMEOUT_DEFAULT};
+use super::delivery::{Delivery, Endpoint};
+use super::http::Url;
+use config::action::ExecCondition;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub struct WebhookActionBuilder<T> {
+    uuid: String,
+    name: Option<String>,
+    message: Option<T>,
+    values: BTreeMap<String, T>,
+    endpoint: Endpoint,
+    queue_size: usize,
+    when: ExecCondition,
+}
+
+impl<T> WebhookActionBuilder<T> {
+    pub fn new<U: Into<String>>(uuid: U, url: Url) -> WebhookActionBuilder<T> {
+        WebhookActionBuilder {
+            uuid: uuid.into(),
+            name: None,
+            message: None,
+            values: BTreeMap::new(),
+            endpoint: Endpoint {
+                url: url,
+                headers: BTreeMap::new(),
+                timeout: Duration::from_millis(TIMEOUT_DEFAULT),
+                retries: RETRIES_DEFAULT,
+                backoff: Duration::from_millis(BACKOFF_DEFAULT),
+            },
+            queue_size: QUEUE_SIZE_DEFAULT,
+            when: ExecCondition::default(),
+        }
+    }
+
+    pub fn name<S: Into<String>>(mut self, name: Option<S>) -> WebhookActionBuilder<T> {
+        self.name = name.map(|name| name.into());
+        self
+    }
+
+    pub fn message<M: Into<T>>(mut self, message: M) -> WebhookActionBuilder<T> {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn values(mut self, values: BTreeMap<String, T>) -> WebhookActionBuilder<T> {
+        self.values = values;
+        self
+    }
+
+    pub fn pair<K: Into<String>, V: Into<T>>(mut self, key: K, value: V) -> WebhookActionBuilder<T> {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn headers(mut self, headers: BTreeMap<String, String>) -> WebhookActionBuilder<T> {
+        self.endpoint.headers = headers;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> WebhookActionBuilder<T> {
+        self.endpoint.timeout = timeout;
+        self
+    }
+
+    pub fn retries(mut self, retries: usize) -> WebhookActionBuilder<T> {
+        self.endpoint.retries = retries;
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Duration) -> WebhookActionBuilder<T> {
+        self.endpoint.backoff = backoff;
+        self
+    }
+
+    pub fn queue_size(mut self, queue_size: usize) -> WebhookActionBuilder<T> {
+        self.queue_size = queue_size;
+        self
+    }
+
+    pub fn when(mut self, when: ExecCondition) -> WebhookActionBuilder<T> {
+        self.when = when;
+        self
+    }
+
+    pub fn build(self) -> WebhookAction<T> {
+        WebhookAction {
+            delivery: Delivery::new(self.queue_size),
+            uuid: self.uuid,
+            name: self.name,
+            message: self.message,
+            values: self.values,
+            endpoint: self.endpoint,
+            queue_size: self.queue_size,
+            when: self.when,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::MAX_BACKOFF;
+use super::http::{self, Url};
+
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+/// Where and how the alerts are delivered.
+#[derive(Clone, Debug)]
+pub struct Endpoint {
+    pub url: Url,
+    pub headers: BTreeMap<String, String>,
+    pub timeout: Duration,
+    pub retries: usize,
+    pub backoff: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub delivered: AtomicUsize,
+    pub failed: AtomicUsize,
+    pub dropped: AtomicUsize,
+}
+
+/// Sends the alerts to the endpoint from a background thread, so the
+/// correlator isn't blocked by the network.
+pub struct Delivery {
+    queue_size: usize,
+    queue: RefCell<Option<SyncSender<Vec<u8>>>>,
+    stats: Arc<Stats>,
+}
+
+impl Delivery {
+    pub fn new(queue_size: usize) -> Delivery {
+        Delivery {
+            queue_size: queue_size,
+            queue: RefCell::new(None),
+            stats: Arc::new(Stats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Queues `body` for delivery. It's dropped if the queue is full.
+    pub fn send(&self, endpoint: &Endpoint, body: Vec<u8>) {
+        let mut queue = self.queue.borrow_mut();
+        if queue.is_none() {
+            *queue = self.start(endpoint);
+        }
+        let result = match *queue {
+            Some(ref sender) => sender.try_send(body),
+            None => {
+                self.stats.failed.fetch_add(1, Ordering::SeqCst);
+                return;
+            }
+        };
+        match result {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                warn!("WebhookAction: the queue is full, dropping alert; url={}", &endpoint.url);
+                self.stats.dropped.fetch_add(1, Ordering::SeqCst);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                error!("WebhookAction: the delivery thread is stopped, dropping alert; url={}", &endpoint.url);
+                self.stats.dropped.fetch_add(1, Ordering::SeqCst);
+                *queue = None;
+            }
+        }
+    }
+
+    fn start(&self, endpoint: &Endpoint) -> Option<SyncSender<Vec<u8>>> {
+        let (sender, receiver) = sync_channel(self.queue_size);
+        let endpoint = endpoint.clone();
+        let stats = self.stats.clone();
+        let spawned = thread::Builder::new()
+                          .name("webhook-action".to_owned())
+                          .spawn(move || deliver(endpoint, receiver, stats));
+        match spawned {
+            Ok(_) => Some(sender),
+            Err(error) => {
+                error!("WebhookAction: failed to start the delivery thread; error={}", error);
+                None
+            }
+        }
+    }
+}
+
+/// Runs until the action is dropped.
+fn deliver(endpoint: Endpoint, receiver: Receiver<Vec<u8>>, stats: Arc<Stats>) {
+    while let Ok(body) = receiver.recv() {
+        let max_backoff = Duration::from_millis(MAX_BACKOFF);
+        let mut backoff = cmp::min(endpoint.backoff, max_backoff);
+        let mut attempt = 0;
+        loop {
+            let result = http::post(&endpoint.url, &endpoint.headers, &body, endpoint.timeout);
+            let retriable = match result {
+                Ok(status) if status >= 200 && status < 300 => {
+                    stats.delivered.fetch_add(1, Ordering::SeqCst);
+                    break;
+                }
+                Ok(status) => {
+                    warn!("WebhookAction: unexpected HTTP status; url={} status={}", &endpoint.url, status);
+                    status >= 500 || status == 408 || status == 429
+                }
+                Err(error) => {
+                    warn!("WebhookAction: failed to post alert; url={} error={}", &endpoint.url, error);
+                    true
+                }
+            };
+            if !retriable || attempt >= endpoint.retries {
+                error!("WebhookAction: giving up delivering alert; url={} attempts={}", &endpoint.url, attempt + 1);
+                stats.failed.fetch_add(1, Ordering::SeqCst);
+                break;
+            }
+            attempt += 1;
+            thread::sleep(backoff);
+            backoff = backoff.checked_mul(2).map_or(max_backoff, |backoff| cmp::min(backoff, max_backoff));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use super::{WebhookAction, WebhookActionBuilder, MAX_RETRIES};
+use super::http::Url;
+use config::action::ExecCondition;
+use duration::SerializableDuration;
+
+use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+impl<T> Deserialize for WebhookAction<T> where T: Deserialize {
+    fn deserialize<D>(deserializer: &mut D) -> Result<WebhookAction<T>, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize_struct("WebhookAction", &[], WebhookActionVisitor(PhantomData))
+    }
+}
+
+enum Field {
+    Uuid,
+    Name,
+    Url,
+    Message,
+    Values,
+    Headers,
+    Timeout,
+    Retries,
+    Backoff,
+    QueueSize,
+    When,
+}
+
+impl Deserialize for Field {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+        where D: Deserializer
+    {
+        struct FieldVisitor;
+
+        impl Visitor for FieldVisitor {
+            type Value = Field;
+
+            fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                where E: Error
+            {
+                match value {
+                    "uuid" => Ok(Field::Uuid),
+                    "name" => Ok(Field::Name),
+                    "url" => Ok(Field::Url),
+                    "message" => Ok(Field::Message),
+                    "values" => Ok(Field::Values),
+                    "headers" => Ok(Field::Headers),
+                    "timeout" => Ok(Field::Timeout),
+                    "retries" => Ok(Field::Retries),
+                    "backoff" => Ok(Field::Backoff),
+                    "queue_size" => Ok(Field::QueueSize),
+                    "when" => Ok(Field::When),
+                    _ => Err(Error::custom(format!("Unexpected field: {}", value))),
+                }
+            }
+        }
+
+        deserializer.deserialize(FieldVisitor)
+    }
+}
+
+struct WebhookActionVisitor<T>(PhantomData<T>);
+
+impl<T> Visitor for WebhookActionVisitor<T> where T: Deserialize {
+    type Value = WebhookAction<T>;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> Result<WebhookAction<T>, V::Error>
+        where V: MapVisitor
+    {
+        let mut uuid: Option<String> = None;
+        let mut name: Option<String> = None;
+        let mut url: Option<String> = None;
+        let mut message: Option<T> = None;
+        let mut values: BTreeMap<String, T> = BTreeMap::new();
+        let mut headers: BTreeMap<String, String> = BTreeMap::new();
+        let mut timeout: Option<SerializableDuration> = None;
+        let mut retries: Option<usize> = None;
+        let mut backoff: Option<SerializableDuration> = None;
+        let mut queue_size: Option<usize> = None;
+        let mut when: ExecCondition = ExecCondition::new();
+
+        while let Some(field) = try!(visitor.visit_key()) {
+            match field {
+                Field::Uuid => uuid = Some(try!(visitor.visit_value())),
+                Field::Name => name = Some(try!(visitor.visit_value())),
+                Field::Url => url = Some(try!(visitor.visit_value())),
+                Field::Message => message = Some(try!(visitor.visit_value())),
+                Field::Values => values = try!(visitor.visit_value()),
+                Field::Headers => headers = try!(visitor.visit_value()),
+                Field::Timeout => timeout = Some(try!(visitor.visit_value())),
+                Field::Retries => retries = Some(try!(visitor.visit_value())),
+                Field::Backoff => backoff = Some(try!(visitor.visit_value())),
+                Field::QueueSize => queue_size = Some(try!(visitor.visit_value())),
+                Field::When => when = try!(visitor.visit_value()),
+            }
+        }
+
+        let uuid = match uuid {
+            Some(uuid) => uuid,
+            None => return visitor.missing_field("uuid"),
+        };
+
+        let url = match url {
+            Some(url) => url,
+            None => {
+                error!("Missing 'url' field: uuid={}", &uuid);
+                return visitor.missing_field("url");
+            }
+        };
+
+        let url = match Url::parse(&url) {
+            Ok(url) => url,
+            Err(error) => return Err(V::Error::custom(error)),
+        };
+
+        if let Some(ref timeout) = timeout {
+            if timeout.0 == Duration::from_millis(0) {
+                return Err(V::Error::custom("timeout must be greater than 0"));
+            }
+        }
+        if let Some(retries) = retries {
+            if retries > MAX_RETRIES {
+                return Err(V::Error::custom(format!("retries can't be greater than {}", MAX_RETRIES)));
+            }
+        }
+        if queue_size == Some(0) {
+            return Err(V::Error::custom("queue_size must be greater than 0"));
+        }
+
+        try!(visitor.end());
+
+        let mut builder = WebhookActionBuilder::new(uuid, url)
+                              .name(name)
+                              .values(values)
+                              .headers(headers)
+                              .when(when);
+        if let Some(message) = message {
+            builder = builder.message(message);
+        }
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout.0);
+        }
+        if let Some(retries) = retries {
+            builder = builder.retries(retries);
+        }
+        if let Some(backoff) = backoff {
+            builder = builder.backoff(backoff.0);
+        }
+        if let Some(queue_size) = queue_size {
+            builder = builder.queue_size(queue_size);
+        }
+        Ok(builder.build())
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::time::Duration;
+
+const HTTP_PREFIX: &'static str = "http://";
+const HTTP_DEFAULT_PORT: u16 = 80;
+
+/// A plain HTTP URL, HTTPS isn't supported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Url {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Url, String> {
+        if !url.starts_with(HTTP_PREFIX) {
+            return Err(format!("Only http:// URLs are supported: {}", url));
+        }
+        let rest = &url[HTTP_PREFIX.len()..];
+        let (authority, path) = match rest.find('/') {
+            Some(pos) => (&rest[..pos], &rest[pos..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(pos) => {
+                match u16::from_str(&authority[pos + 1..]) {
+                    Ok(port) => (&authority[..pos], port),
+                    Err(_) => return Err(format!("Invalid port in URL: {}", url)),
+                }
+            }
+            None => (authority, HTTP_DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in URL: {}", url));
+        }
+        Ok(Url {
+            host: host.to_owned(),
+            port: port,
+            path: path.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}:{}{}", HTTP_PREFIX, &self.host, self.port, &self.path)
+    }
+}
+
+/// POSTs `body` as JSON and returns the status code of the response.
+pub fn post(url: &Url, headers: &BTreeMap<String, String>, body: &[u8], timeout: Duration) -> io::Result<u16> {
+    let addresses = try!((&url.host[..], url.port).to_socket_addrs());
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "The host can't be resolved");
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => return send(stream, url, headers, body, timeout),
+            Err(error) => last_error = error,
+        }
+    }
+    Err(last_error)
+}
+
+fn send(mut stream: TcpStream, url: &Url, headers: &BTreeMap<String, String>, body: &[u8], timeout: Duration) -> io::Result<u16> {
+    try!(stream.set_read_timeout(Some(timeout)));
+    try!(stream.set_write_timeout(Some(timeout)));
+
+    let mut request = Vec::new();
+    try!(write!(request, "POST {} HTTP/1.1\r\n", &url.path));
+    try!(write!(request, "Host: {}:{}\r\n", &url.host, url.port));
+    try!(write!(request, "Content-Type: application/json\r\n"));
+    try!(write!(request, "Content-Length: {}\r\n", body.len()));
+    try!(write!(request, "Connection: close\r\n"));
+    for (name, value) in headers {
+        try!(write!(request, "{}: {}\r\n", name, value));
+    }
+    try!(write!(request, "\r\n"));
+    request.extend_from_slice(body);
+    try!(stream.write_all(&request));
+    try!(stream.flush());
+
+    let mut status_line = String::new();
+    try!(BufReader::new(stream).read_line(&mut status_line));
+    // HTTP/1.1 200 OK
+    status_line.split_whitespace()
+               .nth(1)
+               .and_then(|status| u16::from_str(status).ok())
+               .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid HTTP status line: {}", status_line.trim())))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Url;
+
+    #[test]
+    fn test_given_url_when_it_is_parsed_then_we_get_the_host_the_port_and_the_path() {
+        let expected = Url {
+            host: "incident.example.com".to_owned(),
+            port: 8080,
+            path: "/api/alerts?source=correlation".to_owned(),
+        };
+        assert_eq!(Ok(expected), Url::parse("http://incident.example.com:8080/api/alerts?source=correlation"));
+        assert_eq!(80, Url::parse("http://localhost").unwrap().port);
+        assert_eq!("/", Url::parse("http://localhost").unwrap().path);
+        assert_eq!("http://localhost:80/", Url::parse("http://localhost").unwrap().to_string());
+        let _ = Url::parse("https://localhost/").err().expect("Successfully parsed an HTTPS URL");
+        let _ = Url::parse("http://localhost:http/").err().expect("Successfully parsed an invalid port");
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use action::Action;
+use conditions::CloseReason;
+use config::action::json::JsonAlert;
+use context::base::BaseContext;
+use state::State;
+use Alert;
+use Event;
+use Template;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use super::ExecCondition;
+
+pub use self::builder::WebhookActionBuilder;
+pub use self::delivery::Endpoint;
+pub use self::http::Url;
+use self::delivery::Delivery;
+
+mod builder;
+mod delivery;
+mod deser;
+mod http;
+#[cfg(test)]
+mod test;
+
+pub const TIMEOUT_DEFAULT: u64 = 5000;
+pub const RETRIES_DEFAULT: usize = 3;
+pub const BACKOFF_DEFAULT: u64 = 1000;
+pub const QUEUE_SIZE_DEFAULT: usize = 1000;
+pub const MAX_RETRIES: usize = 100;
+/// The doubled `backoff` stops growing at this many milliseconds.
+pub const MAX_BACKOFF: u64 = 60000;
+
+/// POSTs the alerts as JSON objects to an HTTP endpoint.
+pub struct WebhookAction<T> {
+    pub uuid: String,
+    pub name: Option<String>,
+    pub message: Option<T>,
+    pub values: BTreeMap<String, T>,
+    pub endpoint: Endpoint,
+    /// The maximal number of alerts waiting for delivery, the new ones are dropped over it.
+    pub queue_size: usize,
+    pub when: ExecCondition,
+    delivery: Delivery,
+}
+
+impl<T> WebhookAction<T> {
+    pub fn uuid(&self) -> &String {
+        &self.uuid
+    }
+    pub fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+    pub fn url(&self) -> &Url {
+        &self.endpoint.url
+    }
+    pub fn when(&self) -> &ExecCondition {
+        &self.when
+    }
+    /// The number of alerts accepted by the endpoint.
+    pub fn delivered(&self) -> usize {
+        self.delivery.stats().delivered.load(Ordering::SeqCst)
+    }
+    /// The number of alerts which couldn't be delivered after the retries.
+    pub fn failed(&self) -> usize {
+        self.delivery.stats().failed.load(Ordering::SeqCst)
+    }
+    /// The number of alerts dropped because the queue was full.
+    pub fn dropped(&self) -> usize {
+        self.delivery.stats().dropped.load(Ordering::SeqCst)
+    }
+
+    fn execute<E>(&self, state: &State<E>, context: &BaseContext<E, T>, reason: Option<CloseReason>, event: Option<&E>) where E: Event, T: Template<Event=E> {
+        let template_context = context.template_context(state, reason, event);
+
+        let alert = JsonAlert {
+            uuid: &self.uuid,
+            name: self.name.as_ref(),
+            message: self.message.as_ref(),
+            values: &self.values,
+        };
+        self.delivery.send(&self.endpoint, alert.render(&template_context, context));
+    }
+}
+
+impl<T> From<WebhookAction<T>> for super::ActionType<T> {
+    fn from(action: WebhookAction<T>) -> super::ActionType<T> {
+        super::ActionType::Webhook(action)
+    }
+}
+
+impl<E, T> Action<E, T> for WebhookAction<T> where E: Event, T: Template<Event=E> {
+    fn on_opened(&self, state: &State<E>, context: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {
+        if self.when.on_opened {
+            trace!("WebhookAction: on_opened()");
+            self.execute(state, context, None, None);
+        }
+    }
+
+    fn on_closed(&self, state: &State<E>, context: &BaseContext<E, T>, reason: CloseReason, _: &mut VecDeque<Alert<E>>) {
+        if self.when.is_closing_trigger(reason) {
+            trace!("WebhookAction: on_closed(); reason={}", reason.as_str());
+            self.execute(state, context, Some(reason), None);
+        }
+    }
+
+    fn on_threshold(&self, state: &State<E>, context: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {
+        if self.when.on_threshold {
+            trace!("WebhookAction: on_threshold()");
+            self.execute(state, context, None, None);
+        }
+    }
+
+    fn on_message(&self, event: &E, state: &State<E>, context: &BaseContext<E, T>, _: &mut VecDeque<Alert<E>>) {
+        if self.when.is_message_trigger(event) {
+            trace!("WebhookAction: on_message()");
+            self.execute(state, context, None, Some(event));
+        }
+    }
+
+    fn on_timer(&self, _: &Duration, _: &mut VecDeque<Alert<E>>) {}
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use config::action::webhook::{Url, WebhookAction, WebhookActionBuilder};
+
+use serde_json::{from_slice, from_str, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use test_utils::{MockTemplate, close};
+
+struct Request {
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Stands in for an HTTP server: answers the requests with the given status
+/// codes and sends the received requests back to the test.
+fn serve(statuses: Vec<u16>) -> (String, Receiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for status in statuses {
+            let mut stream = listener.accept().unwrap().0;
+            let mut headers = BTreeMap::new();
+            let mut body = Vec::new();
+            {
+                let mut reader = BufReader::new(&mut stream);
+                let mut line = String::new();
+                let _ = reader.read_line(&mut line);
+                loop {
+                    line.clear();
+                    let _ = reader.read_line(&mut line);
+                    let line = line.trim();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(pos) = line.find(':') {
+                        headers.insert(line[..pos].to_lowercase(), line[pos + 1..].trim().to_owned());
+                    }
+                }
+                let length = headers.get("content-length").and_then(|length| usize::from_str(length).ok()).unwrap_or(0);
+                body.resize(length, 0);
+                let _ = reader.read_exact(&mut body);
+            }
+            let _ = write!(stream, "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            let _ = sender.send(Request {
+                headers: headers,
+                body: body,
+            });
+        }
+    });
+    (url, receiver)
+}
+
+fn wait_until<F: Fn() -> bool>(condition: F) {
+    let started = Instant::now();
+    while !condition() {
+        assert!(started.elapsed() < Duration::from_secs(10), "Timed out waiting for the delivery");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_given_webhook_action_when_the_endpoint_fails_first_then_the_alert_is_delivered_by_a_retry() {
+    let (url, requests) = serve(vec![500, 200]);
+    let action = WebhookActionBuilder::<MockTemplate>::new("uuid", Url::parse(&url).unwrap())
+                     .name(Some("ALERT"))
+                     .message(MockTemplate::literal(b"message"))
+                     .pair("reason", MockTemplate::close_reason())
+                     .headers(btreemap!{"X-Api-Key".to_owned() => "secret".to_owned()})
+                     .backoff(Duration::from_millis(10))
+                     .build();
+
+    assert_true!(close(&action).is_empty());
+    wait_until(|| action.delivered() == 1);
+    assert_eq!(0, action.failed());
+
+    let first = requests.recv().unwrap();
+    let second = requests.recv().unwrap();
+    assert_eq!(first.body, second.body);
+    assert_eq!(Some(&"secret".to_owned()), second.headers.get("x-api-key"));
+    assert_eq!(Some(&"application/json".to_owned()), second.headers.get("content-type"));
+    let alert: Value = from_slice(&second.body).unwrap();
+    assert_eq!(Some("uuid"), alert.find("uuid").and_then(Value::as_str));
+    assert_eq!(Some("ALERT"), alert.find("name").and_then(Value::as_str));
+    assert_eq!(Some("message"), alert.find("message").and_then(Value::as_str));
+    assert_eq!(Some("timeout"), alert.find("reason").and_then(Value::as_str));
+    assert_eq!(Some("2f34112c-6fc8-406b-a6f0-78158ca724b6"),
+               alert.find("context_uuid").and_then(Value::as_str));
+}
+
+#[test]
+fn test_given_webhook_action_when_the_endpoint_rejects_the_alert_then_it_is_not_retried() {
+    let (url, requests) = serve(vec![400]);
+    let action = WebhookActionBuilder::<MockTemplate>::new("uuid", Url::parse(&url).unwrap())
+                     .backoff(Duration::from_millis(10))
+                     .build();
+
+    assert_true!(close(&action).is_empty());
+    wait_until(|| action.failed() == 1);
+    assert_eq!(0, action.delivered());
+    let _ = requests.recv().unwrap();
+    assert!(requests.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
+#[test]
+fn test_given_webhook_action_when_the_endpoint_is_unreachable_then_the_alert_fails_after_the_retries() {
+    let url = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/alerts", listener.local_addr().unwrap())
+    };
+    let action = WebhookActionBuilder::<MockTemplate>::new("uuid", Url::parse(&url).unwrap())
+                     .retries(1)
+                     .backoff(Duration::from_millis(10))
+                     .build();
+
+    assert_true!(close(&action).is_empty());
+    wait_until(|| action.failed() == 1);
+    assert_eq!(0, action.delivered());
+}
+
+#[test]
+fn test_given_webhook_action_in_json_when_it_is_deserialized_then_we_get_the_expected_result() {
+    let json = r#"
+    {
+        "uuid": "uuid",
+        "name": "ALERT",
+        "url": "http://localhost:8080/alerts",
+        "message": "message",
+        "headers": { "Authorization": "Bearer token" },
+        "timeout": 1000,
+        "retries": 5,
+        "backoff": 200,
+        "queue_size": 10
+    }
+    "#;
+    let action = from_str::<WebhookAction<String>>(json).unwrap();
+    assert_eq!("uuid", action.uuid());
+    assert_eq!(Some(&"ALERT".to_owned()), action.name());
+    assert_eq!("http://localhost:8080/alerts", action.url().to_string());
+    assert_eq!(Some(&"Bearer token".to_owned()), action.endpoint.headers.get("Authorization"));
+    assert_eq!(Duration::from_millis(1000), action.endpoint.timeout);
+    assert_eq!(5, action.endpoint.retries);
+    assert_eq!(Duration::from_millis(200), action.endpoint.backoff);
+    assert_eq!(10, action.queue_size);
+
+    let _ = from_str::<WebhookAction<String>>(r#"{ "uuid": "uuid", "url": "https://localhost/" }"#)
+                .err()
+                .expect("Successfully deserialized an HTTPS URL");
+    let _ = from_str::<WebhookAction<String>>(r#"{ "uuid": "uuid" }"#).err().expect("Successfully deserialized a webhook action without url");
+}
+
+#[test]
+fn test_given_webhook_action_in_json_when_its_limits_are_invalid_then_it_is_not_deserialized() {
+    let _ = from_str::<WebhookAction<String>>(r#"{ "uuid": "uuid", "url": "http://localhost/", "timeout": 0 }"#)
+                .err()
+                .expect("Successfully deserialized a webhook action with zero timeout");
+    let _ = from_str::<WebhookAction<String>>(r#"{ "uuid": "uuid", "url": "http://localhost/", "queue_size": 0 }"#)
+                .err()
+                .expect("Successfully deserialized a webhook action with zero queue_size");
+    let _ = from_str::<WebhookAction<String>>(r#"{ "uuid": "uuid", "url": "http://localhost/", "retries": 101 }"#)
+                .err()
+                .expect("Successfully deserialized a webhook action with too many retries");
+    let action = from_str::<WebhookAction<String>>(r#"{ "uuid": "uuid", "url": "http://localhost/", "retries": 100 }"#)
+                     .unwrap();
+    assert_eq!(100, action.endpoint.retries);
+}
@@ -6,10 +6,15 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use config::ContextConfig;
-use serde::de::{Deserialize, Deserializer, MapVisitor, Error, Visitor};
+use config::{ContextConfig, ContextId};
+use config::action::ActionType;
+use conditions::Conditions;
+use pattern::Pattern;
+use serde::de::{Deserialize, Deserializer, MapVisitor, SeqVisitor, Error, Visitor};
+use serde::de::value::ValueDeserializer;
 
 use uuid::Uuid;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 
 const FIELDS: &'static [&'static str] = &["name", "uuid", "conditions", "actions"];
@@ -29,6 +34,11 @@ enum Field {
     ContextId,
     Actions,
     Patterns,
+    MaxInstances,
+    Eviction,
+    MissingKey,
+    KeyMapping,
+    Aggregates,
 }
 
 impl Deserialize for Field {
@@ -50,6 +60,11 @@ impl Deserialize for Field {
                     "context_id" => Ok(Field::ContextId),
                     "actions" => Ok(Field::Actions),
                     "patterns" => Ok(Field::Patterns),
+                    "max_instances" => Ok(Field::MaxInstances),
+                    "eviction" => Ok(Field::Eviction),
+                    "missing_key" => Ok(Field::MissingKey),
+                    "key_mapping" => Ok(Field::KeyMapping),
+                    "aggregates" => Ok(Field::Aggregates),
                     _ => Err(Error::custom(format!("Unexpected field: {}", value))),
                 }
             }
@@ -91,9 +106,14 @@ impl<T> Visitor for ContextVisitor<T> where T: Deserialize {
         let mut name = None;
         let mut uuid: Option<String> = None;
         let mut conditions = None;
-        let mut context_id: Option<Vec<String>> = None;
+        let mut context_id: Option<ContextId<T>> = None;
         let mut actions = None;
         let mut patterns = None;
+        let mut max_instances = None;
+        let mut eviction = None;
+        let mut missing_key = None;
+        let mut key_mapping = None;
+        let mut aggregates = None;
 
         while let Some(field) = try!(visitor.visit_key()) {
             match field {
@@ -103,14 +123,47 @@ impl<T> Visitor for ContextVisitor<T> where T: Deserialize {
                 Field::ContextId => context_id = Some(try!(visitor.visit_value())),
                 Field::Actions => actions = Some(try!(visitor.visit_value())),
                 Field::Patterns => patterns = Some(try!(visitor.visit_value())),
+                Field::MaxInstances => max_instances = Some(try!(visitor.visit_value())),
+                Field::Eviction => eviction = Some(try!(visitor.visit_value())),
+                Field::MissingKey => missing_key = Some(try!(visitor.visit_value())),
+                Field::KeyMapping => key_mapping = Some(try!(visitor.visit_value())),
+                Field::Aggregates => aggregates = Some(try!(visitor.visit_value())),
             }
         }
 
         try!(visitor.end());
 
         let uuid = try!(ContextVisitor::<T>::parse_uuid::<V>(uuid));
-        let actions = actions.unwrap_or_default();
-        let conditions = try!(conditions.ok_or(V::Error::missing_field("conditions")));
+        let mut actions: Vec<ActionType<T>> = actions.unwrap_or_default();
+        let conditions: Conditions = try!(conditions.ok_or(V::Error::missing_field("conditions")));
+        let patterns: Vec<Pattern> = patterns.unwrap_or_default();
+        let key_mapping: BTreeMap<String, BTreeMap<String, String>> = key_mapping.unwrap_or_default();
+
+        if let Some(ContextId::Template(_)) = context_id {
+            if !key_mapping.is_empty() {
+                return Err(V::Error::custom("key_mapping can't be used when context_id is a template"));
+            }
+        }
+
+        // a context without context_id and sliding is linear, it has only one state
+        if context_id.is_none() && !conditions.sliding {
+            let keyed_fields = [
+                ("max_instances", max_instances.is_some()),
+                ("eviction", eviction.is_some()),
+                ("missing_key", missing_key.is_some()),
+                ("key_mapping", !key_mapping.is_empty()),
+            ];
+            if let Some(&(field, _)) = keyed_fields.iter().find(|&&(_, is_set)| is_set) {
+                return Err(V::Error::custom(format!("{} can only be used with context_id or sliding", field)));
+            }
+        }
+
+        for when in actions.iter_mut().filter_map(ActionType::when_mut) {
+            let subscriptions = patterns.iter().chain(conditions.opens.iter()).chain(conditions.closes.iter());
+            if let Err(name) = when.resolve_message_patterns(subscriptions) {
+                return Err(V::Error::custom(format!("on_message_patterns refers to an unknown pattern: {}", name)));
+            }
+        }
 
         Ok(ContextConfig {
             name: name,
@@ -118,17 +171,57 @@ impl<T> Visitor for ContextVisitor<T> where T: Deserialize {
             conditions: conditions,
             context_id: context_id,
             actions: actions,
-            patterns: patterns.unwrap_or_default()
+            patterns: patterns,
+            max_instances: max_instances,
+            eviction: eviction.unwrap_or_default(),
+            missing_key: missing_key.unwrap_or_default(),
+            key_mapping: key_mapping,
+            aggregates: aggregates.unwrap_or_default(),
         })
     }
 }
 
+impl<T> Deserialize for ContextId<T> where T: Deserialize {
+    fn deserialize<D>(deserializer: &mut D) -> Result<ContextId<T>, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize(ContextIdVisitor(PhantomData))
+    }
+}
+
+struct ContextIdVisitor<T> (PhantomData<T>);
+
+impl<T> Visitor for ContextIdVisitor<T> where T: Deserialize {
+    type Value = ContextId<T>;
+
+    fn visit_str<E>(&mut self, value: &str) -> Result<ContextId<T>, E>
+        where E: Error
+    {
+        let mut deserializer = value.into_deserializer();
+        let template = try!(T::deserialize(&mut deserializer));
+        Ok(ContextId::Template(template))
+    }
+
+    fn visit_seq<V>(&mut self, mut visitor: V) -> Result<ContextId<T>, V::Error>
+        where V: SeqVisitor
+    {
+        let mut keys = Vec::new();
+        while let Some(key) = try!(visitor.visit()) {
+            keys.push(key);
+        }
+        try!(visitor.end());
+        Ok(ContextId::Keys(keys))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use config::action::{ActionType, ExecCondition};
     use config::action::message::MessageActionBuilder;
     use conditions::ConditionsBuilder;
-    use config::ContextConfig;
+    use config::{ContextConfig, ContextId};
+    use pattern::PatternKind;
+    use {Eviction, MissingKey};
     use serde_json::from_str;
     use uuid::Uuid;
     use std::time::Duration;
@@ -172,6 +265,7 @@ mod test {
         let expected_exec_cond = ExecCondition {
             on_opened: false,
             on_closed: true,
+            ..ExecCondition::default()
         };
         let expected_actions = vec![ActionType::Message(MessageActionBuilder::<String>::new("uuid1",
                                                                                   "message")
@@ -253,12 +347,29 @@ mod test {
             }
         }
         "#;
-        let expected_context_id = vec!["HOST".to_owned(), "PROGRAM".to_owned()];
+        let expected_context_id = ContextId::Keys(vec!["HOST".to_owned(), "PROGRAM".to_owned()]);
         let result = from_str::<ContextConfig<String>>(text);
         let context = result.expect("Failed to deserialize a valid ContextConfig");
         assert_eq!(&expected_context_id,
                    context.context_id.as_ref().unwrap());
     }
+
+    #[test]
+    fn test_given_config_context_when_its_context_id_is_a_template_then_can_be_deserialized() {
+        let text = r#"
+        {
+            "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+            "context_id": "${HOST}:${PID}",
+            "conditions": {
+                "timeout": 100
+            }
+        }
+        "#;
+        let result = from_str::<ContextConfig<String>>(text);
+        let context = result.expect("Failed to deserialize a valid ContextConfig");
+        assert_eq!(Some(ContextId::Template("${HOST}:${PID}".to_owned())), context.context_id);
+    }
+
     #[test]
     fn test_given_config_when_it_doesn_not_have_conditions_then_it_does_not_panic() {
         let text = r#"
@@ -268,4 +379,155 @@ mod test {
         "#;
         let _ = from_str::<ContextConfig<String>>(text).err().unwrap();
     }
+
+    #[test]
+    fn test_given_config_context_when_it_contains_instance_limit_then_can_be_deserialized() {
+        let text = r#"
+        {
+            "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+            "context_id": ["src_ip"],
+            "max_instances": 1000,
+            "eviction": "oldest",
+            "missing_key": "default:unknown",
+            "key_mapping": {
+                "FW_DROP": { "src_ip": "source" }
+            },
+            "conditions": {
+                "timeout": 100
+            }
+        }
+        "#;
+        let result = from_str::<ContextConfig<String>>(text);
+        let context = result.expect("Failed to deserialize a valid ContextConfig");
+        assert_eq!(Some(1000), context.max_instances);
+        assert_eq!(Eviction::Oldest, context.eviction);
+        assert_eq!(MissingKey::Default("unknown".to_owned()), context.missing_key);
+        assert_eq!(Some(&"source".to_owned()), context.key_mapping.get("FW_DROP").and_then(|mapping| mapping.get("src_ip")));
+    }
+
+    #[test]
+    fn test_given_config_context_when_key_mapping_is_used_with_a_context_id_template_then_we_get_error() {
+        let text = r#"
+        {
+            "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+            "context_id": "${src_ip}",
+            "key_mapping": {
+                "FW_DROP": { "src_ip": "source" }
+            },
+            "conditions": {
+                "timeout": 100
+            }
+        }
+        "#;
+        let _ = from_str::<ContextConfig<String>>(text).err().expect("Successfully deserialized a key_mapping with a context_id template");
+    }
+
+    #[test]
+    fn test_given_config_context_without_context_id_when_it_contains_the_settings_of_the_keys_then_we_get_error() {
+        let settings = [
+            r#""max_instances": 1000"#,
+            r#""eviction": "oldest""#,
+            r#""missing_key": "skip""#,
+            r#""key_mapping": { "FW_DROP": { "src_ip": "source" } }"#,
+        ];
+        for setting in &settings {
+            let text = format!(r#"
+            {{
+                "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+                {},
+                "conditions": {{
+                    "timeout": 100
+                }}
+            }}
+            "#, setting);
+            let _ = from_str::<ContextConfig<String>>(&text).err().expect("Successfully deserialized the settings of the keys without context_id");
+        }
+    }
+
+    #[test]
+    fn test_given_sliding_config_context_without_context_id_when_it_contains_instance_limit_then_can_be_deserialized() {
+        let text = r#"
+        {
+            "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+            "max_instances": 1000,
+            "conditions": {
+                "timeout": 100,
+                "sliding": true
+            }
+        }
+        "#;
+        let context = from_str::<ContextConfig<String>>(text).expect("Failed to deserialize a valid ContextConfig");
+        assert_eq!(Some(1000), context.max_instances);
+    }
+
+    #[test]
+    fn test_given_config_context_when_eviction_has_an_unknown_value_then_we_get_error() {
+        let text = r#"
+        {
+            "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+            "context_id": ["src_ip"],
+            "eviction": "random",
+            "conditions": {
+                "timeout": 100
+            }
+        }
+        "#;
+        let _ = from_str::<ContextConfig<String>>(text).err().expect("Successfully deserialized an invalid eviction policy");
+    }
+
+    #[test]
+    fn test_given_config_context_when_on_message_patterns_are_set_then_they_are_resolved_to_the_patterns_of_the_context() {
+        let text = r#"
+        {
+            "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+            "patterns": [
+                { "name": "login.*", "match": "regex" },
+                "logout"
+            ],
+            "conditions": {
+                "timeout": 100
+            },
+            "actions": [
+                {
+                    "message": {
+                        "uuid": "uuid1",
+                        "when": {
+                            "on_message_patterns": ["login.*"]
+                        },
+                        "message": "message"
+                    }
+                }
+            ]
+        }
+        "#;
+        let mut context = from_str::<ContextConfig<String>>(text).expect("Failed to deserialize a valid ContextConfig");
+        let when = context.actions[0].when_mut().expect("Failed to get the triggers of a message action");
+        assert_eq!(1, when.on_message_patterns.len());
+        assert_eq!(PatternKind::Regex, when.on_message_patterns[0].kind());
+    }
+
+    #[test]
+    fn test_given_config_context_when_on_message_patterns_refer_to_an_unknown_pattern_then_we_get_error() {
+        let text = r#"
+        {
+            "uuid": "86ca9f93-84fb-4813-b037-6526f7a585a3",
+            "patterns": ["login"],
+            "conditions": {
+                "timeout": 100
+            },
+            "actions": [
+                {
+                    "message": {
+                        "uuid": "uuid1",
+                        "when": {
+                            "on_message_patterns": ["logout"]
+                        },
+                        "message": "message"
+                    }
+                }
+            ]
+        }
+        "#;
+        let _ = from_str::<ContextConfig<String>>(text).err().expect("Successfully deserialized an unknown pattern in on_message_patterns");
+    }
 }
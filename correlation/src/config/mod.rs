@@ -10,51 +10,79 @@ use uuid::Uuid;
 use std::collections::BTreeMap;
 
 use config::action::ActionType;
+use config::action::exec::{ExecAction, ExecActionBuilder};
+use config::action::file::{FileAction, FileActionBuilder};
+use config::action::webhook::{WebhookAction, WebhookActionBuilder};
+use config::action::tag::{TagAction, TagActionBuilder};
 use config::action::message::MessageAction;
 use conditions::Conditions;
 use Event;
 use TemplateFactory;
 use CompileError;
+use Pattern;
+use Aggregate;
+use Eviction;
+use MissingKey;
 
 mod deser;
 pub mod action;
 
+/// Defines how the events are grouped by a keyed context.
+#[derive(Debug, PartialEq)]
+pub enum ContextId<T> {
+    /// The values of these fields form the key.
+    Keys(Vec<String>),
+    /// The rendered template is the key, e.g. `"${HOST}:${PID}"`.
+    Template(T),
+}
+
+impl<T> Default for ContextId<T> {
+    fn default() -> ContextId<T> {
+        ContextId::Keys(Vec::new())
+    }
+}
+
+impl<T> From<Vec<String>> for ContextId<T> {
+    fn from(keys: Vec<String>) -> ContextId<T> {
+        ContextId::Keys(keys)
+    }
+}
+
 pub struct ContextConfig<T> {
     pub name: Option<String>,
     pub uuid: Uuid,
     pub conditions: Conditions,
-    pub context_id: Option<Vec<String>>,
+    pub context_id: Option<ContextId<T>>,
     pub actions: Vec<ActionType<T>>,
-    pub patterns: Vec<String>
+    pub patterns: Vec<Pattern>,
+    pub max_instances: Option<usize>,
+    pub eviction: Eviction,
+    pub missing_key: MissingKey,
+    pub key_mapping: BTreeMap<String, BTreeMap<String, String>>,
+    pub aggregates: BTreeMap<String, Aggregate>,
 }
 
 pub fn compile_templates<T, E, TF>(original: Vec<ContextConfig<T>>, factory: &TF) -> Result<Vec<ContextConfig<TF::Template>>, CompileError>
     where T: AsRef<[u8]>, E: Event, TF: TemplateFactory<E> {
     let mut new_contexts: Vec<ContextConfig<TF::Template>> = Vec::new();
     for context in original {
-        let ContextConfig {name, uuid, conditions, context_id, actions, patterns} = context;
+        let ContextConfig {name, uuid, conditions, context_id, actions, patterns, max_instances, eviction, missing_key, key_mapping, aggregates} = context;
+        let context_id = match context_id {
+            Some(ContextId::Keys(keys)) => Some(ContextId::Keys(keys)),
+            Some(ContextId::Template(template)) => Some(ContextId::Template(try!(factory.compile(template.as_ref())))),
+            None => None,
+        };
         let mut new_actions: Vec<ActionType<TF::Template>> = Vec::new();
 
         for action in actions {
-            let ActionType::Message(message_action) = action;
-            let MessageAction {uuid, name, message, values, when, inject_mode} = message_action;
-            let new_message = try!(factory.compile(message.as_ref()));
-            let mut new_values = BTreeMap::new();
-
-            for (key, value) in values {
-                let value = try!(factory.compile(value.as_ref()));
-                new_values.insert(key, value);
-            }
-
-            let action: MessageAction<TF::Template> = MessageAction {
-                uuid: uuid,
-                name: name,
-                message: new_message,
-                values: new_values,
-                when: when,
-                inject_mode: inject_mode
+            let action = match action {
+                ActionType::Message(message_action) => ActionType::Message(try!(compile_message_action(message_action, factory))),
+                ActionType::Exec(exec_action) => ActionType::Exec(try!(compile_exec_action(exec_action, factory))),
+                ActionType::File(file_action) => ActionType::File(try!(compile_file_action(file_action, factory))),
+                ActionType::Webhook(webhook_action) => ActionType::Webhook(try!(compile_webhook_action(webhook_action, factory))),
+                ActionType::Tag(tag_action) => ActionType::Tag(try!(compile_tag_action(tag_action, factory))),
             };
-            new_actions.push(ActionType::Message(action));
+            new_actions.push(action);
         }
 
         let config = ContextConfig {
@@ -63,7 +91,12 @@ pub fn compile_templates<T, E, TF>(original: Vec<ContextConfig<T>>, factory: &TF
             conditions: conditions,
             context_id: context_id,
             actions: new_actions,
-            patterns: patterns
+            patterns: patterns,
+            max_instances: max_instances,
+            eviction: eviction,
+            missing_key: missing_key,
+            key_mapping: key_mapping,
+            aggregates: aggregates,
         };
 
         new_contexts.push(config);
@@ -71,13 +104,128 @@ pub fn compile_templates<T, E, TF>(original: Vec<ContextConfig<T>>, factory: &TF
     Ok(new_contexts)
 }
 
+fn compile_message_action<T, E, TF>(message_action: MessageAction<T>, factory: &TF) -> Result<MessageAction<TF::Template>, CompileError>
+    where T: AsRef<[u8]>, E: Event, TF: TemplateFactory<E> {
+    let MessageAction {uuid, name, message, values, when, inject_mode, rate_limit} = message_action;
+    let new_message = try!(factory.compile(message.as_ref()));
+    let mut new_values = BTreeMap::new();
+
+    for (key, value) in values {
+        let value = try!(factory.compile(value.as_ref()));
+        new_values.insert(key, value);
+    }
+
+    Ok(MessageAction {
+        uuid: uuid,
+        name: name,
+        message: new_message,
+        values: new_values,
+        when: when,
+        inject_mode: inject_mode,
+        rate_limit: rate_limit,
+    })
+}
+
+fn compile_exec_action<T, E, TF>(exec_action: ExecAction<T>, factory: &TF) -> Result<ExecAction<TF::Template>, CompileError>
+    where T: AsRef<[u8]>, E: Event, TF: TemplateFactory<E> {
+    let ExecAction {uuid, name, program, args, env, timeout, max_concurrency, when, inject_mode, ..} = exec_action;
+    let mut new_args = Vec::new();
+    let mut new_env = BTreeMap::new();
+
+    for arg in args {
+        new_args.push(try!(factory.compile(arg.as_ref())));
+    }
+
+    for (key, value) in env {
+        let value = try!(factory.compile(value.as_ref()));
+        new_env.insert(key, value);
+    }
+
+    Ok(ExecActionBuilder::new(uuid, program)
+           .name(name)
+           .args(new_args)
+           .env(new_env)
+           .timeout(timeout)
+           .max_concurrency(max_concurrency)
+           .when(when)
+           .inject_mode(inject_mode)
+           .build())
+}
+
+fn compile_file_action<T, E, TF>(file_action: FileAction<T>, factory: &TF) -> Result<FileAction<TF::Template>, CompileError>
+    where T: AsRef<[u8]>, E: Event, TF: TemplateFactory<E> {
+    let FileAction {uuid, name, path, message, values, template, fsync, when, ..} = file_action;
+    let mut new_values = BTreeMap::new();
+
+    for (key, value) in values {
+        let value = try!(factory.compile(value.as_ref()));
+        new_values.insert(key, value);
+    }
+
+    let mut builder = FileActionBuilder::new(uuid, path)
+                          .name(name)
+                          .values(new_values)
+                          .fsync(fsync)
+                          .when(when);
+    if let Some(message) = message {
+        builder = builder.message(try!(factory.compile(message.as_ref())));
+    }
+    if let Some(template) = template {
+        builder = builder.template(Some(try!(factory.compile(template.as_ref()))));
+    }
+    Ok(builder.build())
+}
+
+fn compile_webhook_action<T, E, TF>(webhook_action: WebhookAction<T>, factory: &TF) -> Result<WebhookAction<TF::Template>, CompileError>
+    where T: AsRef<[u8]>, E: Event, TF: TemplateFactory<E> {
+    let WebhookAction {uuid, name, message, values, endpoint, queue_size, when, ..} = webhook_action;
+    let mut new_values = BTreeMap::new();
+
+    for (key, value) in values {
+        let value = try!(factory.compile(value.as_ref()));
+        new_values.insert(key, value);
+    }
+
+    let mut builder = WebhookActionBuilder::new(uuid, endpoint.url)
+                          .name(name)
+                          .values(new_values)
+                          .headers(endpoint.headers)
+                          .timeout(endpoint.timeout)
+                          .retries(endpoint.retries)
+                          .backoff(endpoint.backoff)
+                          .queue_size(queue_size)
+                          .when(when);
+    if let Some(message) = message {
+        builder = builder.message(try!(factory.compile(message.as_ref())));
+    }
+    Ok(builder.build())
+}
+
+fn compile_tag_action<T, E, TF>(tag_action: TagAction<T>, factory: &TF) -> Result<TagAction<TF::Template>, CompileError>
+    where T: AsRef<[u8]>, E: Event, TF: TemplateFactory<E> {
+    let TagAction {uuid, values} = tag_action;
+    let mut new_values = BTreeMap::new();
+
+    for (key, value) in values {
+        let value = try!(factory.compile(value.as_ref()));
+        new_values.insert(key, value);
+    }
+
+    Ok(TagActionBuilder::new(uuid).values(new_values).build())
+}
+
 pub struct ContextConfigBuilder<T> {
     name: Option<String>,
     uuid: Uuid,
     conditions: Conditions,
-    context_id: Option<Vec<String>>,
+    context_id: Option<ContextId<T>>,
     actions: Vec<ActionType<T>>,
-    patterns: Vec<String>
+    patterns: Vec<Pattern>,
+    max_instances: Option<usize>,
+    eviction: Eviction,
+    missing_key: MissingKey,
+    key_mapping: BTreeMap<String, BTreeMap<String, String>>,
+    aggregates: BTreeMap<String, Aggregate>,
 }
 
 impl<T> ContextConfigBuilder<T> {
@@ -88,12 +236,22 @@ impl<T> ContextConfigBuilder<T> {
             conditions: conditions,
             context_id: None,
             actions: Vec::new(),
-            patterns: Vec::new()
+            patterns: Vec::new(),
+            max_instances: None,
+            eviction: Eviction::default(),
+            missing_key: MissingKey::default(),
+            key_mapping: BTreeMap::new(),
+            aggregates: BTreeMap::new(),
         }
     }
 
     pub fn context_id(mut self, context_id: Option<Vec<String>>) -> ContextConfigBuilder<T> {
-        self.context_id = context_id;
+        self.context_id = context_id.map(ContextId::Keys);
+        self
+    }
+
+    pub fn context_id_template(mut self, template: T) -> ContextConfigBuilder<T> {
+        self.context_id = Some(ContextId::Template(template));
         self
     }
 
@@ -107,8 +265,29 @@ impl<T> ContextConfigBuilder<T> {
         self
     }
 
-    pub fn patterns(mut self, patterns: Vec<String>) -> ContextConfigBuilder<T> {
-        self.patterns = patterns;
+    pub fn patterns<P: Into<Pattern>>(mut self, patterns: Vec<P>) -> ContextConfigBuilder<T> {
+        self.patterns = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn max_instances(mut self, max_instances: usize, eviction: Eviction) -> ContextConfigBuilder<T> {
+        self.max_instances = Some(max_instances);
+        self.eviction = eviction;
+        self
+    }
+
+    pub fn missing_key(mut self, missing_key: MissingKey) -> ContextConfigBuilder<T> {
+        self.missing_key = missing_key;
+        self
+    }
+
+    pub fn key_mapping(mut self, key_mapping: BTreeMap<String, BTreeMap<String, String>>) -> ContextConfigBuilder<T> {
+        self.key_mapping = key_mapping;
+        self
+    }
+
+    pub fn aggregates(mut self, aggregates: BTreeMap<String, Aggregate>) -> ContextConfigBuilder<T> {
+        self.aggregates = aggregates;
         self
     }
 
@@ -119,7 +298,12 @@ impl<T> ContextConfigBuilder<T> {
             conditions: self.conditions,
             context_id: self.context_id,
             actions: self.actions,
-            patterns: self.patterns
+            patterns: self.patterns,
+            max_instances: self.max_instances,
+            eviction: self.eviction,
+            missing_key: self.missing_key,
+            key_mapping: self.key_mapping,
+            aggregates: self.aggregates,
         }
     }
 }
@@ -6,25 +6,42 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use std::iter::Chain;
+use std::slice::Iter;
 use std::time::Duration;
 
 use uuid::Uuid;
 
 use config::action::ActionType;
-use conditions::Conditions;
+use conditions::{CloseReason, Conditions, OutOfOrder, Overflow, Reopen};
 use state::State;
 use action::Action;
+use aggregate::Aggregate;
+use pattern::Pattern;
 use Event;
 use Template;
+use TemplateContext;
 use Alert;
 
+/// Tells whether an event was accepted by a state and where the actions can find it.
+enum Acceptance<E> {
+    Rejected,
+    /// The event is the last message of the state.
+    Stored,
+    /// The event was counted but not stored (e.g. `overflow` is `discard`).
+    Skipped(E),
+}
+
 pub struct BaseContext<E, T> where E: Event, T: Template<Event=E> {
     pub name: Option<String>,
     pub uuid: Uuid,
+    /// The `uuid` formatted once for the templates of the actions.
+    pub hyphenated_uuid: String,
     pub conditions: Conditions,
     pub actions: Vec<ActionType<T>>,
-    pub patterns: Vec<String>,
+    pub patterns: Vec<Pattern>,
+    pub aggregates: BTreeMap<String, Aggregate>,
 }
 
 impl<E, T> BaseContext<E, T> where E: Event, T: Template<Event=E> {
@@ -40,92 +57,330 @@ impl<E, T> BaseContext<E, T> where E: Event, T: Template<Event=E> {
         &self.actions
     }
 
+    /// Returns the rendered values of the aggregates by their names.
+    pub fn aggregate_values(&self, state: &State<E>) -> BTreeMap<&str, String> {
+        self.aggregates
+            .iter()
+            .zip(state.accumulators())
+            .map(|((name, aggregate), accumulator)| (&name[..], aggregate.value(accumulator)))
+            .collect()
+    }
+
+    /// Returns the context of the templates rendered by the actions.
+    pub fn template_context<'a>(&'a self,
+                                state: &'a State<E>,
+                                reason: Option<CloseReason>,
+                                event: Option<&'a E>) -> TemplateContext<'a, E> {
+        let mut template_context = TemplateContext::new(state.messages(), &self.hyphenated_uuid);
+        template_context.context_name = self.name().map(|name| &name[..]);
+        template_context.close_reason = reason;
+        template_context.event = event;
+        template_context.event_count = state.event_count();
+        template_context.aggregates = self.aggregate_values(state);
+        template_context
+    }
+
+    /// Returns every pattern the context is subscribed to.
+    pub fn subscriptions(&self) -> Vec<&Pattern> {
+        self.iter_subscriptions().collect()
+    }
+
+    fn iter_subscriptions(&self) -> Chain<Chain<Iter<Pattern>, Iter<Pattern>>, Iter<Pattern>> {
+        self.patterns
+            .iter()
+            .chain(self.conditions.opens.iter())
+            .chain(self.conditions.closes.iter())
+    }
+
+    /// Returns `true` if the context is subscribed to every event (it has no `patterns`).
+    pub fn is_catch_all(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
     pub fn is_opening(&self, message: &E) -> bool {
-        if self.conditions.first_opens {
-            self.patterns.first().iter().any(|first| message.ids().into_iter().any(|id| &id[..] == first.as_bytes()))
-        } else {
+        let first_opens = self.conditions.first_opens || self.is_ordered();
+        if self.conditions.opens.is_empty() && !first_opens {
             true
+        } else {
+            self.conditions.opens.iter().any(|pattern| BaseContext::<E, T>::is_matching(message, pattern)) ||
+            first_opens && self.patterns.first().iter().any(|first| BaseContext::<E, T>::is_matching(message, first))
         }
     }
 
+    /// Returns `true` if `message` opens the context explicitly (`opens`, `first_opens` or `ordered`).
+    fn is_reopening(&self, message: &E) -> bool {
+        let first_opens = self.conditions.first_opens || self.is_ordered();
+        (!self.conditions.opens.is_empty() || first_opens) && self.is_opening(message)
+    }
+
+    fn is_ordered(&self) -> bool {
+        self.conditions.ordered && !self.patterns.is_empty()
+    }
+
+    fn is_matching(message: &E, pattern: &Pattern) -> bool {
+        pattern.is_matching(message)
+    }
+
+    pub fn is_accepting(&self, message: &E) -> bool {
+        self.is_catch_all() || self.iter_subscriptions().any(|pattern| pattern.is_matching(message))
+    }
+
+    fn is_next_in_sequence(&self, message: &E, state: &State<E>) -> bool {
+        self.patterns.get(state.position()).map_or(false, |next| BaseContext::<E, T>::is_matching(message, next))
+    }
+
     pub fn is_closing(&self, state: &State<E>) -> bool {
-        trace!("Conditions: shoud we close this context?");
-        state.is_open() && self.is_closing_condition_met(state)
+        self.closing_reason(state).is_some()
     }
 
-    fn is_closing_condition_met(&self, state: &State<E>) -> bool {
-        self.is_max_size_reached(state) || self.is_closing_message(state) ||
-        self.is_any_timer_expired(state)
+    pub fn closing_reason(&self, state: &State<E>) -> Option<CloseReason> {
+        trace!("Conditions: shoud we close this context?");
+        if !state.is_open() {
+            None
+        } else if self.is_closing_message(state) {
+            Some(CloseReason::Completed)
+        } else if self.is_max_size_reached(state) {
+            Some(CloseReason::MaxSize)
+        } else if self.is_timeout_expired(state) {
+            Some(CloseReason::Timeout)
+        } else if self.is_renew_timeout_expired(state) {
+            Some(CloseReason::RenewTimeout)
+        } else {
+            None
+        }
     }
 
     fn is_max_size_reached(&self, state: &State<E>) -> bool {
+        self.conditions.overflow == Overflow::Close &&
         self.conditions.max_size.map_or(false, |max_size| state.messages().len() >= max_size)
     }
 
-    fn is_closing_message(&self, state: &State<E>) -> bool {
-        if self.conditions.last_closes {
-            state.messages().last().iter().any(|last_message| {
-                self.patterns.last().iter().any(|last| last_message.ids().into_iter().any(|id| &id[..] == last.as_bytes()))
-            })
-        } else {
-            false
+    fn store_message(&self, event: E, state: &mut State<E>) -> Acceptance<E> {
+        state.update_aggregates(&self.aggregates, &event);
+        let len = state.messages().len();
+        match (self.conditions.overflow, self.conditions.max_size) {
+            (Overflow::Discard, Some(max_size)) if len >= max_size => {
+                state.skip_message();
+                return Acceptance::Skipped(event);
+            }
+            (Overflow::Ring, Some(max_size)) if max_size > 0 && len >= max_size => state.replace_message(0, event),
+            (Overflow::FirstLast, Some(max_size)) if max_size > 0 && len >= 2 * max_size => {
+                state.replace_message(max_size, event)
+            }
+            _ => state.add_message(event),
         }
+        Acceptance::Stored
     }
 
-    fn is_any_timer_expired(&self, state: &State<E>) -> bool {
-        self.is_timeout_expired(state) || self.is_renew_timeout_expired(state)
+    fn is_closing_message(&self, state: &State<E>) -> bool {
+        if self.is_ordered() && state.position() >= self.patterns.len() {
+            return true;
+        }
+        state.last_message().map_or(false, |last_message| {
+            self.conditions.closes.iter().any(|pattern| BaseContext::<E, T>::is_matching(last_message, pattern)) ||
+            !self.is_ordered() && self.conditions.last_closes &&
+            self.patterns.last().iter().any(|last| BaseContext::<E, T>::is_matching(last_message, last))
+        })
     }
 
     fn is_timeout_expired(&self, state: &State<E>) -> bool {
+        // sliding windows measure the timeout for every message separately,
+        // calendar windows are closed only on the boundaries
+        !self.conditions.sliding && self.conditions.calendar.is_none() &&
         state.elapsed_time() >= self.conditions.timeout
     }
 
+    /// Returns `true` if a boundary of the calendar is between `from` and `to`.
+    pub fn is_boundary_crossed(&self, from: &Duration, to: &Duration) -> bool {
+        self.conditions.calendar.map_or(false, |calendar| calendar.is_boundary_crossed(from, to))
+    }
+
     fn is_renew_timeout_expired(&self, state: &State<E>) -> bool {
         self.conditions.renew_timeout.map_or(false, |renew_timeout| {
             state.elapsed_time_since_last_message() >= renew_timeout
         })
     }
 
+    /// Advances the timers of the actions (e.g. rate limits) by the time
+    /// elapsed since the previous timer event, once per timer event.
+    pub fn update_action_timers(&self, span: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        for i in self.actions() {
+            i.on_timer(span, responder);
+        }
+    }
+
+    /// Moves the state to the time `now` of the context clock, then closes it if it timed out.
     pub fn on_timer(&self,
-                    event: &Duration,
+                    now: &Duration,
                     state: &mut State<E>,
                     responder: &mut VecDeque<Alert<E>>) {
-        if state.is_open() {
-            state.update_timers(&event);
+        state.set_time(*now);
+        if let Some(reason) = self.closing_reason(state) {
+            self.close(state, reason, responder);
         }
-        if self.is_closing(state) {
-            self.close(state, responder);
+    }
+
+    /// Closes the state if the wall clock moved from `from` to `to` over a boundary of the calendar.
+    pub fn on_clock(&self,
+                    from: &Duration,
+                    to: &Duration,
+                    state: &mut State<E>,
+                    responder: &mut VecDeque<Alert<E>>) {
+        if state.is_open() && self.is_boundary_crossed(from, to) {
+            self.close(state, CloseReason::Boundary, responder);
         }
     }
 
+    /// Returns `false` if the event was suppressed by the context.
     pub fn on_message(&self,
                       event: E,
                       state: &mut State<E>,
-                      responder: &mut VecDeque<Alert<E>>) {
-        if state.is_open() {
-            state.add_message(event);
-        } else if self.is_opening(&event) {
-            state.add_message(event);
-            self.open(state, responder);
+                      responder: &mut VecDeque<Alert<E>>) -> bool {
+        if !self.is_accepting(&event) {
+            trace!("Context: message is not accepted by the predicates; uuid={}", self.uuid());
+            return true;
+        }
+
+        let mut suppressed = false;
+
+        let acceptance = if !state.is_open() {
+            if self.is_opening(&event) {
+                let acceptance = self.store_message(event, state);
+                self.open(state, responder);
+                acceptance
+            } else {
+                Acceptance::Rejected
+            }
+        } else if self.conditions.on_reopen != Reopen::Append && self.is_reopening(&event) {
+            self.on_reopening_message(event, state, responder)
+        } else if self.conditions.suppress {
+            trace!("Context: suppressing message; uuid={}", self.uuid());
+            state.update_aggregates(&self.aggregates, &event);
+            state.suppress_message(event);
+            suppressed = true;
+            Acceptance::Stored
+        } else if !self.is_ordered() || self.is_next_in_sequence(&event, state) {
+            self.store_message(event, state)
+        } else {
+            self.on_out_of_order_message(event, state, responder)
+        };
+
+        // a stored event is always the last message of the state
+        match acceptance {
+            Acceptance::Stored => {
+                let is_triggering = state.last_message()
+                                         .map_or(false, |event| self.is_message_trigger(event));
+                if is_triggering {
+                    state.normalize();
+                    if let Some(event) = state.messages().last() {
+                        self.on_accepted_message(event, state, responder);
+                    }
+                }
+            }
+            Acceptance::Skipped(ref event) => {
+                if self.is_message_trigger(event) {
+                    state.normalize();
+                    self.on_accepted_message(event, state, responder);
+                }
+            }
+            Acceptance::Rejected => (),
+        }
+
+        self.check_threshold(state, responder);
+
+        if let Some(reason) = self.closing_reason(state) {
+            self.close(state, reason, responder);
         }
 
-        if self.is_closing(state) {
-            self.close(state, responder);
+        !suppressed
+    }
+
+    // the stored messages are put in order only if an action can see them
+    fn is_message_trigger(&self, event: &E) -> bool {
+        self.actions().iter().any(|action| action.is_message_trigger(event))
+    }
+
+    fn on_accepted_message(&self, event: &E, state: &State<E>, responder: &mut VecDeque<Alert<E>>) {
+        for i in self.actions() {
+            i.on_message(event, state, self, responder);
         }
     }
 
+    fn on_out_of_order_message(&self,
+                               event: E,
+                               state: &mut State<E>,
+                               responder: &mut VecDeque<Alert<E>>) -> Acceptance<E> {
+        trace!("Context: received an out of order message; uuid={}", self.uuid());
+        match self.conditions.out_of_order {
+            OutOfOrder::Ignore => Acceptance::Rejected,
+            OutOfOrder::Reset => {
+                state.close();
+                if self.is_opening(&event) {
+                    let acceptance = self.store_message(event, state);
+                    self.open(state, responder);
+                    acceptance
+                } else {
+                    Acceptance::Rejected
+                }
+            }
+            OutOfOrder::Close => {
+                let _ = self.store_message(event, state);
+                self.close(state, CloseReason::OutOfOrder, responder);
+                Acceptance::Rejected
+            }
+        }
+    }
+
+    fn on_reopening_message(&self,
+                            event: E,
+                            state: &mut State<E>,
+                            responder: &mut VecDeque<Alert<E>>) -> Acceptance<E> {
+        trace!("Context: received an opening message while open; uuid={}", self.uuid());
+        match self.conditions.on_reopen {
+            Reopen::Append => self.store_message(event, state),
+            Reopen::CloseAndReopen => {
+                self.close(state, CloseReason::Reopened, responder);
+                let acceptance = self.store_message(event, state);
+                self.open(state, responder);
+                acceptance
+            }
+            Reopen::Ignore => Acceptance::Rejected,
+        }
+    }
+
+    fn check_threshold(&self, state: &mut State<E>, responder: &mut VecDeque<Alert<E>>) {
+        if let Some(min_size) = self.conditions.min_size {
+            if state.is_open() && !state.is_threshold_reached() && state.event_count() >= min_size {
+                trace!("Context: threshold reached; uuid={}", self.uuid());
+                state.mark_threshold_reached();
+                state.normalize();
+                for i in self.actions() {
+                    i.on_threshold(state, self, responder);
+                }
+            }
+        }
+    }
+
+    fn is_threshold_met(&self, state: &State<E>) -> bool {
+        self.conditions.min_size.is_none() || state.is_threshold_reached()
+    }
+
     fn open(&self, state: &mut State<E>, responder: &mut VecDeque<Alert<E>>) {
         trace!("Context: opening state; uuid={}", self.uuid());
+        state.normalize();
         for i in self.actions() {
             i.on_opened(state, self, responder);
         }
         state.open();
     }
 
-    fn close(&self, state: &mut State<E>, responder: &mut VecDeque<Alert<E>>) {
-        trace!("Context: closing state; uuid={}", self.uuid());
-        for i in self.actions() {
-            i.on_closed(state, self, responder);
+    pub fn close(&self, state: &mut State<E>, reason: CloseReason, responder: &mut VecDeque<Alert<E>>) {
+        trace!("Context: closing state; uuid={} reason={}", self.uuid(), reason.as_str());
+        if self.is_threshold_met(state) {
+            state.normalize();
+            for i in self.actions() {
+                i.on_closed(state, self, reason, responder);
+            }
         }
         state.close();
     }
@@ -6,23 +6,106 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use regex;
+use regex::bytes::RegexSet;
 
 use config::ContextConfig;
 use context::Context;
+use Alert;
 use Event;
 use Template;
+use Pattern;
+use PatternKind;
 
-pub struct ContextMap<E, T> where E: Event, T: Template<Event=E> {
+/// Finds the contexts subscribed to the ids of the events.
+#[derive(Default)]
+struct Index {
     map: HashMap<Vec<u8>, Vec<usize>>,
+    catch_all: Vec<usize>,
+    regexes: Vec<String>,
+    regex_indices: Vec<usize>,
+    regex_set: Option<RegexSet>,
+}
+
+impl Index {
+    fn insert(&mut self, index: usize, patterns: &[&Pattern]) {
+        for i in patterns.iter().filter(|pattern| pattern.kind() == PatternKind::Exact) {
+            let indices = self.map.entry(i.name().as_bytes().to_vec()).or_insert_with(Vec::new);
+            // more patterns can have the same name with different predicates
+            if indices.last() != Some(&index) {
+                indices.push(index);
+            }
+        }
+        for i in patterns.iter().filter(|pattern| pattern.kind() != PatternKind::Exact) {
+            if let Some(regex) = i.regex() {
+                self.regexes.push(regex.to_owned());
+                self.regex_indices.push(index);
+            }
+        }
+    }
+
+    fn build_regex_set(&mut self) -> Result<(), regex::Error> {
+        let is_outdated = self.regex_set.as_ref().map_or(true, |regex_set| regex_set.len() != self.regexes.len());
+        if is_outdated && !self.regexes.is_empty() {
+            self.regex_set = Some(try!(RegexSet::new(&self.regexes)));
+        }
+        Ok(())
+    }
+
+    /// Returns the sorted indices of the contexts which are subscribed to any of `keys`.
+    fn lookup<'a, 'k, I>(&'a self, keys: I) -> Cow<'a, [usize]> where I: IntoIterator<Item=&'k [u8]> {
+        if let Some(ref regex_set) = self.regex_set {
+            let mut ids = self.catch_all.clone();
+            for key in keys {
+                if let Some(exact) = self.map.get(key) {
+                    ids.extend(exact.iter());
+                }
+                ids.extend(regex_set.matches(key).iter().map(|i| self.regex_indices[i]));
+            }
+            ids.sort();
+            ids.dedup();
+            return Cow::Owned(ids);
+        }
+
+        // without regexes the index vectors can be used as they are if only one of them is hit
+        let mut found = keys.into_iter().filter_map(|key| self.map.get(key)).map(|indices| &indices[..]);
+        let first = if self.catch_all.is_empty() {
+            found.next()
+        } else {
+            Some(&self.catch_all[..])
+        };
+        match (first, found.next()) {
+            (None, _) => Cow::Borrowed(&[][..]),
+            (Some(indices), None) => Cow::Borrowed(indices),
+            (Some(first), Some(second)) => {
+                let mut ids = first.to_vec();
+                ids.extend_from_slice(second);
+                for i in found {
+                    ids.extend_from_slice(i);
+                }
+                ids.sort();
+                ids.dedup();
+                Cow::Owned(ids)
+            }
+        }
+    }
+}
+
+pub struct ContextMap<E, T> where E: Event, T: Template<Event=E> {
+    index: Index,
     contexts: Vec<Context<E, T>>,
+    max_instances: Option<usize>,
 }
 
 impl<E, T> Default for ContextMap<E, T> where E: Event, T: Template<Event=E> {
     fn default() -> ContextMap<E, T> {
         ContextMap {
-            map: HashMap::default(),
-            contexts: Vec::default()
+            index: Index::default(),
+            contexts: Vec::default(),
+            max_instances: None,
         }
     }
 }
@@ -32,84 +115,144 @@ impl<E, T> ContextMap<E, T> where E: Event, T: Template<Event=E> {
         ContextMap::default()
     }
 
-    pub fn from_configs(configs: Vec<ContextConfig<T>>) -> ContextMap<E, T>
+    /// Returns an error if the regular expressions of the patterns can't be
+    /// looked up together (e.g. they exceed the size limit of the regex engine).
+    pub fn from_configs(configs: Vec<ContextConfig<T>>) -> Result<ContextMap<E, T>, regex::Error>
         where T: Template<Event=E> {
         let mut context_map = ContextMap::new();
         for i in configs {
-            context_map.insert(i.into());
+            context_map.push(i.into());
         }
-        context_map
+        try!(context_map.index.build_regex_set());
+        Ok(context_map)
     }
 
-    pub fn insert(&mut self, context: Context<E, T>) {
-        self.contexts.push(context);
-        let last_context = self.contexts
-                               .last()
-                               .expect("Failed to remove the last Context from a non empty vector");
-        let index_of_last_context = self.contexts.len() - 1;
-        let patterns = last_context.patterns();
-        ContextMap::<E, T>::update_indices(&mut self.map, index_of_last_context, patterns);
-    }
-
-    fn update_indices(map: &mut HashMap<Vec<u8>, Vec<usize>>,
-                      new_index: usize,
-                      patterns: &[String]) {
-        if patterns.is_empty() {
-            ContextMap::<E, T>::add_index_to_every_index_vectors(map, new_index);
-        } else {
-            ContextMap::<E, T>::add_index_to_looked_up_index_vectors(map, new_index, patterns);
-        }
+    /// Limits the number of states of every keyed context together.
+    pub fn set_max_instances(&mut self, max_instances: Option<usize>) {
+        self.max_instances = max_instances;
+    }
+
+    pub fn instances(&self) -> usize {
+        self.contexts.iter().map(|context| context.instances()).sum()
+    }
+
+    pub fn is_instance_limit_reached(&self) -> bool {
+        self.max_instances.map_or(false, |max_instances| self.instances() >= max_instances)
+    }
+
+    pub fn evictions(&self) -> usize {
+        self.contexts.iter().map(|context| context.evictions()).sum()
     }
 
-    fn add_index_to_every_index_vectors(map: &mut HashMap<Vec<u8>, Vec<usize>>, new_index: usize) {
-        for (_, v) in map.iter_mut() {
-            v.push(new_index);
+    pub fn rejections(&self) -> usize {
+        self.contexts.iter().map(|context| context.rejections()).sum()
+    }
+
+    /// Returns how many times the `context_id` keys were missing from the events.
+    pub fn missing_keys(&self) -> BTreeMap<String, usize> {
+        let mut missing_keys = BTreeMap::new();
+        for (key, count) in self.contexts.iter().filter_map(|context| context.missing_keys()).flat_map(|keys| keys.iter()) {
+            *missing_keys.entry(key.clone()).or_insert(0) += *count;
         }
+        missing_keys
+    }
+
+    pub fn insert(&mut self, context: Context<E, T>) -> Result<(), regex::Error> {
+        self.push(context);
+        self.index.build_regex_set()
     }
 
-    fn add_index_to_looked_up_index_vectors(map: &mut HashMap<Vec<u8>, Vec<usize>>,
-                                            new_index: usize,
-                                            patterns: &[String]) {
-        for i in patterns {
-            map.entry(i.as_bytes().to_vec()).or_insert_with(Vec::new).push(new_index);
+    fn push(&mut self, context: Context<E, T>) {
+        let index = self.contexts.len();
+        if context.is_catch_all() {
+            self.index.catch_all.push(index);
+        } else {
+            self.index.insert(index, &context.subscriptions());
         }
+        self.contexts.push(context);
     }
 
     pub fn contexts_mut(&mut self) -> &mut Vec<Context<E, T>> {
         &mut self.contexts
     }
 
+    #[cfg(test)]
     pub fn contexts_iter_mut(&mut self, key: &[u8]) -> Iterator<E, T> {
-        let ids = self.map.get(key);
         Iterator {
-            ids: ids,
+            ids: self.index.lookup(Some(key)),
+            pos: 0,
+            contexts: &mut self.contexts,
+        }
+    }
+
+    /// Delivers `event` to the contexts which are subscribed to any of its ids.
+    /// Returns `false` if a context suppressed it. If the global instance limit
+    /// is reached and a context would evict a state to open a new one, the
+    /// victim is chosen from the states of every keyed context (see `evict()`).
+    pub fn on_message(&mut self, event: E, responder: &mut VecDeque<Alert<E>>) -> bool {
+        let limit_reached = self.is_instance_limit_reached();
+        let ContextMap { ref index, ref mut contexts, .. } = *self;
+        let mut forwarded = true;
+        for &id in index.lookup(event.ids()).iter() {
+            let global_limit_reached = limit_reached &&
+                                       !(contexts[id].map().map_or(false, |context| context.needs_room(&event)) &&
+                                         ContextMap::evict(contexts, responder));
+            if !contexts[id].on_message(event.clone(), global_limit_reached, responder) {
+                forwarded = false;
+            }
+        }
+        forwarded
+    }
+
+    /// Closes the next victim of the eviction policies of the keyed contexts:
+    /// the one which was used least recently (`lru`) or opened first
+    /// (`oldest`). The contexts which reject the new keys are skipped.
+    /// Returns `false` if none of the contexts can evict a state.
+    fn evict(contexts: &mut [Context<E, T>], responder: &mut VecDeque<Alert<E>>) -> bool {
+        let victim = contexts.iter()
+                             .enumerate()
+                             .filter_map(|(i, context)| {
+                                 context.map().and_then(|context| context.next_victim_time()).map(|time| (time, i))
+                             })
+                             .min();
+        match victim {
+            Some((_, i)) => contexts[i].map_mut().map_or(false, |context| context.evict(responder)),
+            None => false,
+        }
+    }
+
+    /// Iterates over the contexts which are subscribed to any id of `event`.
+    /// Every context is returned only once.
+    #[cfg(test)]
+    pub fn contexts_iter_mut_by_event(&mut self, event: &E) -> Iterator<E, T> {
+        Iterator {
+            ids: self.index.lookup(event.ids()),
             pos: 0,
             contexts: &mut self.contexts,
         }
     }
 }
 
+#[cfg(test)]
 pub trait StreamingIterator {
     type Item;
     fn next(&mut self) -> Option<&mut Self::Item>;
 }
 
+#[cfg(test)]
 pub struct Iterator<'a, E, T> where E: 'a + Event, T: 'a + Template<Event=E> {
-    ids: Option<&'a Vec<usize>>,
+    ids: Cow<'a, [usize]>,
     pos: usize,
     contexts: &'a mut Vec<Context<E, T>>,
 }
 
+#[cfg(test)]
 impl<'a, E, T> StreamingIterator for Iterator<'a, E, T> where E: Event, T: Template<Event=E> {
     type Item = Context<E, T>;
     fn next(&mut self) -> Option<&mut Context<E, T>> {
-        if let Some(ids) = self.ids {
-            if let Some(id) = ids.get(self.pos) {
-                self.pos += 1;
-                self.contexts.get_mut(*id)
-            } else {
-                None
-            }
+        if let Some(&id) = self.ids.get(self.pos) {
+            self.pos += 1;
+            self.contexts.get_mut(id)
         } else {
             None
         }
@@ -126,6 +269,7 @@ mod tests {
     use std::time::Duration;
     use Event;
     use Message;
+    use {MessageBuilder, Operator, Pattern, PatternKind};
     use test_utils::{MockTemplate, BaseContextBuilder};
 
     fn assert_context_map_contains_uuid(context_map: &mut ContextMap<Message, MockTemplate>, uuid: &Uuid, key: &str) {
@@ -151,9 +295,97 @@ mod tests {
             let base = BaseContextBuilder::new(uuid.to_owned(), conditions).patterns(patterns).build();
             LinearContext::new(base)
         };
-        context_map.insert(Context::Linear(context1));
+        context_map.insert(Context::Linear(context1)).expect("Failed to insert a context");
         assert_eq!(context_map.contexts_mut().len(), 1);
         assert_context_map_contains_uuid(&mut context_map, &uuid, "A");
         assert_context_map_contains_uuid(&mut context_map, &uuid, "B");
     }
+
+    #[test]
+    fn test_given_context_map_when_a_context_has_more_patterns_with_the_same_name_then_its_index_is_inserted_only_once() {
+        let mut context_map = ContextMap::<Message, MockTemplate>::new();
+        let context = {
+            let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+            let patterns = vec![
+                Pattern::new("A").predicate("status", Operator::Equals("ok".to_owned())),
+                Pattern::new("A").predicate("status", Operator::Equals("failed".to_owned())),
+            ];
+            let base = BaseContextBuilder::new(Uuid::new_v4(), conditions).patterns(patterns).build();
+            LinearContext::new(base)
+        };
+        context_map.insert(Context::Linear(context)).expect("Failed to insert a context");
+        let mut iter = context_map.contexts_iter_mut(b"A");
+        assert_true!(iter.next().is_some());
+        assert_true!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_given_context_map_when_a_context_has_glob_and_regex_patterns_then_it_is_looked_up_by_them() {
+        let mut context_map = ContextMap::<Message, MockTemplate>::new();
+        let uuid = Uuid::new_v4();
+        let context = {
+            let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+            let patterns = vec![
+                Pattern::new("LOGIN"),
+                Pattern::with_kind("SSHD_AUTH_*", PatternKind::Glob).unwrap(),
+                Pattern::with_kind("^SUDO_[0-9]+$", PatternKind::Regex).unwrap(),
+            ];
+            let base = BaseContextBuilder::new(uuid.to_owned(), conditions).patterns(patterns).build();
+            LinearContext::new(base)
+        };
+        context_map.insert(Context::Linear(context)).expect("Failed to insert a context");
+        assert_context_map_contains_uuid(&mut context_map, &uuid, "LOGIN");
+        assert_context_map_contains_uuid(&mut context_map, &uuid, "SSHD_AUTH_FAILED");
+        assert_context_map_contains_uuid(&mut context_map, &uuid, "SUDO_42");
+        assert_true!(context_map.contexts_iter_mut(b"SUDO_X").next().is_none());
+        assert_true!(context_map.contexts_iter_mut(b"SSHD_LOGIN").next().is_none());
+    }
+
+    #[test]
+    fn test_given_context_map_when_a_context_has_only_opening_patterns_then_it_receives_every_event() {
+        let mut context_map = ContextMap::<Message, MockTemplate>::new();
+        let uuid = Uuid::new_v4();
+        let context = {
+            let conditions = ConditionsBuilder::new(Duration::from_millis(100)).opens(vec!["LOGIN"]).build();
+            let base = BaseContextBuilder::new(uuid.to_owned(), conditions).patterns(Vec::<Pattern>::new()).build();
+            LinearContext::new(base)
+        };
+        context_map.insert(Context::Linear(context)).expect("Failed to insert a context");
+        assert_context_map_contains_uuid(&mut context_map, &uuid, "LOGIN");
+        assert_context_map_contains_uuid(&mut context_map, &uuid, "LOGOUT");
+    }
+
+    #[test]
+    fn test_given_context_map_when_more_ids_of_an_event_match_a_context_then_it_is_returned_only_once() {
+        let mut context_map = ContextMap::<Message, MockTemplate>::new();
+        let context = {
+            let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+            let patterns = vec![
+                Pattern::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258"),
+                Pattern::with_kind("SSHD_*", PatternKind::Glob).unwrap(),
+            ];
+            let base = BaseContextBuilder::new(Uuid::new_v4(), conditions).patterns(patterns).build();
+            LinearContext::new(base)
+        };
+        context_map.insert(Context::Linear(context)).expect("Failed to insert a context");
+        let event = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").name(Some("SSHD_AUTH")).build();
+        let mut iter = context_map.contexts_iter_mut_by_event(&event);
+        assert_true!(iter.next().is_some());
+        assert_true!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_given_context_map_with_exact_patterns_when_more_ids_of_an_event_match_then_every_context_is_returned_once() {
+        let mut context_map = ContextMap::<Message, MockTemplate>::new();
+        for patterns in vec![vec!["11eaf6f8-0640-460f-aee2-a72d2f2ab258", "SSHD_AUTH"], vec!["SSHD_AUTH"]] {
+            let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+            let base = BaseContextBuilder::new(Uuid::new_v4(), conditions).patterns(patterns).build();
+            context_map.insert(Context::Linear(LinearContext::new(base))).expect("Failed to insert a context");
+        }
+        let event = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").name(Some("SSHD_AUTH")).build();
+        let mut iter = context_map.contexts_iter_mut_by_event(&event);
+        assert_true!(iter.next().is_some());
+        assert_true!(iter.next().is_some());
+        assert_true!(iter.next().is_none());
+    }
 }
@@ -15,6 +15,7 @@ use state::State;
 use context::base::BaseContext;
 use Event;
 use Template;
+use Pattern;
 
 pub struct LinearContext<E, T> where E: Event, T: Template<Event=E> {
     base: BaseContext<E, T>,
@@ -29,12 +30,18 @@ impl<E, T> LinearContext<E, T> where E: Event, T: Template<Event=E> {
         }
     }
 
-    pub fn on_timer(&mut self, event: &Duration, responder: &mut VecDeque<Alert<E>>) {
-        self.base.on_timer(event, &mut self.state, responder);
+    pub fn on_timer(&mut self, now: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        let span = if *now > self.state.now() { *now - self.state.now() } else { Duration::from_secs(0) };
+        self.base.update_action_timers(&span, responder);
+        self.base.on_timer(now, &mut self.state, responder);
     }
 
-    pub fn on_message(&mut self, event: E, responder: &mut VecDeque<Alert<E>>) {
-        self.base.on_message(event, &mut self.state, responder);
+    pub fn on_clock(&mut self, from: &Duration, to: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        self.base.on_clock(from, to, &mut self.state, responder);
+    }
+
+    pub fn on_message(&mut self, event: E, responder: &mut VecDeque<Alert<E>>) -> bool {
+        self.base.on_message(event, &mut self.state, responder)
     }
 
     #[allow(dead_code)]
@@ -42,8 +49,12 @@ impl<E, T> LinearContext<E, T> where E: Event, T: Template<Event=E> {
         self.state.is_open()
     }
 
-    pub fn patterns(&self) -> &[String] {
-        &self.base.patterns
+    pub fn subscriptions(&self) -> Vec<&Pattern> {
+        self.base.subscriptions()
+    }
+
+    pub fn is_catch_all(&self) -> bool {
+        self.base.is_catch_all()
     }
 
     #[allow(dead_code)]
@@ -0,0 +1,55 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+/// Defines what a `MapContext` does when a new key would exceed its
+/// instance limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eviction {
+    /// The state which received a message least recently is closed.
+    Lru,
+    /// The state which was opened first is closed.
+    Oldest,
+    /// The new key isn't opened.
+    Reject,
+}
+
+impl Default for Eviction {
+    fn default() -> Eviction {
+        Eviction::Lru
+    }
+}
+
+mod deser {
+    use super::Eviction;
+    use serde::de::{Deserialize, Deserializer, Error, Visitor};
+
+    impl Deserialize for Eviction {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Eviction, D::Error>
+            where D: Deserializer
+        {
+            struct EvictionVisitor;
+
+            impl Visitor for EvictionVisitor {
+                type Value = Eviction;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<Eviction, E>
+                    where E: Error
+                {
+                    match value {
+                        "lru" => Ok(Eviction::Lru),
+                        "oldest" => Ok(Eviction::Oldest),
+                        "reject" => Ok(Eviction::Reject),
+                        _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(EvictionVisitor)
+        }
+    }
+}
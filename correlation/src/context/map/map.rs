@@ -8,40 +8,235 @@
 
 use std::collections::BTreeMap;
 use std::collections::VecDeque;
+use std::sync::Arc;
 use std::time::Duration;
 
+use conditions::CloseReason;
+use config::ContextId;
 use state::State;
 use context::base::BaseContext;
+use context::map::{Eviction, MissingKey};
 use Event;
 use Template;
+use Pattern;
 use Alert;
+use TemplateContext;
+
+/// The name of the key when `context_id` is a template.
+const CONTEXT_ID: &'static str = "context_id";
 
 pub type ContextKey = Vec<(Vec<u8>, Vec<u8>)>;
 
 pub struct MapContext<E, T> where E: Event, T: Template<Event=E> {
     base: BaseContext<E, T>,
-    map: BTreeMap<ContextKey, State<E>>,
-    context_id: Vec<String>,
+    map: BTreeMap<Arc<ContextKey>, State<E>>,
+    context_id: ContextId<T>,
+    max_instances: Option<usize>,
+    eviction: Eviction,
+    evictions: usize,
+    rejections: usize,
+    missing_key: MissingKey,
+    missing_keys: BTreeMap<String, usize>,
+    key_mapping: Vec<(Pattern, BTreeMap<String, String>)>,
+    /// The keys of the states in eviction order: the first one is the victim.
+    order: BTreeMap<u64, Arc<ContextKey>>,
+    next_order: u64,
+    /// The time of the context clock, the new states are opened at this time.
+    now: Duration,
 }
 
 impl<E, T> MapContext<E, T> where E: Event, T: Template<Event=E> {
-    pub fn new(base: BaseContext<E, T>, context_id: Vec<String>) -> MapContext<E, T> {
+    pub fn new<K: Into<ContextId<T>>>(base: BaseContext<E, T>, context_id: K) -> MapContext<E, T> {
         MapContext {
             base: base,
             map: BTreeMap::new(),
-            context_id: context_id,
+            context_id: context_id.into(),
+            max_instances: None,
+            eviction: Eviction::default(),
+            evictions: 0,
+            rejections: 0,
+            missing_key: MissingKey::default(),
+            missing_keys: BTreeMap::new(),
+            key_mapping: Vec::new(),
+            order: BTreeMap::new(),
+            next_order: 0,
+            now: Duration::from_secs(0),
         }
     }
 
+    pub fn set_max_instances(&mut self, max_instances: Option<usize>, eviction: Eviction) {
+        self.max_instances = max_instances;
+        self.eviction = eviction;
+    }
+
+    pub fn set_missing_key(&mut self, missing_key: MissingKey) {
+        self.missing_key = missing_key;
+    }
+
+    /// Maps the `context_id` keys to different fields depending on which
+    /// pattern matches the event: pattern name -> (key -> field).
+    pub fn set_key_mapping(&mut self, key_mapping: BTreeMap<String, BTreeMap<String, String>>) {
+        self.key_mapping = key_mapping.into_iter()
+                                      .map(|(name, mapping)| {
+                                          let pattern = self.base
+                                                            .subscriptions()
+                                                            .into_iter()
+                                                            .find(|pattern| pattern.name() == name)
+                                                            .cloned()
+                                                            .unwrap_or_else(|| Pattern::new(name));
+                                          (pattern, mapping)
+                                      })
+                                      .collect();
+    }
+
+    /// Returns how many times each `context_id` key was missing from the events.
+    pub fn missing_keys(&self) -> &BTreeMap<String, usize> {
+        &self.missing_keys
+    }
+
+    pub fn instances(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns the number of states closed to make room for new ones.
+    pub fn evictions(&self) -> usize {
+        self.evictions
+    }
+
+    /// Returns the number of messages which couldn't open a new state.
+    pub fn rejections(&self) -> usize {
+        self.rejections
+    }
+
+    fn is_limit_reached(&self) -> bool {
+        self.max_instances.map_or(false, |max_instances| self.map.len() >= max_instances)
+    }
+
+    /// Evicts a state if `event` would open a new one over the instance limit
+    /// of this context or over the global limit, according to the eviction
+    /// policy. Returns `false` if the event should be rejected.
+    fn make_room(&mut self, key: &ContextKey, event: &E, global_limit_reached: bool, responder: &mut VecDeque<Alert<E>>) -> bool {
+        if self.map.contains_key(key) || !self.base.is_opening(event) {
+            return true;
+        }
+
+        if !self.is_limit_reached() && !global_limit_reached {
+            return true;
+        }
+
+        // the `ContextMap` evicts the victim of the global limit from any context
+        // before delivering the event, so only this context can make room here
+        if self.evict(responder) {
+            true
+        } else {
+            trace!("MapContext: rejecting a new state; uuid={}", self.base.uuid());
+            self.rejections += 1;
+            false
+        }
+    }
+
+    /// Returns `true` if `event` would open a new state and this context would
+    /// make room for it by evicting a state. Only the global instance limit
+    /// can stop it, this context's own limit is handled by `on_message()`.
+    pub fn needs_room(&self, event: &E) -> bool {
+        if self.eviction == Eviction::Reject || self.is_limit_reached() ||
+           !self.base.is_accepting(event) || !self.base.is_opening(event) {
+            return false;
+        }
+        match self.key(self.key_values(event)) {
+            Some(key) => !self.map.contains_key(&key),
+            None => false,
+        }
+    }
+
+    /// Returns the time by which the next victim of the eviction is ordered:
+    /// its last message with `lru`, its opening with `oldest`. It's `None`
+    /// if the context doesn't evict or it doesn't have states.
+    pub fn next_victim_time(&self) -> Option<Duration> {
+        let state = match self.eviction {
+            Eviction::Lru | Eviction::Oldest => self.order.values().next().and_then(|key| self.map.get(key)),
+            Eviction::Reject => None,
+        };
+        state.map(|state| {
+            match self.eviction {
+                Eviction::Lru => state.last_message_at(),
+                _ => state.opened_at(),
+            }
+        })
+    }
+
+    /// Closes the next victim of the eviction policy. Returns `false` if
+    /// there isn't any.
+    pub fn evict(&mut self, responder: &mut VecDeque<Alert<E>>) -> bool {
+        let victim = match self.eviction {
+            Eviction::Lru | Eviction::Oldest => self.order.values().next().cloned(),
+            Eviction::Reject => None,
+        };
+
+        match victim.and_then(|victim| self.remove_state(&victim)) {
+            Some(mut state) => {
+                trace!("MapContext: evicting a state; uuid={}", self.base.uuid());
+                self.base.close(&mut state, CloseReason::Evicted, responder);
+                self.evictions += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove_state(&mut self, key: &ContextKey) -> Option<State<E>> {
+        let state = self.map.remove(key);
+        if let Some(ref state) = state {
+            let _ = self.order.remove(&state.order());
+        }
+        state
+    }
+
+    /// Moves the state to the end of the eviction order.
+    fn reorder(order: &mut BTreeMap<u64, Arc<ContextKey>>, next_order: &mut u64, state: &mut State<E>, is_new: bool) {
+        if !is_new {
+            let _ = order.remove(&state.order());
+        }
+        let key = state.shared_key().clone();
+        let _ = order.insert(*next_order, key);
+        state.set_order(*next_order);
+        *next_order += 1;
+    }
+
+    pub fn on_timer(&mut self, now: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        self.update_clock(now, responder);
+        self.on_timer_with(|base, state| base.on_timer(now, state, responder));
+    }
+
+    /// Moves the clock of the context to `now` and advances the timers of
+    /// the actions by the elapsed time. It doesn't update the states.
+    pub fn update_clock(&mut self, now: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        let span = if *now > self.now { *now - self.now } else { Duration::from_secs(0) };
+        self.now = self.now + span;
+        self.base.update_action_timers(&span, responder);
+    }
+
+    /// Calls `f` with every state of this context then removes the closed ones.
+    #[allow(for_kv_map)]
+    pub fn on_timer_with<F>(&mut self, mut f: F) where F: FnMut(&BaseContext<E, T>, &mut State<E>) {
+        for (_, mut state) in &mut self.map {
+            f(&self.base, &mut state);
+        }
+        self.remove_closed_states();
+    }
+
     #[allow(for_kv_map)]
-    pub fn on_timer(&mut self, event: &Duration, responder: &mut VecDeque<Alert<E>>) {
+    pub fn on_clock(&mut self, from: &Duration, to: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        if !self.base.is_boundary_crossed(from, to) {
+            return;
+        }
         for (_, mut state) in &mut self.map {
-            self.base.on_timer(&event, &mut state, responder);
+            self.base.on_clock(from, to, &mut state, responder);
         }
         self.remove_closed_states();
     }
 
-    fn get_closed_state_ids(&self) -> Vec<ContextKey> {
+    fn get_closed_state_ids(&self) -> Vec<Arc<ContextKey>> {
         self.map
             .iter()
             .filter_map(|(id, state)| {
@@ -51,26 +246,114 @@ impl<E, T> MapContext<E, T> where E: Event, T: Template<Event=E> {
                     Some(id.clone())
                 }
             })
-            .collect::<Vec<ContextKey>>()
+            .collect::<Vec<Arc<ContextKey>>>()
     }
 
     fn remove_closed_states(&mut self) {
         for id in self.get_closed_state_ids() {
-            let _ = self.map.remove(&id);
+            let _ = self.remove_state(&id);
         }
     }
 
-    pub fn on_message(&mut self, event: E, responder: &mut VecDeque<Alert<E>>) {
-        self.update_state(event, responder);
+    /// Returns `false` if the event was suppressed by the context. If the
+    /// event would open a new state over the instance limit of this context
+    /// or `global_limit_reached` is set, a state is evicted first (see `make_room()`).
+    pub fn on_message(&mut self, event: E, global_limit_reached: bool, responder: &mut VecDeque<Alert<E>>) -> bool {
+        if !self.base.is_accepting(&event) {
+            return true;
+        }
+        let key = match self.key_of(&event) {
+            Some(key) => key,
+            None => {
+                trace!("MapContext: skipping a message with missing keys; uuid={}", self.base.uuid());
+                return true;
+            }
+        };
+        if !self.make_room(&key, &event, global_limit_reached, responder) {
+            return true;
+        }
+        let forwarded = self.update_state(key, event, responder);
         self.remove_closed_states();
+        forwarded
+    }
+
+    /// Returns the name and the value of every key. A value is `None` if the
+    /// key is missing from `event` (or the rendered template is empty).
+    fn key_values(&self, event: &E) -> Vec<(String, Option<Vec<u8>>)> {
+        match self.context_id {
+            ContextId::Keys(ref keys) => {
+                let mapping = self.key_mapping
+                                  .iter()
+                                  .find(|&&(ref pattern, _)| pattern.is_matching(event))
+                                  .map(|&(_, ref mapping)| mapping);
+                keys.iter()
+                    .map(|key| {
+                        let field = mapping.and_then(|mapping| mapping.get(key)).unwrap_or(key);
+                        (key.clone(), event.get(field.as_bytes()).map(|value| value.to_vec()))
+                    })
+                    .collect()
+            }
+            ContextId::Template(ref template) => {
+                let mut template_context = TemplateContext::new(&[][..], &self.base.hyphenated_uuid);
+                template_context.event = Some(event);
+                let mut value = Vec::new();
+                template.format(&template_context, &mut value);
+                let value = if value.is_empty() { None } else { Some(value) };
+                vec![(CONTEXT_ID.to_owned(), value)]
+            }
+        }
+    }
+
+    /// Returns `None` if a key is missing and the event should be skipped.
+    fn key(&self, values: Vec<(String, Option<Vec<u8>>)>) -> Option<ContextKey> {
+        values.into_iter()
+              .map(|(key, value)| {
+                  let value = match (value, &self.missing_key) {
+                      (Some(value), _) => value,
+                      (None, &MissingKey::GroupEmpty) => Vec::new(),
+                      (None, &MissingKey::Skip) => return None,
+                      (None, &MissingKey::Default(ref value)) => value.as_bytes().to_vec(),
+                  };
+                  Some((key.into_bytes(), value))
+              })
+              .collect()
+    }
+
+    /// Computes the key of `event` and counts its missing keys. Returns `None`
+    /// if the event should be skipped.
+    fn key_of(&mut self, event: &E) -> Option<ContextKey> {
+        let values = self.key_values(event);
+        for &(ref key, _) in values.iter().filter(|&&(_, ref value)| value.is_none()) {
+            *self.missing_keys.entry(key.clone()).or_insert(0) += 1;
+        }
+        self.key(values)
     }
 
-    fn update_state(&mut self, event: E, responder: &mut VecDeque<Alert<E>>) {
-        let key = self.context_id.iter().map(|key| {
-                ((&key[..].as_bytes()).to_vec(), event.get(key.as_bytes()).map_or_else(Vec::new, |value| value.to_vec()))
-            }).collect::<ContextKey>();
-        let mut state = self.map.entry(key).or_insert_with(State::new);
-        self.base.on_message(event, &mut state, responder);
+    fn update_state(&mut self, key: ContextKey, event: E, responder: &mut VecDeque<Alert<E>>) -> bool {
+        let is_new = !self.map.contains_key(&key);
+        let mut state = if is_new {
+            let key = Arc::new(key);
+            let now = self.now;
+            self.map.entry(key.clone()).or_insert_with(|| {
+                let mut state = State::with_key(key);
+                state.set_time(now);
+                state
+            })
+        } else {
+            self.map.get_mut(&key).expect("Failed to get an existing state")
+        };
+        let opened_at = state.opened_at();
+        let forwarded = self.base.on_message(event, &mut state, responder);
+        let is_reordered = match self.eviction {
+            Eviction::Lru => true,
+            // the opening time is reset when the state is reopened
+            Eviction::Oldest => is_new || state.opened_at() > opened_at,
+            Eviction::Reject => false,
+        };
+        if is_reordered {
+            MapContext::<E, T>::reorder(&mut self.order, &mut self.next_order, &mut state, is_new);
+        }
+        forwarded
     }
 
     #[allow(dead_code)]
@@ -78,7 +361,11 @@ impl<E, T> MapContext<E, T> where E: Event, T: Template<Event=E> {
         !self.map.is_empty()
     }
 
-    pub fn patterns(&self) -> &[String] {
-        &self.base.patterns
+    pub fn subscriptions(&self) -> Vec<&Pattern> {
+        self.base.subscriptions()
+    }
+
+    pub fn is_catch_all(&self) -> bool {
+        self.base.is_catch_all()
     }
 }
@@ -0,0 +1,59 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+/// Defines what a `MapContext` does with an event which doesn't have
+/// every `context_id` key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MissingKey {
+    /// The missing keys are treated as empty values.
+    GroupEmpty,
+    /// The event isn't delivered to the context.
+    Skip,
+    /// The missing keys get the given value.
+    Default(String),
+}
+
+impl Default for MissingKey {
+    fn default() -> MissingKey {
+        MissingKey::GroupEmpty
+    }
+}
+
+mod deser {
+    use super::MissingKey;
+    use serde::de::{Deserialize, Deserializer, Error, Visitor};
+
+    const DEFAULT_PREFIX: &'static str = "default:";
+
+    impl Deserialize for MissingKey {
+        fn deserialize<D>(deserializer: &mut D) -> Result<MissingKey, D::Error>
+            where D: Deserializer
+        {
+            struct MissingKeyVisitor;
+
+            impl Visitor for MissingKeyVisitor {
+                type Value = MissingKey;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<MissingKey, E>
+                    where E: Error
+                {
+                    match value {
+                        "group_empty" => Ok(MissingKey::GroupEmpty),
+                        "skip" => Ok(MissingKey::Skip),
+                        _ if value.starts_with(DEFAULT_PREFIX) => {
+                            Ok(MissingKey::Default(value[DEFAULT_PREFIX.len()..].to_owned()))
+                        }
+                        _ => Err(E::custom(format!("Unexpected value: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(MissingKeyVisitor)
+        }
+    }
+}
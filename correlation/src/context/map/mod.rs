@@ -6,8 +6,12 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+pub use self::eviction::Eviction;
 pub use self::map::MapContext;
+pub use self::missing_key::MissingKey;
 
+pub mod eviction;
 pub mod map;
+pub mod missing_key;
 #[cfg(test)]
 mod test;
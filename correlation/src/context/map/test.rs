@@ -6,15 +6,23 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use conditions::ConditionsBuilder;
-use context::MapContext;
+use conditions::{CloseReason, ConditionsBuilder};
+use config::ContextId;
+use config::action::ExecCondition;
+use config::action::message::MessageActionBuilder;
+use context::map::{Eviction, MapContext, MissingKey};
 use message::MessageBuilder;
 use test_utils::{MockTemplate, BaseContextBuilder};
 use Message;
+use Event;
+use TemplateContext;
 
 use uuid::Uuid;
 use std::time::Duration;
 use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[test]
 fn test_given_map_context_when_messages_have_the_same_kvpairs_then_they_go_to_the_same_context() {
@@ -55,14 +63,232 @@ fn test_given_map_context_when_messages_have_the_same_kvpairs_then_they_go_to_th
                    .build();
 
     assert_false!(context.is_open());
-    context.on_message(msg1, &mut responder);
+    context.on_message(msg1, false, &mut responder);
     assert_true!(context.is_open());
     context.on_timer(&delta, &mut responder);
-    context.on_message(msg2, &mut responder);
-    context.on_message(msg3, &mut responder);
-    context.on_timer(&delta, &mut responder);
-    context.on_timer(&delta, &mut responder);
+    context.on_message(msg2, false, &mut responder);
+    context.on_message(msg3, false, &mut responder);
+    context.on_timer(&(delta * 2), &mut responder);
+    context.on_timer(&(delta * 3), &mut responder);
     assert_true!(context.is_open());
-    context.on_timer(&delta, &mut responder);
+    context.on_timer(&(delta * 4), &mut responder);
+    assert_false!(context.is_open());
+}
+
+#[test]
+fn test_given_map_context_with_min_size_when_the_threshold_is_reached_for_a_key_then_the_threshold_actions_are_executed() {
+    let mut responder = VecDeque::default();
+    let msg_id = "11eaf6f8-0640-460f-aee2-a72d2f2ab258";
+    let mut context = {
+        let base_context = {
+            let conditions = ConditionsBuilder::new(Duration::from_millis(60))
+                                 .min_size(3)
+                                 .build();
+            let when = ExecCondition {
+                on_closed: false,
+                on_threshold: true,
+                ..ExecCondition::default()
+            };
+            let actions = vec![
+                MessageActionBuilder::<MockTemplate>::new("threshold", MockTemplate::literal(b"threshold")).when(when).build().into(),
+                MessageActionBuilder::<MockTemplate>::new("closed", MockTemplate::literal(b"closed")).build().into(),
+            ];
+            BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).patterns(vec![msg_id.to_owned()]).actions(actions).build()
+        };
+        MapContext::new(base_context, vec!["user_name".to_owned()])
+    };
+    let failed_login = |user_name: &[u8]| MessageBuilder::new(msg_id.as_bytes(), b"message").pair(b"user_name", user_name).build();
+
+    context.on_message(failed_login(b"linus"), false, &mut responder);
+    context.on_message(failed_login(b"linus"), false, &mut responder);
+    context.on_message(failed_login(b"bill"), false, &mut responder);
+    context.on_message(failed_login(b"bill"), false, &mut responder);
+    assert_eq!(0, responder.len());
+    context.on_message(failed_login(b"linus"), false, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(b"threshold", responder[0].message.message());
+    context.on_message(failed_login(b"linus"), false, &mut responder);
+    assert_eq!(1, responder.len());
+    context.on_timer(&Duration::from_millis(60), &mut responder);
     assert_false!(context.is_open());
+    assert_eq!(2, responder.len());
+    assert_eq!(b"closed", responder[1].message.message());
+}
+
+fn limited_map_context(eviction: Eviction) -> MapContext<Message, MockTemplate> {
+    let base_context = {
+        let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+        let actions = vec![
+            MessageActionBuilder::<MockTemplate>::new("closed", MockTemplate::literal(b"closed")).build().into(),
+        ];
+        BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).actions(actions).build()
+    };
+    let mut context = MapContext::new(base_context, vec!["src_ip".to_owned()]);
+    context.set_max_instances(Some(2), eviction);
+    context
+}
+
+fn message_from(src_ip: &[u8]) -> Message {
+    MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").pair(b"src_ip", src_ip).build()
+}
+
+#[test]
+fn test_given_map_context_with_lru_eviction_when_the_limit_is_reached_then_the_least_recently_used_state_is_closed() {
+    let mut responder = VecDeque::default();
+    let mut context = limited_map_context(Eviction::Lru);
+    let delta = Duration::from_millis(10);
+    for (i, src_ip) in [b"1", b"2", b"1", b"3"].iter().enumerate() {
+        context.on_timer(&(delta * (i as u32 + 1)), &mut responder);
+        context.on_message(message_from(*src_ip), false, &mut responder);
+    }
+    assert_eq!(2, context.instances());
+    assert_eq!(1, context.evictions());
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(CloseReason::Evicted), responder[0].close_reason);
+    // the state of "1" is still open, it doesn't need room
+    context.on_message(message_from(b"1"), false, &mut responder);
+    assert_eq!(1, context.evictions());
+    context.on_message(message_from(b"2"), false, &mut responder);
+    assert_eq!(2, context.evictions());
+}
+
+#[test]
+fn test_given_map_context_with_lru_eviction_when_the_states_have_the_same_age_then_the_least_recently_used_state_is_closed() {
+    let mut responder = VecDeque::default();
+    let mut context = limited_map_context(Eviction::Lru);
+    for src_ip in &[b"1", b"2", b"1", b"3", b"1"] {
+        context.on_message(message_from(*src_ip), false, &mut responder);
+    }
+    assert_eq!(2, context.instances());
+    assert_eq!(1, context.evictions());
+}
+
+#[test]
+fn test_given_map_context_with_oldest_eviction_when_the_global_limit_is_reached_then_the_oldest_state_is_closed() {
+    let mut responder = VecDeque::default();
+    let mut context = limited_map_context(Eviction::Oldest);
+    context.on_message(message_from(b"1"), false, &mut responder);
+    context.on_timer(&Duration::from_millis(10), &mut responder);
+    context.on_message(message_from(b"2"), false, &mut responder);
+    context.on_message(message_from(b"1"), false, &mut responder);
+    context.on_message(message_from(b"3"), true, &mut responder);
+    assert_eq!(2, context.instances());
+    assert_eq!(1, context.evictions());
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(CloseReason::Evicted), responder[0].close_reason);
+}
+
+#[test]
+fn test_given_map_context_with_reject_eviction_when_the_limit_is_reached_then_new_keys_are_rejected() {
+    let mut responder = VecDeque::default();
+    let mut context = limited_map_context(Eviction::Reject);
+    context.on_message(message_from(b"1"), false, &mut responder);
+    context.on_message(message_from(b"2"), false, &mut responder);
+    context.on_message(message_from(b"3"), false, &mut responder);
+    context.on_message(message_from(b"2"), false, &mut responder);
+    assert_eq!(2, context.instances());
+    assert_eq!(1, context.rejections());
+    assert_eq!(0, context.evictions());
+    assert_eq!(0, responder.len());
+}
+
+fn instances_of_logins(missing_key: MissingKey) -> usize {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build();
+    let mut context = MapContext::new(base_context, vec!["user_name".to_owned()]);
+    context.set_missing_key(missing_key);
+    let with_user = |user: &[u8]| MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").pair(b"user_name", user).build();
+    let without_user = || MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").build();
+    context.on_message(with_user(b"alice"), false, &mut responder);
+    context.on_message(without_user(), false, &mut responder);
+    context.on_message(without_user(), false, &mut responder);
+    context.on_message(with_user(b"root"), false, &mut responder);
+    assert_eq!(Some(&2), context.missing_keys().get("user_name"));
+    context.instances()
+}
+
+#[test]
+fn test_given_map_context_with_group_empty_policy_when_a_key_is_missing_then_the_events_are_grouped_by_empty_value() {
+    assert_eq!(3, instances_of_logins(MissingKey::GroupEmpty));
+}
+
+#[test]
+fn test_given_map_context_with_skip_policy_when_a_key_is_missing_then_the_event_is_not_delivered() {
+    assert_eq!(2, instances_of_logins(MissingKey::Skip));
+}
+
+#[test]
+fn test_given_map_context_with_default_policy_when_a_key_is_missing_then_the_default_value_is_used() {
+    assert_eq!(2, instances_of_logins(MissingKey::Default("root".to_owned())));
+}
+
+fn lowercase_user_name(context: &TemplateContext<Message>, buffer: &mut Write) {
+    if let Some(user_name) = context.event.and_then(|event| event.get(b"user_name")) {
+        let _ = buffer.write(String::from_utf8_lossy(user_name).to_lowercase().as_bytes());
+    }
+}
+
+#[test]
+fn test_given_map_context_with_template_context_id_when_messages_are_received_then_they_are_grouped_by_the_rendered_key() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build();
+    let template = MockTemplate { with_context: Box::new(lowercase_user_name) };
+    let mut context = MapContext::new(base_context, ContextId::Template(template));
+    context.set_missing_key(MissingKey::Skip);
+    for user_name in &[&b"Alice"[..], b"alice", b"ALICE", b"bob", b""] {
+        let message = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").pair(b"user_name", user_name).build();
+        context.on_message(message, false, &mut responder);
+    }
+    assert_eq!(2, context.instances());
+    assert_eq!(Some(&1), context.missing_keys().get("context_id"));
+}
+
+#[test]
+fn test_given_map_context_with_template_context_id_when_an_event_opens_a_state_over_the_limit_then_the_key_is_rendered_once() {
+    let mut responder = VecDeque::default();
+    let renders = Arc::new(AtomicUsize::new(0));
+    let template = {
+        let renders = renders.clone();
+        MockTemplate {
+            with_context: Box::new(move |context: &TemplateContext<Message>, buffer: &mut Write| {
+                let _ = renders.fetch_add(1, Ordering::SeqCst);
+                lowercase_user_name(context, buffer);
+            }),
+        }
+    };
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build();
+    let mut context = MapContext::new(base_context, ContextId::Template(template));
+    context.set_max_instances(Some(1), Eviction::Lru);
+    for user_name in &[&b"alice"[..], b"bob"] {
+        let message = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").pair(b"user_name", user_name).build();
+        context.on_message(message, false, &mut responder);
+    }
+    assert_eq!(1, context.evictions());
+    assert_eq!(2, renders.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_given_map_context_with_key_mapping_when_events_have_different_field_names_then_they_are_grouped_together() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                           .patterns(vec!["FW_DROP", "WEB_REQ"])
+                           .build();
+    let mut context = MapContext::new(base_context, vec!["ip".to_owned()]);
+    context.set_key_mapping(btreemap!{
+        "FW_DROP".to_owned() => btreemap!{ "ip".to_owned() => "src_ip".to_owned() },
+        "WEB_REQ".to_owned() => btreemap!{ "ip".to_owned() => "client_ip".to_owned() },
+    });
+    let message = |name: &str, key: &[u8], ip: &[u8]| {
+        MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").name(Some(name)).pair(key, ip).build()
+    };
+    context.on_message(message("FW_DROP", b"src_ip", b"10.0.0.1"), false, &mut responder);
+    context.on_message(message("WEB_REQ", b"client_ip", b"10.0.0.1"), false, &mut responder);
+    assert_eq!(1, context.instances());
+    context.on_message(message("WEB_REQ", b"src_ip", b"10.0.0.2"), false, &mut responder);
+    assert_eq!(2, context.instances());
+    assert_eq!(Some(&1), context.missing_keys().get("ip"));
 }
@@ -6,16 +6,18 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::time::Duration;
 
 use config::ContextConfig;
 use Event;
 use Template;
+use Pattern;
 use Alert;
 
 pub use self::linear::LinearContext;
 pub use self::map::MapContext;
+pub use self::window::WindowContext;
 pub use self::base::BaseContext;
 pub use self::context_map::ContextMap;
 
@@ -23,44 +25,112 @@ pub mod base;
 pub mod context_map;
 pub mod linear;
 pub mod map;
+pub mod window;
 #[cfg(test)]
 mod test;
 
 pub enum Context<E, T> where E: Event, T: Template<Event=E> {
     Linear(LinearContext<E, T>),
     Map(MapContext<E, T>),
+    Window(WindowContext<E, T>),
 }
 
 impl<E, T> Context<E, T> where E: Event, T: Template<Event=E> {
-    pub fn on_message(&mut self, event: E, responder: &mut VecDeque<Alert<E>>) {
+    /// Returns `false` if the event was suppressed by the context. A keyed
+    /// context evicts a state if `event` would open a new one over the
+    /// instance limit, or rejects the event.
+    pub fn on_message(&mut self, event: E, global_limit_reached: bool, responder: &mut VecDeque<Alert<E>>) -> bool {
         match *self {
             Context::Linear(ref mut context) => context.on_message(event, responder),
-            Context::Map(ref mut context) => context.on_message(event, responder),
+            Context::Map(ref mut context) => context.on_message(event, global_limit_reached, responder),
+            Context::Window(ref mut context) => context.on_message(event, global_limit_reached, responder),
         }
     }
 
-    pub fn on_timer(&mut self, event: &Duration, responder: &mut VecDeque<Alert<E>>) {
+    fn map(&self) -> Option<&MapContext<E, T>> {
         match *self {
-            Context::Linear(ref mut context) => context.on_timer(event, responder),
-            Context::Map(ref mut context) => context.on_timer(event, responder),
+            Context::Linear(_) => None,
+            Context::Map(ref context) => Some(context),
+            Context::Window(ref context) => Some(context.map()),
         }
     }
 
-    pub fn patterns(&self) -> &[String] {
+    fn map_mut(&mut self) -> Option<&mut MapContext<E, T>> {
         match *self {
-            Context::Linear(ref context) => context.patterns(),
-            Context::Map(ref context) => context.patterns(),
+            Context::Linear(_) => None,
+            Context::Map(ref mut context) => Some(context),
+            Context::Window(ref mut context) => Some(context.map_mut()),
+        }
+    }
+
+    pub fn instances(&self) -> usize {
+        self.map().map_or(0, |context| context.instances())
+    }
+
+    pub fn evictions(&self) -> usize {
+        self.map().map_or(0, |context| context.evictions())
+    }
+
+    pub fn rejections(&self) -> usize {
+        self.map().map_or(0, |context| context.rejections())
+    }
+
+    pub fn missing_keys(&self) -> Option<&BTreeMap<String, usize>> {
+        self.map().map(|context| context.missing_keys())
+    }
+
+    /// Moves the context to the time `now` of the correlator clock.
+    pub fn on_timer(&mut self, now: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        match *self {
+            Context::Linear(ref mut context) => context.on_timer(now, responder),
+            Context::Map(ref mut context) => context.on_timer(now, responder),
+            Context::Window(ref mut context) => context.on_timer(now, responder),
+        }
+    }
+
+    pub fn on_clock(&mut self, from: &Duration, to: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        match *self {
+            Context::Linear(ref mut context) => context.on_clock(from, to, responder),
+            Context::Map(ref mut context) => context.on_clock(from, to, responder),
+            Context::Window(ref mut context) => context.on_clock(from, to, responder),
+        }
+    }
+
+    pub fn subscriptions(&self) -> Vec<&Pattern> {
+        match *self {
+            Context::Linear(ref context) => context.subscriptions(),
+            Context::Map(ref context) => context.subscriptions(),
+            Context::Window(ref context) => context.subscriptions(),
+        }
+    }
+
+    /// Returns `true` if the context receives every event regardless of its subscriptions.
+    pub fn is_catch_all(&self) -> bool {
+        match *self {
+            Context::Linear(ref context) => context.is_catch_all(),
+            Context::Map(ref context) => context.is_catch_all(),
+            Context::Window(ref context) => context.is_catch_all(),
         }
     }
 }
 
 impl<E, T> From<ContextConfig<T>> for Context<E, T> where E: Event, T: Template<Event=E> {
     fn from(config: ContextConfig<T>) -> Context<E, T> {
-        let ContextConfig {name, uuid, conditions, context_id, actions, patterns} = config;
-        let base = BaseContext {uuid: uuid, conditions: conditions, name: name, patterns: patterns, actions: actions};
+        let ContextConfig {name, uuid, conditions, context_id, actions, patterns, max_instances, eviction, missing_key, key_mapping, aggregates} = config;
+        let base = BaseContext {uuid: uuid, hyphenated_uuid: uuid.to_hyphenated_string(), conditions: conditions, name: name, patterns: patterns, actions: actions, aggregates: aggregates};
 
-        if let Some(context_id) = context_id {
-            Context::Map(MapContext::new(base, context_id))
+        if base.conditions.sliding {
+            let mut context = WindowContext::new(base, context_id.unwrap_or_default());
+            context.set_max_instances(max_instances, eviction);
+            context.set_missing_key(missing_key);
+            context.set_key_mapping(key_mapping);
+            Context::Window(context)
+        } else if let Some(context_id) = context_id {
+            let mut context = MapContext::new(base, context_id);
+            context.set_max_instances(max_instances, eviction);
+            context.set_missing_key(missing_key);
+            context.set_key_mapping(key_mapping);
+            Context::Map(context)
         } else {
             Context::Linear(LinearContext::new(base))
         }
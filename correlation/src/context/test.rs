@@ -11,10 +11,15 @@ use std::time::Duration;
 use std::collections::VecDeque;
 
 use message::MessageBuilder;
-use context::LinearContext;
-use conditions::ConditionsBuilder;
+use context::{LinearContext, WindowContext};
+use conditions::{CloseReason, ConditionsBuilder, OutOfOrder, Overflow, Reopen};
+use config::action::ExecCondition;
+use config::action::message::MessageActionBuilder;
 use test_utils::{MockTemplate, BaseContextBuilder};
+use state::State;
 use Message;
+use Event;
+use {Aggregate, AggregateFunction, Operator, Pattern};
 
 #[test]
 fn test_given_close_condition_with_timeout_when_the_timeout_expires_then_the_condition_is_met() {
@@ -34,9 +39,9 @@ fn test_given_close_condition_with_timeout_when_the_timeout_expires_then_the_con
     assert_true!(context.is_open());
     context.on_timer(&Duration::from_millis(50), &mut responder);
     assert_true!(context.is_open());
-    context.on_timer(&Duration::from_millis(49), &mut responder);
+    context.on_timer(&Duration::from_millis(99), &mut responder);
     assert_true!(context.is_open());
-    context.on_timer(&Duration::from_millis(1), &mut responder);
+    context.on_timer(&Duration::from_millis(100), &mut responder);
     assert_false!(context.is_open());
 }
 
@@ -84,9 +89,9 @@ fn test_given_close_condition_with_renew_timeout_when_the_timeout_expires_withou
     assert_true!(context.is_open());
     context.on_timer(&Duration::from_millis(8), &mut responder);
     assert_true!(context.is_open());
-    context.on_timer(&Duration::from_millis(1), &mut responder);
+    context.on_timer(&Duration::from_millis(9), &mut responder);
     assert_true!(context.is_open());
-    context.on_timer(&Duration::from_millis(1), &mut responder);
+    context.on_timer(&Duration::from_millis(10), &mut responder);
     assert_false!(context.is_open());
 }
 
@@ -111,10 +116,356 @@ fn test_given_close_condition_with_renew_timeout_when_the_timeout_expires_with_r
     assert_true!(context.is_open());
     context.on_timer(&Duration::from_millis(8), &mut responder);
     assert_true!(context.is_open());
-    context.on_timer(&Duration::from_millis(1), &mut responder);
+    context.on_timer(&Duration::from_millis(9), &mut responder);
     assert_true!(context.is_open());
     context.on_message(msg1.clone(), &mut responder);
     assert_true!(context.is_open());
-    context.on_timer(&Duration::from_millis(1), &mut responder);
+    context.on_timer(&Duration::from_millis(10), &mut responder);
     assert_true!(context.is_open());
 }
+
+fn login_read_logout_context(out_of_order: OutOfOrder) -> LinearContext<Message, MockTemplate> {
+    let patterns = vec![
+        "LOGIN".to_owned(),
+        "MAIL_READ".to_owned(),
+        "LOGOUT".to_owned(),
+    ];
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .ordered(true)
+                         .out_of_order(out_of_order)
+                         .build();
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).patterns(patterns).build();
+    LinearContext::new(base)
+}
+
+fn named_message(name: &str) -> Message {
+    MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").name(Some(name)).build()
+}
+
+#[test]
+fn test_given_ordered_context_when_the_events_arrive_in_order_then_the_context_is_closed() {
+    let mut responder = VecDeque::default();
+    let mut context = login_read_logout_context(OutOfOrder::Ignore);
+    context.on_message(named_message("MAIL_READ"), &mut responder);
+    assert_false!(context.is_open());
+    context.on_message(named_message("LOGIN"), &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(named_message("MAIL_READ"), &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    assert_false!(context.is_open());
+}
+
+#[test]
+fn test_given_ordered_context_when_an_event_is_skipped_then_the_context_is_not_closed() {
+    let mut responder = VecDeque::default();
+    let mut context = login_read_logout_context(OutOfOrder::Ignore);
+    context.on_message(named_message("LOGIN"), &mut responder);
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(named_message("MAIL_READ"), &mut responder);
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    assert_false!(context.is_open());
+}
+
+#[test]
+fn test_given_ordered_context_with_reset_policy_when_an_event_is_out_of_order_then_the_sequence_is_restarted() {
+    let mut responder = VecDeque::default();
+    let mut context = login_read_logout_context(OutOfOrder::Reset);
+    context.on_message(named_message("LOGIN"), &mut responder);
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    assert_false!(context.is_open());
+    context.on_message(named_message("LOGIN"), &mut responder);
+    context.on_message(named_message("LOGIN"), &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(named_message("MAIL_READ"), &mut responder);
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    assert_false!(context.is_open());
+}
+
+#[test]
+fn test_given_ordered_context_with_close_policy_when_an_event_is_out_of_order_then_the_context_is_closed() {
+    let mut responder = VecDeque::default();
+    let mut context = login_read_logout_context(OutOfOrder::Close);
+    context.on_message(named_message("LOGIN"), &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    assert_false!(context.is_open());
+}
+
+#[test]
+fn test_given_context_when_it_is_closed_then_the_alerts_contain_the_close_reason() {
+    let mut responder = VecDeque::default();
+    let patterns = vec![
+        "LOGIN".to_owned(),
+        "LOGOUT".to_owned(),
+    ];
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .first_opens(true)
+                         .last_closes(true)
+                         .build();
+    let actions = vec![MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::literal(b"message")).build().into()];
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).patterns(patterns).actions(actions).build();
+    let mut context = LinearContext::new(base);
+    context.on_message(named_message("LOGIN"), &mut responder);
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    context.on_message(named_message("LOGIN"), &mut responder);
+    context.on_timer(&Duration::from_millis(100), &mut responder);
+    assert_eq!(2, responder.len());
+    assert_eq!(Some(CloseReason::Completed), responder[0].close_reason);
+    assert_eq!(Some(CloseReason::Timeout), responder[1].close_reason);
+}
+
+#[test]
+fn test_given_sliding_window_context_when_messages_expire_then_the_threshold_is_evaluated_against_the_window() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(30))
+                         .sliding(true)
+                         .min_size(3)
+                         .build();
+    let when = ExecCondition {
+        on_closed: false,
+        on_threshold: true,
+        ..ExecCondition::default()
+    };
+    let actions = vec![MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::context_len()).when(when).build().into()];
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).actions(actions).build();
+    let mut context = WindowContext::new(base, vec!["user_name".to_owned()]);
+    let msg = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").pair(b"user_name", b"linus").build();
+
+    context.on_message(msg.clone(), false, &mut responder);
+    context.on_timer(&Duration::from_millis(10), &mut responder);
+    context.on_message(msg.clone(), false, &mut responder);
+    context.on_timer(&Duration::from_millis(20), &mut responder);
+    context.on_message(msg.clone(), false, &mut responder);
+    assert_eq!(1, responder.len());
+    context.on_timer(&Duration::from_millis(35), &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(msg.clone(), false, &mut responder);
+    assert_eq!(2, responder.len());
+    assert_eq!(b"3", responder[1].message.message());
+    context.on_timer(&Duration::from_millis(64), &mut responder);
+    assert_true!(context.is_open());
+    context.on_timer(&Duration::from_millis(65), &mut responder);
+    assert_false!(context.is_open());
+    assert_eq!(2, responder.len());
+}
+
+#[test]
+fn test_given_sliding_window_context_with_aggregates_when_messages_expire_then_the_aggregates_are_recomputed() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(30))
+                         .sliding(true)
+                         .min_size(2)
+                         .build();
+    let when = ExecCondition {
+        on_closed: false,
+        on_threshold: true,
+        ..ExecCondition::default()
+    };
+    let actions = vec![MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::context_len()).when(when).build().into()];
+    let aggregates = btreemap!{
+        "bytes".to_owned() => Aggregate::new(AggregateFunction::Sum, Some("bytes".to_owned())),
+    };
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                   .actions(actions)
+                   .aggregates(aggregates)
+                   .build();
+    let mut context = WindowContext::new(base, vec!["user_name".to_owned()]);
+    let msg = |bytes: &[u8]| {
+        MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message")
+            .pair(b"user_name", b"linus")
+            .pair(b"bytes", bytes)
+            .build()
+    };
+
+    context.on_message(msg(b"100"), false, &mut responder);
+    context.on_timer(&Duration::from_millis(20), &mut responder);
+    context.on_message(msg(b"10"), false, &mut responder);
+    assert_eq!(1, responder.len());
+    assert_eq!(Some(&b"110"[..]), responder[0].message.get(b"bytes"));
+    context.on_timer(&Duration::from_millis(30), &mut responder);
+    context.on_message(msg(b"1"), false, &mut responder);
+    assert_eq!(2, responder.len());
+    assert_eq!(Some(&b"11"[..]), responder[1].message.get(b"bytes"));
+}
+
+#[test]
+fn test_given_context_with_predicates_when_a_message_does_not_match_them_then_it_is_not_added_to_the_context() {
+    let mut responder = VecDeque::default();
+    let patterns = vec![
+        Pattern::new("AUTH").predicate("status", Operator::Equals("failed".to_owned())),
+    ];
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .max_size(2)
+                         .build();
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).patterns(patterns).build();
+    let mut context = LinearContext::new(base);
+    let failed = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").name(Some("AUTH")).pair(b"status", b"failed").build();
+    let succeeded = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", "message").name(Some("AUTH")).pair(b"status", b"ok").build();
+    context.on_message(succeeded.clone(), &mut responder);
+    assert_false!(context.is_open());
+    context.on_message(failed.clone(), &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(succeeded, &mut responder);
+    assert_true!(context.is_open());
+    context.on_message(failed, &mut responder);
+    assert_false!(context.is_open());
+}
+
+fn stored_messages_with_overflow(overflow: Overflow) -> (Vec<Vec<u8>>, usize, bool) {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .max_size(2)
+                         .overflow(overflow)
+                         .build();
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build();
+    let mut state = State::new();
+    for i in 1..8 {
+        let message = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", &i.to_string()).build();
+        base.on_message(message, &mut state, &mut responder);
+    }
+    state.normalize();
+    let messages = state.messages().iter().map(|message| message.message().to_vec()).collect();
+    (messages, state.event_count(), state.is_open())
+}
+
+#[test]
+fn test_given_context_with_discard_overflow_when_max_size_is_reached_then_only_the_first_messages_are_stored() {
+    let (messages, event_count, is_open) = stored_messages_with_overflow(Overflow::Discard);
+    assert_eq!(vec![b"1".to_vec(), b"2".to_vec()], messages);
+    assert_eq!(7, event_count);
+    assert_true!(is_open);
+}
+
+#[test]
+fn test_given_context_with_ring_overflow_when_max_size_is_reached_then_the_last_messages_are_stored() {
+    let (messages, event_count, is_open) = stored_messages_with_overflow(Overflow::Ring);
+    assert_eq!(vec![b"6".to_vec(), b"7".to_vec()], messages);
+    assert_eq!(7, event_count);
+    assert_true!(is_open);
+}
+
+#[test]
+fn test_given_context_with_first_last_overflow_when_max_size_is_reached_then_the_first_and_the_last_messages_are_stored() {
+    let (messages, event_count, is_open) = stored_messages_with_overflow(Overflow::FirstLast);
+    assert_eq!(vec![b"1".to_vec(), b"2".to_vec(), b"6".to_vec(), b"7".to_vec()], messages);
+    assert_eq!(7, event_count);
+    assert_true!(is_open);
+}
+
+#[test]
+fn test_given_context_with_first_last_overflow_when_the_last_messages_are_replaced_many_times_then_they_stay_in_order() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .max_size(3)
+                         .overflow(Overflow::FirstLast)
+                         .build();
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions).build();
+    let mut state = State::new();
+    for i in 1..21 {
+        let message = MessageBuilder::new("11eaf6f8-0640-460f-aee2-a72d2f2ab258", &i.to_string()).build();
+        base.on_message(message, &mut state, &mut responder);
+        assert_eq!(i.to_string().as_bytes(), state.last_message().unwrap().message());
+        if i == 11 {
+            state.normalize();
+            let messages: Vec<&[u8]> = state.messages().iter().map(|message| message.message()).collect();
+            assert_eq!(vec![&b"1"[..], b"2", b"3", b"9", b"10", b"11"], messages);
+        }
+    }
+    state.normalize();
+    let messages: Vec<&[u8]> = state.messages().iter().map(|message| message.message()).collect();
+    assert_eq!(vec![&b"1"[..], b"2", b"3", b"18", b"19", b"20"], messages);
+    assert_eq!(20, state.event_count());
+}
+
+#[test]
+fn test_given_context_with_close_overflow_when_max_size_is_reached_then_the_context_is_closed_and_reopened() {
+    let (messages, event_count, is_open) = stored_messages_with_overflow(Overflow::Close);
+    assert_eq!(1, messages.len());
+    assert_eq!(1, event_count);
+    assert_true!(is_open);
+}
+
+fn alerts_of_double_login(on_reopen: Reopen) -> Vec<(Option<CloseReason>, Vec<u8>)> {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .first_opens(true)
+                         .last_closes(true)
+                         .on_reopen(on_reopen)
+                         .build();
+    let actions = vec![MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::event_count()).build().into()];
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                   .patterns(vec!["LOGIN", "MAIL_READ", "LOGOUT"])
+                   .actions(actions)
+                   .build();
+    let mut context = LinearContext::new(base);
+    for name in &["LOGIN", "MAIL_READ", "LOGIN", "LOGOUT"] {
+        context.on_message(named_message(name), &mut responder);
+    }
+    assert_false!(context.is_open());
+    responder.iter().map(|alert| (alert.close_reason, alert.message.message().to_vec())).collect()
+}
+
+#[test]
+fn test_given_context_with_append_reopen_policy_when_it_is_opened_twice_then_the_events_are_merged() {
+    let alerts = alerts_of_double_login(Reopen::Append);
+    assert_eq!(vec![(Some(CloseReason::Completed), b"4".to_vec())], alerts);
+}
+
+#[test]
+fn test_given_context_with_close_and_reopen_policy_when_it_is_opened_twice_then_a_new_instance_is_started() {
+    let alerts = alerts_of_double_login(Reopen::CloseAndReopen);
+    assert_eq!(vec![(Some(CloseReason::Reopened), b"2".to_vec()), (Some(CloseReason::Completed), b"2".to_vec())], alerts);
+}
+
+#[test]
+fn test_given_context_with_ignore_reopen_policy_when_it_is_opened_twice_then_the_second_opening_event_is_dropped() {
+    let alerts = alerts_of_double_login(Reopen::Ignore);
+    assert_eq!(vec![(Some(CloseReason::Completed), b"3".to_vec())], alerts);
+}
+
+#[test]
+fn test_given_context_without_patterns_when_it_has_opening_patterns_then_it_receives_unrelated_events() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .opens(vec!["LOGIN"])
+                         .closes(vec!["LOGOUT"])
+                         .build();
+    let actions = vec![MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::event_count()).build().into()];
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                   .patterns(Vec::<Pattern>::new())
+                   .actions(actions)
+                   .build();
+    let mut context = LinearContext::new(base);
+    for name in &["LOGIN", "MAIL_READ", "LOGOUT"] {
+        context.on_message(named_message(name), &mut responder);
+    }
+    assert_false!(context.is_open());
+    assert_eq!(1, responder.len());
+    assert_eq!(b"3", responder[0].message.message());
+}
+
+#[test]
+fn test_given_ordered_context_with_discard_overflow_when_the_events_arrive_in_order_then_the_context_is_completed() {
+    let mut responder = VecDeque::default();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100))
+                         .ordered(true)
+                         .max_size(2)
+                         .overflow(Overflow::Discard)
+                         .build();
+    let actions = vec![MessageActionBuilder::<MockTemplate>::new("uuid", MockTemplate::close_reason()).build().into()];
+    let base = BaseContextBuilder::<Message, MockTemplate>::new(Uuid::new_v4(), conditions)
+                   .patterns(vec!["LOGIN", "MAIL_READ", "MAIL_SENT", "LOGOUT"])
+                   .actions(actions)
+                   .build();
+    let mut context = LinearContext::new(base);
+    for name in &["LOGIN", "MAIL_READ", "MAIL_SENT"] {
+        context.on_message(named_message(name), &mut responder);
+        assert_true!(context.is_open());
+    }
+    context.on_message(named_message("LOGOUT"), &mut responder);
+    assert_false!(context.is_open());
+    assert_eq!(1, responder.len());
+    assert_eq!(b"completed", responder[0].message.message());
+}
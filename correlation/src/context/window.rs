@@ -0,0 +1,113 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use conditions::CloseReason;
+use context::base::BaseContext;
+use context::map::{Eviction, MapContext, MissingKey};
+use config::ContextId;
+use state::State;
+use Event;
+use Template;
+use Pattern;
+use Alert;
+
+/// A sliding window context. Every message stays in the window for
+/// `timeout` time, the conditions are evaluated against the messages
+/// which are currently in the window.
+pub struct WindowContext<E, T> where E: Event, T: Template<Event=E> {
+    map: MapContext<E, T>,
+}
+
+impl<E, T> WindowContext<E, T> where E: Event, T: Template<Event=E> {
+    pub fn new<K: Into<ContextId<T>>>(base: BaseContext<E, T>, context_id: K) -> WindowContext<E, T> {
+        WindowContext {
+            map: MapContext::new(base, context_id),
+        }
+    }
+
+    pub fn on_timer(&mut self, now: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        self.map.update_clock(now, responder);
+        self.map.on_timer_with(|base, state| WindowContext::slide(base, now, state, responder));
+    }
+
+    pub fn on_clock(&mut self, from: &Duration, to: &Duration, responder: &mut VecDeque<Alert<E>>) {
+        self.map.on_clock(from, to, responder);
+    }
+
+    fn slide(base: &BaseContext<E, T>,
+             now: &Duration,
+             state: &mut State<E>,
+             responder: &mut VecDeque<Alert<E>>) {
+        state.set_time(*now);
+        if !state.is_open() {
+            return;
+        }
+
+        state.normalize();
+        let expired = state.expired_messages(base.conditions.timeout);
+
+        if expired == state.messages().len() {
+            // the actions can still see the last messages of the window
+            base.close(state, CloseReason::Timeout, responder);
+            return;
+        }
+
+        state.remove_messages(expired);
+        if expired > 0 && !base.aggregates.is_empty() {
+            state.rebuild_aggregates(&base.aggregates);
+        }
+
+        if base.conditions.min_size.map_or(false, |min_size| state.event_count() < min_size) {
+            state.rearm_threshold();
+        }
+
+        if let Some(reason) = base.closing_reason(state) {
+            base.close(state, reason, responder);
+        }
+    }
+
+    pub fn set_max_instances(&mut self, max_instances: Option<usize>, eviction: Eviction) {
+        self.map.set_max_instances(max_instances, eviction);
+    }
+
+    pub fn set_missing_key(&mut self, missing_key: MissingKey) {
+        self.map.set_missing_key(missing_key);
+    }
+
+    pub fn set_key_mapping(&mut self, key_mapping: BTreeMap<String, BTreeMap<String, String>>) {
+        self.map.set_key_mapping(key_mapping);
+    }
+
+    pub fn map(&self) -> &MapContext<E, T> {
+        &self.map
+    }
+
+    pub fn map_mut(&mut self) -> &mut MapContext<E, T> {
+        &mut self.map
+    }
+
+    pub fn on_message(&mut self, event: E, global_limit_reached: bool, responder: &mut VecDeque<Alert<E>>) -> bool {
+        self.map.on_message(event, global_limit_reached, responder)
+    }
+
+    #[allow(dead_code)]
+    pub fn is_open(&self) -> bool {
+        self.map.is_open()
+    }
+
+    pub fn subscriptions(&self) -> Vec<&Pattern> {
+        self.map.subscriptions()
+    }
+
+    pub fn is_catch_all(&self) -> bool {
+        self.map.is_catch_all()
+    }
+}
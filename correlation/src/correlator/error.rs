@@ -6,11 +6,14 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+use regex;
 use serde_json;
 use serde_yaml;
 use std::io;
 use std::fmt::{Display, Error as FmtError, Formatter};
 
+use uuid::Uuid;
+
 use CompileError;
 
 #[derive(Debug)]
@@ -19,6 +22,10 @@ pub enum Error {
     SerdeJson(serde_json::error::Error),
     SerdeYaml(serde_yaml::error::Error),
     TemplateCompileError(CompileError),
+    /// The regular expressions of the patterns can't be looked up together.
+    Regex(regex::Error),
+    /// The uuids of the contexts which can send messages back to each other endlessly.
+    LoopbackCycle(Vec<Uuid>),
     UnsupportedFileExtension(String),
     FileExtensionNotFound,
     NotUtf8FileName
@@ -48,6 +55,12 @@ impl From<CompileError> for Error {
     }
 }
 
+impl From<regex::Error> for Error {
+    fn from(error: regex::Error) -> Error {
+        Error::Regex(error)
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, formatter: &mut Formatter) -> Result<(), FmtError> {
         match *self {
@@ -55,6 +68,11 @@ impl Display for Error {
             Error::SerdeJson(ref error) => error.fmt(formatter),
             Error::SerdeYaml(ref error) => error.fmt(formatter),
             Error::TemplateCompileError(ref error) => error.fmt(formatter),
+            Error::Regex(ref error) => error.fmt(formatter),
+            Error::LoopbackCycle(ref contexts) => {
+                let contexts = contexts.iter().map(|uuid| uuid.to_hyphenated_string()).collect::<Vec<String>>();
+                formatter.write_fmt(format_args!("The loopback messages of the contexts form a cycle: {}", contexts.join(" -> ")))
+            }
             Error::UnsupportedFileExtension(ref ext) => formatter.write_fmt(format_args!("File extension '{}' is not supported", ext)),
             Error::FileExtensionNotFound => formatter.write_str("The configuration file does not have an extension"),
             Error::NotUtf8FileName => formatter.write_str("File name is not a valid UTF-8 character sequence"),
@@ -69,6 +87,8 @@ impl ::std::error::Error for Error {
             Error::SerdeJson(ref error) => error.description(),
             Error::SerdeYaml(ref error) => error.description(),
             Error::TemplateCompileError(ref error) => error.description(),
+            Error::Regex(ref error) => error.description(),
+            Error::LoopbackCycle(_) => "The loopback messages of the contexts form a cycle",
             Error::UnsupportedFileExtension(_) => "The correlation library does not support this file format",
             Error::FileExtensionNotFound => "The configuration file does not have file extension",
             Error::NotUtf8FileName => "File name is not a valid UTF-8 character sequence",
@@ -80,7 +100,9 @@ impl ::std::error::Error for Error {
             Error::SerdeJson(ref error) => error.cause(),
             Error::SerdeYaml(ref error) => error.cause(),
             Error::TemplateCompileError(ref error) => error.cause(),
-            Error::UnsupportedFileExtension(_) |
+            Error::Regex(ref error) => error.cause(),
+            Error::LoopbackCycle(_) |
+                Error::UnsupportedFileExtension(_) |
                 Error::FileExtensionNotFound |
                 Error::NotUtf8FileName => None,
         }
@@ -0,0 +1,287 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::cmp;
+use std::str;
+use std::time::Duration;
+
+use Event;
+
+/// The processing time after which an idle stream advances the clock, in milliseconds.
+pub const IDLE_TIMEOUT_DEFAULT_MS: u64 = 60000;
+
+/// Drives the clock of a `Correlator` by the timestamps of the events.
+///
+/// The clock follows the watermark: the greatest timestamp seen so far minus
+/// `allowed_lateness`. Events older than the watermark are late and they
+/// are not correlated. If no event arrives for `idle_timeout` processing
+/// time, the watermark is advanced by the processing time, so the contexts
+/// can time out while the stream is idle.
+#[derive(Clone, Debug)]
+pub struct EventTime {
+    field: String,
+    allowed_lateness: Duration,
+    idle_timeout: Option<Duration>,
+    max_timestamp: Option<Duration>,
+    idle_time: Duration,
+    late_events: usize,
+}
+
+impl EventTime {
+    pub fn new<S: Into<String>>(field: S, allowed_lateness: Duration) -> EventTime {
+        EventTime {
+            field: field.into(),
+            allowed_lateness: allowed_lateness,
+            idle_timeout: Some(Duration::from_millis(IDLE_TIMEOUT_DEFAULT_MS)),
+            max_timestamp: None,
+            idle_time: Duration::from_secs(0),
+            late_events: 0,
+        }
+    }
+
+    /// Sets the processing time after which an idle stream advances the
+    /// clock. `None` disables it, then only the events advance the clock.
+    pub fn idle_timeout(mut self, idle_timeout: Option<Duration>) -> EventTime {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn allowed_lateness(&self) -> Duration {
+        self.allowed_lateness
+    }
+
+    pub fn watermark(&self) -> Option<Duration> {
+        self.max_timestamp.map(|max_timestamp| {
+            if max_timestamp > self.allowed_lateness {
+                max_timestamp - self.allowed_lateness
+            } else {
+                Duration::from_secs(0)
+            }
+        })
+    }
+
+    /// Returns the number of events dropped because they were older than the watermark.
+    pub fn late_events(&self) -> usize {
+        self.late_events
+    }
+
+    /// Returns the timestamp of `event`, or `None` if it doesn't have a valid one.
+    pub fn timestamp<E: Event>(&self, event: &E) -> Option<Duration> {
+        let timestamp = event.get(self.field.as_bytes()).and_then(parse_timestamp);
+        if timestamp.is_none() {
+            trace!("EventTime: message without a valid timestamp; field={}", &self.field);
+        }
+        timestamp
+    }
+
+    /// Moves the watermark by an event with `timestamp`. Returns `false` if
+    /// the event is late.
+    pub fn accept(&mut self, timestamp: Duration) -> bool {
+        if self.watermark().map_or(false, |watermark| timestamp < watermark) {
+            trace!("EventTime: dropping a late message");
+            self.late_events += 1;
+            return false;
+        }
+        if self.max_timestamp.map_or(true, |max_timestamp| timestamp > max_timestamp) {
+            self.max_timestamp = Some(timestamp);
+        }
+        self.idle_time = Duration::from_secs(0);
+        true
+    }
+
+    /// Advances the watermark by the processing time spent after the stream
+    /// has been idle for `idle_timeout`. Returns `true` if the watermark moved.
+    pub fn on_idle(&mut self, span: Duration) -> bool {
+        let idle_time = self.idle_time;
+        self.idle_time = idle_time + span;
+        match (self.idle_timeout, self.max_timestamp) {
+            (Some(idle_timeout), Some(max_timestamp)) if self.idle_time > idle_timeout => {
+                self.max_timestamp = Some(max_timestamp + self.idle_time - cmp::max(idle_time, idle_timeout));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parses a UNIX timestamp (`1465550000` or `1465550000.123`) or an ISO 8601
+/// date (`2016-06-10T10:13:20.123+02:00`) into the time elapsed since the epoch.
+pub fn parse_timestamp(value: &[u8]) -> Option<Duration> {
+    let value = match str::from_utf8(value) {
+        Ok(value) => value.trim().as_bytes(),
+        Err(_) => return None,
+    };
+    parse_unix_timestamp(value).or_else(|| parse_iso8601(value))
+}
+
+fn is_digit(c: u8) -> bool {
+    c >= b'0' && c <= b'9'
+}
+
+fn parse_number(value: &[u8]) -> Option<i64> {
+    if value.is_empty() || value.len() > 18 || !value.iter().all(|c| is_digit(*c)) {
+        return None;
+    }
+    Some(value.iter().fold(0, |number, c| number * 10 + (c - b'0') as i64))
+}
+
+fn parse_fraction(value: &[u8]) -> Option<u32> {
+    let digits = &value[..cmp::min(value.len(), 9)];
+    parse_number(digits).map(|number| number as u32 * 10u32.pow(9 - digits.len() as u32))
+}
+
+fn parse_unix_timestamp(value: &[u8]) -> Option<Duration> {
+    let mut parts = value.splitn(2, |c| *c == b'.');
+    let secs = parts.next().and_then(parse_number);
+    let nanos = match parts.next() {
+        Some(fraction) => parse_fraction(fraction),
+        None => Some(0),
+    };
+    match (secs, nanos) {
+        (Some(secs), Some(nanos)) => Some(Duration::new(secs as u64, nanos)),
+        _ => None,
+    }
+}
+
+fn parse_iso8601(value: &[u8]) -> Option<Duration> {
+    if value.len() < 19 || value[4] != b'-' || value[7] != b'-' || (value[10] != b'T' && value[10] != b' ') ||
+       value[13] != b':' || value[16] != b':' {
+        return None;
+    }
+
+    let fields = (parse_number(&value[0..4]),
+                  parse_number(&value[5..7]),
+                  parse_number(&value[8..10]),
+                  parse_number(&value[11..13]),
+                  parse_number(&value[14..16]),
+                  parse_number(&value[17..19]));
+    let (year, month, day, hour, minute, second) = match fields {
+        (Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second)) => {
+            (year, month, day, hour, minute, second)
+        }
+        _ => return None,
+    };
+
+    if month < 1 || month > 12 || day < 1 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut rest = &value[19..];
+    let mut nanos = 0;
+    if rest.first() == Some(&b'.') {
+        let digits = rest[1..].iter().take_while(|c| is_digit(**c)).count();
+        nanos = match parse_fraction(&rest[1..digits + 1]) {
+            Some(nanos) => nanos,
+            None => return None,
+        };
+        rest = &rest[digits + 1..];
+    }
+
+    let offset = if rest.is_empty() || rest == b"Z" {
+        0
+    } else {
+        match str::from_utf8(rest).ok().and_then(parse_utc_offset) {
+            Some(offset) => offset,
+            None => return None,
+        }
+    };
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    if seconds < 0 {
+        None
+    } else {
+        Some(Duration::new(seconds as u64, nanos))
+    }
+}
+
+/// Parses `+HH:MM`, `+HHMM` or `+HH` into seconds.
+pub fn parse_utc_offset(value: &str) -> Option<i64> {
+    let sign = match value.as_bytes().first() {
+        Some(&b'+') => 1,
+        Some(&b'-') => -1,
+        _ => return None,
+    };
+    let value = value[1..].replace(":", "");
+    let value = value.as_bytes();
+    let offset = match value.len() {
+        2 => parse_number(value).map(|hours| hours * 3600),
+        4 => {
+            parse_number(&value[0..2]).and_then(|hours| parse_number(&value[2..4]).map(|minutes| hours * 3600 + minutes * 60))
+        }
+        _ => None,
+    };
+    offset.map(|offset| sign * offset)
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EventTime, parse_timestamp};
+    use message::MessageBuilder;
+    use std::time::Duration;
+
+    #[test]
+    fn test_given_unix_timestamp_when_it_is_parsed_then_we_get_the_time_since_the_epoch() {
+        assert_eq!(Some(Duration::new(1465553600, 0)), parse_timestamp(b"1465553600"));
+        assert_eq!(Some(Duration::new(1465553600, 120_000_000)), parse_timestamp(b"1465553600.12"));
+        assert_eq!(None, parse_timestamp(b"1465553600."));
+        assert_eq!(None, parse_timestamp(b"yesterday"));
+    }
+
+    #[test]
+    fn test_given_iso8601_date_when_it_is_parsed_then_we_get_the_time_since_the_epoch() {
+        assert_eq!(Some(Duration::new(1465553600, 0)), parse_timestamp(b"2016-06-10T10:13:20Z"));
+        assert_eq!(Some(Duration::new(1465553600, 0)), parse_timestamp(b"2016-06-10T10:13:20"));
+        assert_eq!(Some(Duration::new(1465553600, 500_000_000)), parse_timestamp(b"2016-06-10 12:13:20.5+02:00"));
+        assert_eq!(Some(Duration::new(1465553600, 0)), parse_timestamp(b"2016-06-10T05:13:20-0500"));
+        assert_eq!(Some(Duration::new(951782400, 0)), parse_timestamp(b"2000-02-29T00:00:00Z"));
+        assert_eq!(None, parse_timestamp(b"2016-13-10T10:13:20Z"));
+        assert_eq!(None, parse_timestamp(b"2016-06-10T10:13:20 CEST"));
+    }
+
+    #[test]
+    fn test_given_event_time_when_events_arrive_then_the_watermark_follows_them_with_the_allowed_lateness() {
+        let mut event_time = EventTime::new("ts", Duration::from_secs(10));
+        let message = MessageBuilder::new("uuid", "message").pair(b"ts", b"not a timestamp").build();
+        assert_true!(event_time.accept(Duration::from_secs(100)));
+        assert_eq!(Some(Duration::from_secs(90)), event_time.watermark());
+        assert_true!(event_time.accept(Duration::from_secs(95)));
+        assert_true!(event_time.accept(Duration::from_secs(130)));
+        assert_false!(event_time.accept(Duration::from_secs(115)));
+        assert_eq!(None, event_time.timestamp(&message));
+        assert_eq!(1, event_time.late_events());
+        assert_eq!(Some(Duration::from_secs(120)), event_time.watermark());
+    }
+
+    #[test]
+    fn test_given_event_time_when_the_stream_is_idle_then_the_processing_time_advances_the_watermark() {
+        let mut event_time = EventTime::new("ts", Duration::from_secs(0)).idle_timeout(Some(Duration::from_secs(60)));
+        assert_false!(event_time.on_idle(Duration::from_secs(60)));
+        assert_true!(event_time.accept(Duration::from_secs(100)));
+        assert_false!(event_time.on_idle(Duration::from_secs(50)));
+        assert_true!(event_time.on_idle(Duration::from_secs(20)));
+        assert_eq!(Some(Duration::from_secs(110)), event_time.watermark());
+        assert_true!(event_time.on_idle(Duration::from_secs(10)));
+        assert_eq!(Some(Duration::from_secs(120)), event_time.watermark());
+        assert_true!(event_time.accept(Duration::from_secs(130)));
+        assert_false!(event_time.on_idle(Duration::from_secs(10)));
+    }
+}
@@ -18,6 +18,7 @@ use config::compile_templates;
 use ContextMap;
 use super::Correlator;
 use super::Error;
+use super::loopback;
 use Event;
 use TemplateFactory;
 
@@ -27,8 +28,12 @@ impl CorrelatorFactory {
     pub fn from_path<T, P, E, TF>(path: P, template_factory: &TF) -> Result<Correlator<E, TF::Template>, Error>
         where P: AsRef<Path>, E: Event, TF: TemplateFactory<E> {
         let contexts = try!(CorrelatorFactory::load_file(path));
+        if let Some(cycle) = loopback::find_cycle(&contexts) {
+            return Err(Error::LoopbackCycle(cycle));
+        }
         let contexts_after_template_compilation = try!(compile_templates(contexts, template_factory));
-        Ok(Correlator::new(ContextMap::from_configs(contexts_after_template_compilation)))
+        let context_map = try!(ContextMap::from_configs(contexts_after_template_compilation));
+        Ok(Correlator::new(context_map))
     }
 
     pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Vec<ContextConfig<String>>, Error> {
@@ -0,0 +1,189 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use uuid::Uuid;
+
+use config::ContextConfig;
+use config::action::ActionType;
+use config::action::message::InjectMode;
+
+/// Returns the ids (uuid and name) of the messages a context can send back
+/// to the correlator.
+fn loopback_ids<T>(context: &ContextConfig<T>) -> Vec<(&str, Option<&str>)> {
+    context.actions
+           .iter()
+           .filter_map(|action| {
+               match *action {
+                   ActionType::Message(ref action) if action.inject_mode == InjectMode::Loopback => {
+                       Some((&action.uuid[..], action.name.as_ref().map(|name| &name[..])))
+                   }
+                   ActionType::Exec(ref action) if action.inject_mode == InjectMode::Loopback => {
+                       Some((&action.uuid[..], action.name.as_ref().map(|name| &name[..])))
+                   }
+                   _ => None,
+               }
+           })
+           .collect()
+}
+
+/// Returns `true` if the context receives every event regardless of its subscriptions.
+fn is_catch_all<T>(context: &ContextConfig<T>) -> bool {
+    context.patterns.is_empty()
+}
+
+/// The predicates of the patterns are not evaluated, so a message can
+/// match a pattern here even if it never matches it at runtime.
+fn is_accepting<T>(context: &ContextConfig<T>, uuid: &str, name: Option<&str>) -> bool {
+    is_catch_all(context) ||
+    context.patterns
+           .iter()
+           .chain(context.conditions.opens.iter())
+           .chain(context.conditions.closes.iter())
+           .any(|pattern| {
+               pattern.is_matching_id(uuid.as_bytes()) || name.map_or(false, |name| pattern.is_matching_id(name.as_bytes()))
+           })
+}
+
+/// Returns the uuids of the contexts forming a cycle (the first one is repeated
+/// at the end) if the messages sent back by a context can get back to it.
+///
+/// A catch-all context receiving its own messages isn't a cycle: it's only
+/// reported by a warning and its depth is limited by `max_loopback_depth`.
+pub fn find_cycle<T>(contexts: &[ContextConfig<T>]) -> Option<Vec<Uuid>> {
+    let self_loops = contexts.iter()
+                             .map(|context| {
+                                 is_catch_all(context) &&
+                                 loopback_ids(context).iter().any(|&(uuid, name)| is_accepting(context, uuid, name))
+                             })
+                             .collect::<Vec<bool>>();
+    for (context, _) in contexts.iter().zip(&self_loops).filter(|&(_, &self_loop)| self_loop) {
+        warn!("A catch-all context receives its own loopback messages; uuid={}", context.uuid);
+    }
+    let edges = contexts.iter()
+                        .enumerate()
+                        .map(|(from, context)| {
+                            let ids = loopback_ids(context);
+                            (0..contexts.len())
+                                .filter(|&to| !(to == from && self_loops[from]))
+                                .filter(|&to| ids.iter().any(|&(uuid, name)| is_accepting(&contexts[to], uuid, name)))
+                                .collect::<Vec<usize>>()
+                        })
+                        .collect::<Vec<Vec<usize>>>();
+    let mut finished = vec![false; contexts.len()];
+    for start in 0..contexts.len() {
+        let mut path = Vec::new();
+        if let Some(cycle) = visit(start, &edges, &mut path, &mut finished) {
+            return Some(cycle.into_iter().map(|i| contexts[i].uuid).collect());
+        }
+    }
+    None
+}
+
+fn visit(node: usize, edges: &[Vec<usize>], path: &mut Vec<usize>, finished: &mut [bool]) -> Option<Vec<usize>> {
+    if let Some(pos) = path.iter().position(|&i| i == node) {
+        let mut cycle = path[pos..].to_vec();
+        cycle.push(node);
+        return Some(cycle);
+    }
+    if finished[node] {
+        return None;
+    }
+    path.push(node);
+    for &next in &edges[node] {
+        if let Some(cycle) = visit(next, edges, path, finished) {
+            return Some(cycle);
+        }
+    }
+    let _ = path.pop();
+    finished[node] = true;
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::find_cycle;
+    use config::ContextConfig;
+    use serde_json::from_str;
+
+    fn contexts(json: &str) -> Vec<ContextConfig<String>> {
+        from_str::<Vec<ContextConfig<String>>>(json).expect("Failed to deserialize the contexts")
+    }
+
+    #[test]
+    fn test_given_contexts_when_they_loop_back_to_each_other_then_the_cycle_is_found() {
+        let contexts = contexts(r#"[
+            {
+                "uuid": "185e96da-c00e-454b-b4fe-9d0a14a86335",
+                "patterns": ["LOGIN"],
+                "conditions": { "timeout": 100 },
+                "actions": [{ "message": { "uuid": "uuid1", "name": "LOGIN_BURST", "message": "burst", "inject_mode": "loopback" } }]
+            },
+            {
+                "uuid": "285e96da-c00e-454b-b4fe-9d0a14a86335",
+                "patterns": ["LOGIN_BURST"],
+                "conditions": { "timeout": 100 },
+                "actions": [{ "message": { "uuid": "uuid2", "name": "LOGIN", "message": "login", "inject_mode": "loopback" } }]
+            }
+        ]"#);
+        let cycle = find_cycle(&contexts).expect("Failed to find a loopback cycle");
+        let uuids = cycle.iter().map(|uuid| uuid.to_hyphenated_string()).collect::<Vec<String>>();
+        assert_eq!(vec!["185e96da-c00e-454b-b4fe-9d0a14a86335",
+                        "285e96da-c00e-454b-b4fe-9d0a14a86335",
+                        "185e96da-c00e-454b-b4fe-9d0a14a86335"],
+                   uuids);
+    }
+
+    #[test]
+    fn test_given_contexts_when_the_loopback_messages_go_only_forward_then_there_is_no_cycle() {
+        let contexts = contexts(r#"[
+            {
+                "uuid": "185e96da-c00e-454b-b4fe-9d0a14a86335",
+                "patterns": ["LOGIN"],
+                "conditions": { "timeout": 100 },
+                "actions": [{ "message": { "uuid": "uuid1", "name": "LOGIN_BURST", "message": "burst", "inject_mode": "loopback" } }]
+            },
+            {
+                "uuid": "285e96da-c00e-454b-b4fe-9d0a14a86335",
+                "patterns": ["LOGIN_BURST"],
+                "conditions": { "timeout": 100 },
+                "actions": [{ "message": { "uuid": "uuid2", "message": "attack" } }]
+            }
+        ]"#);
+        assert_eq!(None, find_cycle(&contexts));
+    }
+
+    #[test]
+    fn test_given_catch_all_context_when_it_loops_back_to_itself_then_it_is_not_a_cycle() {
+        let contexts = contexts(r#"[
+            {
+                "uuid": "185e96da-c00e-454b-b4fe-9d0a14a86335",
+                "conditions": { "timeout": 100, "opens": ["LOGIN"] },
+                "actions": [{ "message": { "uuid": "uuid1", "message": "summary", "inject_mode": "loopback" } }]
+            }
+        ]"#);
+        assert_eq!(None, find_cycle(&contexts));
+    }
+
+    #[test]
+    fn test_given_catch_all_context_when_an_other_context_consumes_its_loopback_messages_and_sends_them_back_then_the_cycle_is_found() {
+        let contexts = contexts(r#"[
+            {
+                "uuid": "185e96da-c00e-454b-b4fe-9d0a14a86335",
+                "conditions": { "timeout": 100 },
+                "actions": [{ "message": { "uuid": "uuid1", "name": "SUMMARY", "message": "summary", "inject_mode": "loopback" } }]
+            },
+            {
+                "uuid": "285e96da-c00e-454b-b4fe-9d0a14a86335",
+                "patterns": ["SUMMARY"],
+                "conditions": { "timeout": 100 },
+                "actions": [{ "message": { "uuid": "uuid2", "message": "attack", "inject_mode": "loopback" } }]
+            }
+        ]"#);
+        assert_true!(find_cycle(&contexts).is_some());
+    }
+}
@@ -6,7 +6,6 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use context::context_map::StreamingIterator;
 use reactor::{EventHandler, SharedData};
 use Event;
 use Template;
@@ -17,11 +16,8 @@ pub struct MessageEventHandler;
 impl<'a, E, T> EventHandler<E, SharedData<'a, E, T>> for MessageEventHandler where E: 'a + Event, T: Template<Event=E> {
     fn handle_event(&mut self, event: E, data: &mut SharedData<E, T>) {
         trace!("MessageEventHandler: handle_event()");
-        for i in event.ids() {
-            let mut iter = data.map.contexts_iter_mut(i);
-            while let Some(context) = iter.next() {
-                context.on_message(event.clone(), data.responder);
-            }
+        if !data.map.on_message(event, data.responder) {
+            data.suppressed = true;
         }
     }
 }
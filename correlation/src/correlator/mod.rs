@@ -6,10 +6,13 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::time::Duration;
-use std::collections::VecDeque;
+use std::cmp;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
 
 use Alert;
+use config::action::message::{InjectMode, LOOPBACK_CHAIN, LOOPBACK_DEPTH};
 use context::ContextMap;
 use self::timer::TimerEventHandler;
 use self::message::MessageEventHandler;
@@ -18,10 +21,15 @@ use Event;
 use Template;
 
 pub use self::error::Error;
+pub use self::event_time::{EventTime, IDLE_TIMEOUT_DEFAULT_MS, parse_utc_offset};
 pub use self::factory::CorrelatorFactory;
 
+pub const MAX_LOOPBACK_DEPTH_DEFAULT: usize = 16;
+
 mod error;
+mod event_time;
 mod factory;
+mod loopback;
 mod message;
 mod timer;
 #[cfg(test)]
@@ -32,6 +40,23 @@ pub struct Correlator<E, T> where E: 'static + Event, T: 'static + Template<Even
     pub responses: VecDeque<Alert<E>>,
     message_event_handler: MessageEventHandler,
     timer_event_handler: TimerEventHandler,
+    event_time: Option<EventTime>,
+    /// The time of the contexts: the processing time elapsed by the timer
+    /// events, or the event time in event time mode. It never goes backwards.
+    time: Duration,
+    /// The event time the timers of the contexts were advanced to.
+    event_clock: Option<Duration>,
+    /// The events which are ahead of the watermark by their timestamps and sequence numbers.
+    pending: BTreeMap<(Duration, u64), E>,
+    next_pending: u64,
+    /// The number of `tag` alerts dropped because their event was already forwarded.
+    dropped_tags: usize,
+    /// The wall clock at the last timer event.
+    clock: Duration,
+    /// `true` if the wall clock was set by `set_clock()`, then it's advanced by the timer events.
+    manual_clock: bool,
+    max_loopback_depth: usize,
+    loopback_overflows: usize,
 }
 
 impl<E, T> Correlator<E, T> where E: Event, T: 'static + Template<Event=E> {
@@ -41,16 +66,217 @@ impl<E, T> Correlator<E, T> where E: Event, T: 'static + Template<Event=E> {
             responses: VecDeque::new(),
             message_event_handler: MessageEventHandler::default(),
             timer_event_handler: TimerEventHandler::default(),
+            event_time: None,
+            time: Duration::from_secs(0),
+            event_clock: None,
+            pending: BTreeMap::new(),
+            next_pending: 0,
+            dropped_tags: 0,
+            clock: system_time(),
+            manual_clock: false,
+            max_loopback_depth: MAX_LOOPBACK_DEPTH_DEFAULT,
+            loopback_overflows: 0,
+        }
+    }
+
+    /// Returns `false` if the message was suppressed by a context.
+    pub fn push_message(&mut self, message: E) -> bool {
+        if self.is_loopback_overflow(&message) {
+            return true;
+        }
+        let timestamp = match self.event_time {
+            Some(ref mut event_time) => {
+                match event_time.timestamp(&message) {
+                    Some(timestamp) if !event_time.accept(timestamp) => return true,
+                    timestamp => timestamp,
+                }
+            }
+            None => None,
+        };
+        match timestamp {
+            Some(timestamp) => {
+                let id = self.next_pending;
+                self.next_pending += 1;
+                let _ = self.pending.insert((timestamp, id), message);
+                self.release_pending(Some(id))
+            }
+            None => self.handle_message(message),
         }
     }
 
-    pub fn push_message(&mut self, message: E) {
+    fn handle_message(&mut self, message: E) -> bool {
         let mut shared_data = SharedData::new(&mut self.context_map, &mut self.responses);
         self.message_event_handler.handle_event(message, &mut shared_data);
+        !shared_data.suppressed
     }
 
-    pub fn elapse_time(&mut self, span: Duration) {
+    /// Correlates the pending events which are not ahead of the watermark in
+    /// the order of their timestamps, then advances the timers to the watermark.
+    /// Returns `false` if the event `own` was correlated and suppressed.
+    fn release_pending(&mut self, own: Option<u64>) -> bool {
+        let watermark = match self.event_time.as_ref().and_then(EventTime::watermark) {
+            Some(watermark) => watermark,
+            None => return true,
+        };
+        let mut forwarded = true;
+        loop {
+            let key = match self.pending.keys().next() {
+                Some(&key) if key.0 <= watermark => key,
+                _ => break,
+            };
+            let message = self.pending.remove(&key).expect("Failed to remove a pending event");
+            self.advance_event_clock(key.0);
+            if Some(key.1) == own {
+                forwarded = self.handle_message(message);
+            } else {
+                // the event left the pipeline when it was buffered, it can't be suppressed or tagged anymore
+                let len = self.responses.len();
+                if !self.handle_message(message) {
+                    warn!("Correlator: a buffered message was suppressed after it had been forwarded");
+                }
+                let alerts = self.responses.split_off(len);
+                for alert in alerts {
+                    if alert.inject_mode == InjectMode::Tag {
+                        warn!("Correlator: dropping a tag alert of a buffered message which had been forwarded");
+                        self.dropped_tags += 1;
+                    } else {
+                        self.responses.push_back(alert);
+                    }
+                }
+            }
+        }
+        self.advance_event_clock(watermark);
+        forwarded
+    }
+
+    /// Advances the timers of the contexts to the event time `to`.
+    fn advance_event_clock(&mut self, to: Duration) {
+        match self.event_clock {
+            Some(from) if to > from => {
+                self.advance_time(to);
+                cross_boundaries(&mut self.context_map, &from, &to, &mut self.responses);
+                self.event_clock = Some(to);
+            }
+            Some(_) => (),
+            None => {
+                self.advance_time(to);
+                self.event_clock = Some(to);
+            }
+        }
+    }
+
+    /// Moves the clock of the contexts to `to` and closes the timed out ones.
+    fn advance_time(&mut self, to: Duration) {
+        self.time = cmp::max(self.time, to);
         let mut shared_data = SharedData::new(&mut self.context_map, &mut self.responses);
-        self.timer_event_handler.handle_event(&span, &mut shared_data);
+        self.timer_event_handler.handle_event(&self.time, &mut shared_data);
+    }
+
+    /// Messages sent back by `loopback` alerts more than `max_loopback_depth`
+    /// times in a row are dropped.
+    pub fn set_max_loopback_depth(&mut self, max_loopback_depth: usize) {
+        self.max_loopback_depth = max_loopback_depth;
+    }
+
+    /// Returns the number of messages dropped because of `max_loopback_depth`.
+    pub fn loopback_overflows(&self) -> usize {
+        self.loopback_overflows
+    }
+
+    fn is_loopback_overflow(&mut self, message: &E) -> bool {
+        let depth = loopback_depth(message);
+        if depth > self.max_loopback_depth {
+            error!("Dropping a loopback message over the maximal depth; depth={} contexts={}",
+                   depth,
+                   String::from_utf8_lossy(message.get(LOOPBACK_CHAIN.as_bytes()).unwrap_or(b"")));
+            self.loopback_overflows += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Limits the number of states of every keyed context together.
+    pub fn set_max_instances(&mut self, max_instances: Option<usize>) {
+        self.context_map.set_max_instances(max_instances);
+    }
+
+    /// Drives the timers by the timestamps of the messages instead of `elapse_time()`.
+    /// The pending events of the previous mode are correlated immediately.
+    pub fn set_event_time(&mut self, event_time: Option<EventTime>) {
+        self.event_time = event_time;
+        self.event_clock = None;
+        let pending = mem::replace(&mut self.pending, BTreeMap::new());
+        for (_, message) in pending {
+            let _ = self.handle_message(message);
+        }
+    }
+
+    /// Returns the number of `tag` alerts dropped in event time mode, because
+    /// their message was held back and forwarded before it was correlated.
+    pub fn dropped_tags(&self) -> usize {
+        self.dropped_tags
+    }
+
+    /// Returns the number of messages dropped because they arrived after the watermark.
+    pub fn late_events(&self) -> usize {
+        self.event_time.as_ref().map_or(0, |event_time| event_time.late_events())
+    }
+
+    /// Sets the wall clock (the time elapsed since the UNIX epoch). By default
+    /// the system time is read on every `elapse_time()`, once the clock is set
+    /// it's advanced by the spans instead (e.g. to replay a recorded timeline).
+    pub fn set_clock(&mut self, now: Duration) {
+        self.clock = now;
+        self.manual_clock = true;
+    }
+
+    /// In event time mode the timestamps of the messages advance the time,
+    /// `span` is used only if the stream is idle (see `EventTime::idle_timeout()`).
+    pub fn elapse_time(&mut self, span: Duration) {
+        let idle = match self.event_time {
+            Some(ref mut event_time) => Some(event_time.on_idle(span)),
+            None => None,
+        };
+        match idle {
+            Some(true) => {
+                let _ = self.release_pending(None);
+            }
+            Some(false) => (),
+            None => {
+                let time = self.time + span;
+                self.advance_time(time);
+                let from = self.clock;
+                // the spans of the timer events are late and they drift, so they only
+                // advance the clock if it's set manually. It never goes backwards.
+                let to = if self.manual_clock { from + span } else { system_time() };
+                self.clock = cmp::max(from, to);
+                cross_boundaries(&mut self.context_map, &from, &self.clock, &mut self.responses);
+            }
+        }
+    }
+}
+
+fn system_time() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0))
+}
+
+/// Returns how many times `message` was sent back to the correlator in a row.
+pub fn loopback_depth<E: Event>(message: &E) -> usize {
+    message.get(LOOPBACK_DEPTH.as_bytes())
+           .and_then(|depth| ::std::str::from_utf8(depth).ok())
+           .and_then(|depth| depth.parse().ok())
+           .unwrap_or(0)
+}
+
+/// Closes the calendar windows if the clock moved over their boundaries.
+fn cross_boundaries<E, T>(context_map: &mut ContextMap<E, T>,
+                          from: &Duration,
+                          to: &Duration,
+                          responder: &mut VecDeque<Alert<E>>)
+    where E: Event, T: Template<Event=E>
+{
+    for i in context_map.contexts_mut() {
+        i.on_clock(from, to, responder);
     }
 }
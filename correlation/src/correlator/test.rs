@@ -7,15 +7,19 @@
 // modified, or distributed except according to those terms.
 
 use config::{ContextConfigBuilder, ContextConfig};
-use config::action::message::MessageActionBuilder;
-use conditions::ConditionsBuilder;
-use correlator::Correlator;
+use config::action::message::{InjectMode, MessageActionBuilder};
+use config::action::tag::TagActionBuilder;
+use conditions::{CloseReason, ConditionsBuilder};
+use correlator::{Correlator, EventTime, loopback_depth};
 use context::ContextMap;
 use message::MessageBuilder;
+use Calendar;
 use Message;
+use Event;
 
 use uuid::Uuid;
 use serde_json::from_str;
+use std::thread;
 use std::time::Duration;
 use config::compile_templates;
 
@@ -112,7 +116,7 @@ fn test_given_manually_built_correlator_when_it_closes_a_context_then_the_action
     ];
     let template_factory = MockTemplateFactory::compile_value();
     let contexts = compile_templates(contexts, &template_factory).unwrap();
-    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts));
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
     correlator.push_message(MessageBuilder::new(uuid1.as_bytes(), b"message").build());
     correlator.elapse_time(Duration::from_millis(20));
     correlator.push_message(MessageBuilder::new(uuid2.as_bytes(), b"message").build());
@@ -145,7 +149,7 @@ fn test_given_correlator_when_it_is_built_from_json_then_it_produces_the_expecte
     let contexts = result.expect("Failed to deserialize a config::ContextConfig from JSON");
     let template_factory = MockTemplateFactory::compile_value();
     let contexts = compile_templates(contexts, &template_factory).unwrap();
-    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts));
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
     correlator.push_message(MessageBuilder::new(uuid1, "message")
                                         .name(Some("p1"))
                                         .build());
@@ -172,3 +176,296 @@ fn test_given_correlator_when_it_is_built_from_json_then_it_produces_the_expecte
     println!("{:?}", &correlator.responses);
     assert_eq!(5, correlator.responses.len());
 }
+
+#[test]
+fn test_given_suppressing_context_when_a_message_is_repeated_then_it_is_suppressed_and_counted() {
+    let condition = ConditionsBuilder::new(Duration::from_millis(100))
+                        .suppress(true)
+                        .build();
+    let action = MessageActionBuilder::new("uuid", MockTemplate::event_count()).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["DISK_FULL"])
+            .context_id(Some(vec!["HOST".to_owned()]))
+            .actions(vec![action.into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    let message = |host: &str, seq: &str| {
+        MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+            .name(Some("DISK_FULL"))
+            .pair(b"HOST", host.as_bytes())
+            .pair(b"SEQ", seq.as_bytes())
+            .build()
+    };
+    assert_eq!(true, correlator.push_message(message("host1", "1")));
+    assert_eq!(false, correlator.push_message(message("host1", "2")));
+    assert_eq!(true, correlator.push_message(message("host2", "3")));
+    assert_eq!(false, correlator.push_message(message("host1", "4")));
+    assert_eq!(false, correlator.push_message(message("host1", "5")));
+    correlator.elapse_time(Duration::from_millis(100));
+    assert_eq!(2, correlator.responses.len());
+    let counts = correlator.responses.iter().map(|alert| alert.message.message().to_vec()).collect::<Vec<Vec<u8>>>();
+    assert_eq!(vec![b"4".to_vec(), b"1".to_vec()], counts);
+    assert_eq!(true, correlator.push_message(message("host1", "6")));
+}
+
+#[test]
+fn test_given_correlator_with_global_instance_limit_when_a_context_without_states_opens_a_key_then_the_victim_is_chosen_from_every_context() {
+    let action = MessageActionBuilder::new("uuid", MockTemplate::literal(b"closed")).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), ConditionsBuilder::new(Duration::from_secs(60)).build())
+            .patterns(vec!["LOGIN"])
+            .context_id(Some(vec!["USER".to_owned()]))
+            .actions(vec![action.into()])
+            .build(),
+        ContextConfigBuilder::new(Uuid::new_v4(), ConditionsBuilder::new(Duration::from_secs(60)).build())
+            .patterns(vec!["DISK_FULL"])
+            .context_id(Some(vec!["HOST".to_owned()]))
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    correlator.set_max_instances(Some(2));
+    let message = |name: &str, key: &str, value: &str| {
+        MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+            .name(Some(name))
+            .pair(key.as_bytes(), value.as_bytes())
+            .build()
+    };
+    correlator.push_message(message("LOGIN", "USER", "alice"));
+    correlator.elapse_time(Duration::from_secs(1));
+    correlator.push_message(message("LOGIN", "USER", "bob"));
+    correlator.elapse_time(Duration::from_secs(1));
+    correlator.push_message(message("DISK_FULL", "HOST", "host1"));
+    assert_eq!(2, correlator.context_map.instances());
+    assert_eq!(1, correlator.context_map.evictions());
+    assert_eq!(0, correlator.context_map.rejections());
+    assert_eq!(1, correlator.responses.len());
+    assert_eq!(Some(CloseReason::Evicted), correlator.responses[0].close_reason);
+    // alice was used least recently, bob is still open
+    correlator.push_message(message("LOGIN", "USER", "bob"));
+    assert_eq!(1, correlator.context_map.evictions());
+}
+
+#[test]
+fn test_given_correlator_in_event_time_mode_when_messages_arrive_then_their_timestamps_drive_the_timers() {
+    let condition = ConditionsBuilder::new(Duration::from_secs(10)).build();
+    let action = MessageActionBuilder::new("uuid", MockTemplate::event_count()).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["LOGIN"])
+            .actions(vec![action.into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    correlator.set_event_time(Some(EventTime::new("TIMESTAMP", Duration::from_secs(2)).idle_timeout(None)));
+    let message = |timestamp: &str| {
+        MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+            .name(Some("LOGIN"))
+            .pair(b"TIMESTAMP", timestamp.as_bytes())
+            .build()
+    };
+    correlator.push_message(message("2016-06-10T10:13:20Z"));
+    correlator.elapse_time(Duration::from_secs(3600));
+    assert_eq!(0, correlator.responses.len());
+    correlator.push_message(message("2016-06-10T10:13:25Z"));
+    correlator.push_message(message("2016-06-10T10:13:21Z"));
+    assert_eq!(1, correlator.late_events());
+    assert_eq!(0, correlator.responses.len());
+    correlator.push_message(message("2016-06-10T10:13:35Z"));
+    assert_eq!(1, correlator.responses.len());
+    assert_eq!(b"2", correlator.responses[0].message.message());
+}
+
+#[test]
+fn test_given_correlator_in_event_time_mode_when_the_events_arrive_out_of_order_then_they_are_correlated_in_the_order_of_their_timestamps() {
+    let condition = ConditionsBuilder::new(Duration::from_secs(10)).renew_timeout(Duration::from_secs(3)).build();
+    let action = MessageActionBuilder::new("uuid", MockTemplate::event_count()).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["LOGIN"])
+            .actions(vec![action.into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    correlator.set_event_time(Some(EventTime::new("TIMESTAMP", Duration::from_secs(5))));
+    let message = |timestamp: &str| {
+        MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+            .name(Some("LOGIN"))
+            .pair(b"TIMESTAMP", timestamp.as_bytes())
+            .build()
+    };
+    // the gaps between the timestamps are shorter than the renew timeout,
+    // but the gaps between the watermarks at their arrival are not
+    correlator.push_message(message("100"));
+    correlator.push_message(message("104"));
+    correlator.push_message(message("102"));
+    correlator.push_message(message("106"));
+    assert_eq!(0, correlator.responses.len());
+    correlator.push_message(message("115"));
+    assert_eq!(1, correlator.responses.len());
+    assert_eq!(b"4", correlator.responses[0].message.message());
+}
+
+#[test]
+fn test_given_correlator_in_event_time_mode_when_a_sliding_window_slides_then_the_messages_expire_by_their_timestamps() {
+    let condition = ConditionsBuilder::new(Duration::from_secs(10)).sliding(true).build();
+    let action = MessageActionBuilder::new("uuid", MockTemplate::context_len()).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["LOGIN"])
+            .actions(vec![action.into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    // the processing time elapsed before the event time mode doesn't count
+    correlator.elapse_time(Duration::from_secs(3600));
+    correlator.set_event_time(Some(EventTime::new("TIMESTAMP", Duration::from_secs(0)).idle_timeout(None)));
+    let message = |timestamp: &str| {
+        MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+            .name(Some("LOGIN"))
+            .pair(b"TIMESTAMP", timestamp.as_bytes())
+            .build()
+    };
+    correlator.push_message(message("100000"));
+    correlator.push_message(message("100005"));
+    correlator.push_message(message("100009"));
+    correlator.push_message(message("100016"));
+    assert_eq!(0, correlator.responses.len());
+    correlator.push_message(message("100030"));
+    assert_eq!(1, correlator.responses.len());
+    assert_eq!(b"2", correlator.responses[0].message.message());
+}
+
+#[test]
+fn test_given_correlator_in_event_time_mode_when_a_suppressible_message_is_held_back_then_it_is_forwarded_and_its_tags_are_counted() {
+    let condition = ConditionsBuilder::new(Duration::from_secs(60)).suppress(true).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["DISK_FULL"])
+            .actions(vec![TagActionBuilder::new("tag").build().into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    correlator.set_event_time(Some(EventTime::new("TIMESTAMP", Duration::from_secs(5)).idle_timeout(None)));
+    let message = |timestamp: &str| {
+        MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+            .name(Some("DISK_FULL"))
+            .pair(b"TIMESTAMP", timestamp.as_bytes())
+            .build()
+    };
+    assert_eq!(true, correlator.push_message(message("100")));
+    assert_eq!(true, correlator.push_message(message("110")));
+    assert_eq!(1, correlator.dropped_tags());
+    // the second message is suppressed only when it's released, after it was forwarded
+    assert_eq!(true, correlator.push_message(message("120")));
+    assert_eq!(2, correlator.dropped_tags());
+    assert_false!(correlator.responses.iter().any(|alert| alert.inject_mode == InjectMode::Tag));
+}
+
+#[test]
+fn test_given_correlator_in_event_time_mode_when_the_stream_is_idle_then_the_contexts_time_out() {
+    let condition = ConditionsBuilder::new(Duration::from_secs(10)).build();
+    let action = MessageActionBuilder::new("uuid", MockTemplate::event_count()).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["LOGIN"])
+            .actions(vec![action.into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    let event_time = EventTime::new("TIMESTAMP", Duration::from_secs(0)).idle_timeout(Some(Duration::from_secs(30)));
+    correlator.set_event_time(Some(event_time));
+    correlator.push_message(MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+                                .name(Some("LOGIN"))
+                                .pair(b"TIMESTAMP", b"100")
+                                .build());
+    correlator.elapse_time(Duration::from_secs(30));
+    assert_eq!(0, correlator.responses.len());
+    correlator.elapse_time(Duration::from_secs(10));
+    assert_eq!(1, correlator.responses.len());
+}
+
+#[test]
+fn test_given_calendar_context_when_the_clock_crosses_a_boundary_then_the_context_is_closed() {
+    let condition = ConditionsBuilder::new(Duration::from_secs(10))
+                        .calendar(Calendar::new(Duration::from_secs(3600)).utc_offset(1800))
+                        .build();
+    let action = MessageActionBuilder::new("uuid", MockTemplate::event_count()).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["SUDO"])
+            .context_id(Some(vec!["USER".to_owned()]))
+            .actions(vec![action.into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    // 10:15 UTC, the boundaries are at half past every hour
+    correlator.set_clock(Duration::from_secs(1465553700));
+    let message = |user: &str| {
+        MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message")
+            .name(Some("SUDO"))
+            .pair(b"USER", user.as_bytes())
+            .build()
+    };
+    correlator.push_message(message("alice"));
+    correlator.push_message(message("bob"));
+    // the timeout is ignored
+    correlator.elapse_time(Duration::from_secs(60));
+    correlator.push_message(message("alice"));
+    correlator.elapse_time(Duration::from_secs(839));
+    assert_eq!(0, correlator.responses.len());
+    correlator.elapse_time(Duration::from_secs(1));
+    assert_eq!(2, correlator.responses.len());
+    assert!(correlator.responses.iter().all(|alert| alert.close_reason == Some(CloseReason::Boundary)));
+    let mut counts = correlator.responses.iter().map(|alert| alert.message.message().to_vec()).collect::<Vec<Vec<u8>>>();
+    counts.sort();
+    assert_eq!(vec![b"1".to_vec(), b"2".to_vec()], counts);
+}
+
+#[test]
+fn test_given_calendar_context_when_the_timer_events_are_late_then_the_system_time_finds_the_boundaries() {
+    let condition = ConditionsBuilder::new(Duration::from_secs(10))
+                        .calendar(Calendar::new(Duration::from_millis(1)))
+                        .build();
+    let action = MessageActionBuilder::new("uuid", MockTemplate::event_count()).build();
+    let contexts = vec![
+        ContextConfigBuilder::new(Uuid::new_v4(), condition)
+            .patterns(vec!["SUDO"])
+            .actions(vec![action.into()])
+            .build(),
+    ];
+    let mut correlator: Correlator<Message, MockTemplate> = Correlator::new(ContextMap::from_configs(contexts).expect("Failed to build a ContextMap"));
+    correlator.push_message(MessageBuilder::new("1b47ba91-d867-4a8c-9553-a5dfd6ea1274", "message").name(Some("SUDO")).build());
+    thread::sleep(Duration::from_millis(5));
+    correlator.elapse_time(Duration::from_secs(0));
+    assert_eq!(1, correlator.responses.len());
+    assert_eq!(Some(CloseReason::Boundary), correlator.responses[0].close_reason);
+}
+
+#[test]
+fn test_given_context_which_loops_back_to_itself_when_the_maximal_depth_is_reached_then_the_message_is_dropped() {
+    let action = MessageActionBuilder::<MockTemplate>::new("LOGIN", MockTemplate::literal(b"login"))
+                     .inject_mode(InjectMode::Loopback)
+                     .build();
+    let context = ContextConfigBuilder::new(Uuid::new_v4(), ConditionsBuilder::new(Duration::from_millis(100)).max_size(1).build())
+                      .patterns(vec!["LOGIN"])
+                      .actions(vec![action.into()])
+                      .build();
+    let mut correlator = Correlator::new(ContextMap::from_configs(vec![context]).expect("Failed to build a ContextMap"));
+    correlator.set_max_loopback_depth(3);
+
+    let mut message = MessageBuilder::new("LOGIN", "login").build();
+    let mut depths = Vec::new();
+    while correlator.push_message(message) {
+        let alert = match correlator.responses.pop_front() {
+            Some(alert) => alert,
+            None => break,
+        };
+        depths.push(loopback_depth(&alert.message));
+        message = alert.message;
+    }
+    assert_eq!(vec![1, 2, 3, 4], depths);
+    assert_eq!(1, correlator.loopback_overflows());
+    assert_eq!(0, correlator.responses.len());
+}
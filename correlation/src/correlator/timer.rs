@@ -16,9 +16,9 @@ use std::time::Duration;
 pub struct TimerEventHandler;
 
 impl<'a, 'd, E, T> EventHandler<&'d Duration, SharedData<'a, E, T>> for TimerEventHandler where E: 'a + Event, T: Template<Event=E>{
-    fn handle_event(&mut self, event: &Duration, data: &mut SharedData<E, T>) {
+    fn handle_event(&mut self, now: &Duration, data: &mut SharedData<E, T>) {
         for i in data.map.contexts_mut() {
-            i.on_timer(event, data.responder);
+            i.on_timer(now, data.responder);
         }
     }
 }
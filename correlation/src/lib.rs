@@ -18,6 +18,7 @@ extern crate serde;
 extern crate serde_json;
 extern crate serde_yaml;
 extern crate rustc_serialize;
+extern crate regex;
 #[macro_use]
 extern crate log;
 
@@ -25,22 +26,31 @@ extern crate log;
 mod macros;
 
 pub use action::Alert;
-pub use conditions::{Conditions, ConditionsBuilder};
+pub use aggregate::{Aggregate, AggregateFunction};
+pub use calendar::Calendar;
+pub use conditions::{Conditions, ConditionsBuilder, CloseReason, OutOfOrder, Overflow, Reopen};
 pub use config::action::ActionType;
 pub use message::{Message, MessageBuilder};
+pub use pattern::{Pattern, PatternKind, Predicate, Operator};
 pub use context::ContextMap;
+pub use context::map::{Eviction, MissingKey};
 pub use reactor::{EventHandler, SharedData};
+pub use template::{MessageTemplate, MessageTemplateFactory};
 
 pub mod config;
 pub mod correlator;
 pub mod test_utils;
 mod conditions;
 mod action;
+mod aggregate;
+mod calendar;
 mod message;
 mod context;
+mod pattern;
 mod reactor;
 mod state;
 mod duration;
+mod template;
 
 pub trait Event: Clone {
     fn get(&self, key: &[u8]) -> Option<&[u8]>;
@@ -118,8 +128,40 @@ impl ::std::error::Error for CompileError {
 }
 
 use std::io::Write;
+use std::collections::BTreeMap;
+
+/// Everything a `Template` can refer to when an action is executed.
+pub struct TemplateContext<'a, E: 'a + Event> {
+    pub messages: &'a [E],
+    pub context_id: &'a str,
+    pub context_name: Option<&'a str>,
+    pub close_reason: Option<CloseReason>,
+    /// The number of events received by the context, `messages` may contain less.
+    pub event_count: usize,
+    /// The rendered values of the aggregates of the context by their names.
+    pub aggregates: BTreeMap<&'a str, String>,
+    /// The event which triggered the action, `None` if it wasn't triggered by an event.
+    pub event: Option<&'a E>,
+}
+
+impl<'a, E: Event> TemplateContext<'a, E> {
+    pub fn new(messages: &'a [E], context_id: &'a str) -> TemplateContext<'a, E> {
+        TemplateContext {
+            messages: messages,
+            context_id: context_id,
+            context_name: None,
+            close_reason: None,
+            event_count: messages.len(),
+            aggregates: BTreeMap::new(),
+            event: None,
+        }
+    }
+}
 
 pub trait Template: Send {
     type Event: Event;
     fn format_with_context(&self, messages: &[Self::Event], context_id: &str, buffer: &mut Write);
+    fn format(&self, context: &TemplateContext<Self::Event>, buffer: &mut Write) {
+        self.format_with_context(context.messages, context.context_id, buffer)
+    }
 }
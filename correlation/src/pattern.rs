@@ -0,0 +1,480 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::str::{self, FromStr};
+
+use regex::{self, bytes};
+
+use Event;
+
+/// Defines how the `name` of a `Pattern` is compared to the ids of an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatternKind {
+    Exact,
+    Regex,
+    Glob,
+}
+
+impl Default for PatternKind {
+    fn default() -> PatternKind {
+        PatternKind::Exact
+    }
+}
+
+/// An element of a context's `patterns` list. An event matches it if one
+/// of its ids matches `name` and every predicate holds for its fields.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pub name: String,
+    pub predicates: Vec<Predicate>,
+    kind: PatternKind,
+    regex: Option<bytes::Regex>,
+}
+
+impl Pattern {
+    pub fn new<S: Into<String>>(name: S) -> Pattern {
+        Pattern {
+            name: name.into(),
+            predicates: Vec::new(),
+            kind: PatternKind::Exact,
+            regex: None,
+        }
+    }
+
+    pub fn with_kind<S: Into<String>>(name: S, kind: PatternKind) -> Result<Pattern, regex::Error> {
+        let name = name.into();
+        let regex = match kind {
+            PatternKind::Exact => None,
+            PatternKind::Regex => Some(try!(bytes::Regex::new(&name))),
+            PatternKind::Glob => Some(try!(bytes::Regex::new(&Pattern::glob_to_regex(&name)))),
+        };
+        Ok(Pattern {
+            name: name,
+            predicates: Vec::new(),
+            kind: kind,
+            regex: regex,
+        })
+    }
+
+    fn glob_to_regex(glob: &str) -> String {
+        let mut regex = String::from("^");
+        for c in glob.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                _ => regex.push_str(&regex::quote(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        regex
+    }
+
+    pub fn predicate<S: Into<String>>(mut self, key: S, operator: Operator) -> Pattern {
+        self.predicates.push(Predicate {
+            key: key.into(),
+            operator: operator,
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> PatternKind {
+        self.kind
+    }
+
+    /// Returns the compiled expression of a `Regex` or `Glob` pattern.
+    pub fn regex(&self) -> Option<&str> {
+        self.regex.as_ref().map(|regex| regex.as_str())
+    }
+
+    pub fn is_matching_id(&self, id: &[u8]) -> bool {
+        match self.regex {
+            Some(ref regex) => regex.is_match(id),
+            None => id == self.name.as_bytes(),
+        }
+    }
+
+    pub fn is_matching<E: Event>(&self, event: &E) -> bool {
+        event.ids().into_iter().any(|id| self.is_matching_id(id)) &&
+        self.predicates.iter().all(|predicate| predicate.is_matching(event))
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        self.name == other.name && self.kind == other.kind && self.predicates == other.predicates
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(name: String) -> Pattern {
+        Pattern::new(name)
+    }
+}
+
+impl<'a> From<&'a str> for Pattern {
+    fn from(name: &'a str) -> Pattern {
+        Pattern::new(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Predicate {
+    pub key: String,
+    pub operator: Operator,
+}
+
+impl Predicate {
+    pub fn is_matching<E: Event>(&self, event: &E) -> bool {
+        let value = event.get(self.key.as_bytes());
+        match self.operator {
+            Operator::Equals(ref expected) => value == Some(expected.as_bytes()),
+            Operator::NotEquals(ref expected) => value != Some(expected.as_bytes()),
+            Operator::Exists(exists) => value.is_some() == exists,
+            Operator::LessThan(limit) => Predicate::to_number(value).map_or(false, |value| value < limit),
+            Operator::LessOrEquals(limit) => Predicate::to_number(value).map_or(false, |value| value <= limit),
+            Operator::GreaterThan(limit) => Predicate::to_number(value).map_or(false, |value| value > limit),
+            Operator::GreaterOrEquals(limit) => Predicate::to_number(value).map_or(false, |value| value >= limit),
+        }
+    }
+
+    fn to_number(value: Option<&[u8]>) -> Option<f64> {
+        value.and_then(|value| str::from_utf8(value).ok())
+             .and_then(|value| f64::from_str(value.trim()).ok())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operator {
+    Equals(String),
+    NotEquals(String),
+    Exists(bool),
+    LessThan(f64),
+    LessOrEquals(f64),
+    GreaterThan(f64),
+    GreaterOrEquals(f64),
+}
+
+mod deser {
+    use super::{Operator, Pattern, PatternKind, Predicate};
+    use serde::de::{Deserialize, Deserializer, Error, MapVisitor, Visitor};
+    use std::collections::BTreeMap;
+
+    impl Deserialize for Pattern {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Pattern, D::Error>
+            where D: Deserializer
+        {
+            deserializer.deserialize(PatternVisitor)
+        }
+    }
+
+    impl Deserialize for PatternKind {
+        fn deserialize<D>(deserializer: &mut D) -> Result<PatternKind, D::Error>
+            where D: Deserializer
+        {
+            deserializer.deserialize_str(PatternKindVisitor)
+        }
+    }
+
+    struct PatternKindVisitor;
+
+    impl Visitor for PatternKindVisitor {
+        type Value = PatternKind;
+
+        fn visit_str<E>(&mut self, value: &str) -> Result<PatternKind, E>
+            where E: Error
+        {
+            match value {
+                "exact" => Ok(PatternKind::Exact),
+                "regex" => Ok(PatternKind::Regex),
+                "glob" => Ok(PatternKind::Glob),
+                _ => Err(E::custom(format!("Unexpected value for match: {}", value))),
+            }
+        }
+    }
+
+    enum Field {
+        Name,
+        Match,
+        Fields,
+    }
+
+    impl Deserialize for Field {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Field, D::Error>
+            where D: Deserializer
+        {
+            struct FieldVisitor;
+
+            impl Visitor for FieldVisitor {
+                type Value = Field;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                    where E: Error
+                {
+                    match value {
+                        "name" | "uuid" => Ok(Field::Name),
+                        "match" => Ok(Field::Match),
+                        "fields" => Ok(Field::Fields),
+                        _ => Err(E::custom(format!("Unexpected field: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(FieldVisitor)
+        }
+    }
+
+    struct PatternVisitor;
+
+    impl Visitor for PatternVisitor {
+        type Value = Pattern;
+
+        fn visit_str<E>(&mut self, value: &str) -> Result<Pattern, E>
+            where E: Error
+        {
+            Ok(Pattern::new(value))
+        }
+
+        fn visit_map<V>(&mut self, mut visitor: V) -> Result<Pattern, V::Error>
+            where V: MapVisitor
+        {
+            let mut name: Option<String> = None;
+            let mut kind: Option<PatternKind> = None;
+            let mut fields: Option<BTreeMap<String, Operators>> = None;
+
+            while let Some(field) = try!(visitor.visit_key()) {
+                match field {
+                    Field::Name => name = Some(try!(visitor.visit_value())),
+                    Field::Match => kind = Some(try!(visitor.visit_value())),
+                    Field::Fields => fields = Some(try!(visitor.visit_value())),
+                }
+            }
+
+            let name = match name {
+                Some(name) => name,
+                None => return visitor.missing_field("name"),
+            };
+
+            try!(visitor.end());
+
+            let mut pattern = match Pattern::with_kind(name, kind.unwrap_or_default()) {
+                Ok(pattern) => pattern,
+                Err(error) => return Err(V::Error::custom(format!("Invalid pattern: {}", error))),
+            };
+
+            for (key, operators) in fields.unwrap_or_default() {
+                for operator in operators.0 {
+                    pattern.predicates.push(Predicate {
+                        key: key.clone(),
+                        operator: operator,
+                    });
+                }
+            }
+
+            Ok(pattern)
+        }
+    }
+
+    struct Operators(Vec<Operator>);
+
+    impl Deserialize for Operators {
+        fn deserialize<D>(deserializer: &mut D) -> Result<Operators, D::Error>
+            where D: Deserializer
+        {
+            deserializer.deserialize_map(OperatorsVisitor)
+        }
+    }
+
+    enum OperatorField {
+        Equals,
+        NotEquals,
+        Exists,
+        LessThan,
+        LessOrEquals,
+        GreaterThan,
+        GreaterOrEquals,
+    }
+
+    impl Deserialize for OperatorField {
+        fn deserialize<D>(deserializer: &mut D) -> Result<OperatorField, D::Error>
+            where D: Deserializer
+        {
+            struct FieldVisitor;
+
+            impl Visitor for FieldVisitor {
+                type Value = OperatorField;
+
+                fn visit_str<E>(&mut self, value: &str) -> Result<OperatorField, E>
+                    where E: Error
+                {
+                    match value {
+                        "equals" => Ok(OperatorField::Equals),
+                        "not_equals" => Ok(OperatorField::NotEquals),
+                        "exists" => Ok(OperatorField::Exists),
+                        "less_than" => Ok(OperatorField::LessThan),
+                        "less_or_equals" => Ok(OperatorField::LessOrEquals),
+                        "greater_than" => Ok(OperatorField::GreaterThan),
+                        "greater_or_equals" => Ok(OperatorField::GreaterOrEquals),
+                        _ => Err(E::custom(format!("Unexpected operator: {}", value))),
+                    }
+                }
+            }
+
+            deserializer.deserialize(FieldVisitor)
+        }
+    }
+
+    struct OperatorsVisitor;
+
+    impl Visitor for OperatorsVisitor {
+        type Value = Operators;
+
+        fn visit_map<V>(&mut self, mut visitor: V) -> Result<Operators, V::Error>
+            where V: MapVisitor
+        {
+            let mut operators = Vec::new();
+
+            while let Some(field) = try!(visitor.visit_key()) {
+                let operator = match field {
+                    OperatorField::Equals => Operator::Equals(try!(visitor.visit_value())),
+                    OperatorField::NotEquals => Operator::NotEquals(try!(visitor.visit_value())),
+                    OperatorField::Exists => Operator::Exists(try!(visitor.visit_value())),
+                    OperatorField::LessThan => Operator::LessThan(try!(visitor.visit_value())),
+                    OperatorField::LessOrEquals => Operator::LessOrEquals(try!(visitor.visit_value())),
+                    OperatorField::GreaterThan => Operator::GreaterThan(try!(visitor.visit_value())),
+                    OperatorField::GreaterOrEquals => Operator::GreaterOrEquals(try!(visitor.visit_value())),
+                };
+                operators.push(operator);
+            }
+
+            try!(visitor.end());
+
+            Ok(Operators(operators))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Operator, Pattern, PatternKind};
+    use message::MessageBuilder;
+    use serde_json::from_str;
+
+    #[test]
+    fn test_given_pattern_as_a_string_when_it_is_deserialized_then_it_does_not_have_predicates() {
+        let result = from_str::<Pattern>(r#""LOGIN""#);
+        let pattern = result.expect("Failed to deserialize a valid Pattern");
+        assert_eq!(Pattern::new("LOGIN"), pattern);
+    }
+
+    #[test]
+    fn test_given_pattern_as_an_object_when_it_is_deserialized_then_we_get_the_predicates() {
+        let text = r#"
+        {
+            "name": "AUTH",
+            "fields": {
+                "status": {
+                    "equals": "failed"
+                },
+                "retries": {
+                    "greater_than": 3,
+                    "less_or_equals": 10
+                }
+            }
+        }
+        "#;
+        let expected = Pattern::new("AUTH")
+                           .predicate("retries", Operator::GreaterThan(3.0))
+                           .predicate("retries", Operator::LessOrEquals(10.0))
+                           .predicate("status", Operator::Equals("failed".to_owned()));
+        let result = from_str::<Pattern>(text);
+        let pattern = result.expect("Failed to deserialize a valid Pattern");
+        assert_eq!(expected, pattern);
+    }
+
+    #[test]
+    fn test_given_pattern_when_it_contains_an_unknown_operator_then_it_cannot_be_deserialized() {
+        let text = r#"{ "name": "AUTH", "fields": { "status": { "unknown": "failed" } } }"#;
+        let result = from_str::<Pattern>(text);
+        let _ = result.err().expect("Successfully deserialized a Pattern with an unknown operator");
+    }
+
+    #[test]
+    fn test_given_pattern_without_name_when_it_is_deserialized_then_we_get_error() {
+        let text = r#"{ "fields": {} }"#;
+        let result = from_str::<Pattern>(text);
+        let _ = result.err().expect("Successfully deserialized a Pattern without a name");
+    }
+
+    #[test]
+    fn test_given_pattern_with_predicates_when_an_event_is_checked_then_every_predicate_has_to_match() {
+        let pattern = Pattern::new("AUTH")
+                          .predicate("status", Operator::NotEquals("ok".to_owned()))
+                          .predicate("user_name", Operator::Exists(true))
+                          .predicate("retries", Operator::GreaterOrEquals(3.0));
+        let failed = MessageBuilder::new("uuid", "message")
+                         .name(Some("AUTH"))
+                         .pair(b"status", b"failed")
+                         .pair(b"user_name", b"linus")
+                         .pair(b"retries", b"3")
+                         .build();
+        let succeeded = MessageBuilder::new("uuid", "message")
+                            .name(Some("AUTH"))
+                            .pair(b"status", b"ok")
+                            .pair(b"user_name", b"linus")
+                            .pair(b"retries", b"3")
+                            .build();
+        let not_a_number = MessageBuilder::new("uuid", "message")
+                               .name(Some("AUTH"))
+                               .pair(b"user_name", b"linus")
+                               .pair(b"retries", b"many")
+                               .build();
+        let other = MessageBuilder::new("uuid", "message")
+                        .name(Some("LOGIN"))
+                        .pair(b"user_name", b"linus")
+                        .pair(b"retries", b"3")
+                        .build();
+        assert_eq!(true, pattern.is_matching(&failed));
+        assert_eq!(false, pattern.is_matching(&succeeded));
+        assert_eq!(false, pattern.is_matching(&not_a_number));
+        assert_eq!(false, pattern.is_matching(&other));
+    }
+
+    #[test]
+    fn test_given_pattern_with_match_kind_when_it_is_deserialized_then_its_name_is_compiled() {
+        let result = from_str::<Pattern>(r#"{ "name": "SSHD_AUTH_*", "match": "glob" }"#);
+        let pattern = result.expect("Failed to deserialize a valid Pattern");
+        assert_eq!(PatternKind::Glob, pattern.kind());
+        assert_eq!(Some("^SSHD_AUTH_.*$"), pattern.regex());
+    }
+
+    #[test]
+    fn test_given_pattern_with_invalid_regex_when_it_is_deserialized_then_we_get_error() {
+        let result = from_str::<Pattern>(r#"{ "name": "SSHD_(", "match": "regex" }"#);
+        let _ = result.err().expect("Successfully deserialized a Pattern with an invalid regex");
+    }
+
+    #[test]
+    fn test_given_glob_pattern_when_ids_are_checked_then_only_the_whole_id_can_match() {
+        let pattern = Pattern::with_kind("SSHD_AUTH_?", PatternKind::Glob).unwrap();
+        assert_eq!(true, pattern.is_matching_id(b"SSHD_AUTH_1"));
+        assert_eq!(false, pattern.is_matching_id(b"SSHD_AUTH_10"));
+        assert_eq!(false, pattern.is_matching_id(b"X_SSHD_AUTH_1"));
+    }
+
+    #[test]
+    fn test_given_regex_pattern_when_ids_are_checked_then_they_are_searched_for_the_expression() {
+        let pattern = Pattern::with_kind("^SSHD_(AUTH|LOGIN)", PatternKind::Regex).unwrap();
+        assert_eq!(true, pattern.is_matching_id(b"SSHD_AUTH_FAILED"));
+        assert_eq!(true, pattern.is_matching_id(b"SSHD_LOGIN"));
+        assert_eq!(false, pattern.is_matching_id(b"SSHD_LOGOUT"));
+    }
+}
@@ -16,6 +16,8 @@ use std::collections::VecDeque;
 pub struct SharedData<'a, E, T> where E: 'a + MsgEvent, T: 'a + Template<Event=E> {
     pub responder: &'a mut VecDeque<Alert<E>>,
     pub map: &'a mut ContextMap<E, T>,
+    /// Set if a context suppressed the handled message.
+    pub suppressed: bool,
 }
 
 impl<'a, E, T> SharedData<'a, E, T> where E: 'a + MsgEvent, T: Template<Event=E> {
@@ -23,6 +25,7 @@ impl<'a, E, T> SharedData<'a, E, T> where E: 'a + MsgEvent, T: Template<Event=E>
         SharedData {
             map: map,
             responder: responder,
+            suppressed: false,
         }
     }
 }
@@ -7,14 +7,33 @@
 // modified, or distributed except according to those terms.
 
 use Event;
+use aggregate::{Accumulator, Aggregate};
+use context::map::map::ContextKey;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Debug)]
 pub struct State<E: Event> {
-    elapsed_time: Duration,
-    elapsed_time_since_last_message: Duration,
+    /// The time of the context clock the state was updated to.
+    now: Duration,
+    opened_at: Duration,
+    last_message_at: Duration,
+    /// The messages before `first` were removed, they are dropped in batches.
     messages: Vec<E>,
+    /// The timestamps of the messages.
+    arrivals: Vec<Duration>,
+    first: usize,
+    /// The messages from this index are a ring buffer rotated by the second
+    /// value, it's put in order by `normalize()`.
+    ring: Option<(usize, usize)>,
+    event_count: usize,
+    position: usize,
     opened: bool,
+    threshold_reached: bool,
+    accumulators: Vec<Accumulator>,
+    key: Arc<ContextKey>,
+    order: u64,
 }
 
 impl<E: Event> Default for State<E> {
@@ -29,53 +48,270 @@ impl<E: Event> State<E> {
     }
 
     pub fn with_messages(messages: Vec<E>) -> State<E> {
+        let arrivals = vec![Duration::from_secs(0); messages.len()];
+        let len = messages.len();
         State {
-            elapsed_time: Duration::from_secs(0),
-            elapsed_time_since_last_message: Duration::from_secs(0),
+            now: Duration::from_secs(0),
+            opened_at: Duration::from_secs(0),
+            last_message_at: Duration::from_secs(0),
+            event_count: len,
             messages: messages,
+            arrivals: arrivals,
+            first: 0,
+            ring: None,
+            position: len,
             opened: false,
+            threshold_reached: false,
+            accumulators: Vec::new(),
+            key: Arc::new(ContextKey::new()),
+            order: 0,
         }
     }
 
+    /// Creates a state which belongs to the given `context_id` key of a map context.
+    /// The key is shared with the map, so it isn't copied.
+    pub fn with_key<K: Into<Arc<ContextKey>>>(key: K) -> State<E> {
+        let mut state = State::new();
+        state.key = key.into();
+        state
+    }
+
+    /// The `context_id` key of the state, it's empty for linear contexts.
+    pub fn key(&self) -> &ContextKey {
+        &self.key
+    }
+
+    pub fn shared_key(&self) -> &Arc<ContextKey> {
+        &self.key
+    }
+
+    /// The position of the state in the eviction order of a map context.
+    pub fn order(&self) -> u64 {
+        self.order
+    }
+
+    pub fn set_order(&mut self, order: u64) {
+        self.order = order;
+    }
+
     pub fn is_open(&self) -> bool {
         self.opened
     }
 
     pub fn open(&mut self) {
         self.opened = true;
+        self.opened_at = self.now;
     }
 
     pub fn close(&mut self) {
         self.reset();
     }
 
+    pub fn is_threshold_reached(&self) -> bool {
+        self.threshold_reached
+    }
+
+    pub fn mark_threshold_reached(&mut self) {
+        self.threshold_reached = true;
+    }
+
+    pub fn rearm_threshold(&mut self) {
+        self.threshold_reached = false;
+    }
+
+    /// The time of the context clock the state was updated to (see `set_time()`).
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// The time when the state was opened.
+    pub fn opened_at(&self) -> Duration {
+        self.opened_at
+    }
+
+    /// The time when the state received its last message.
+    pub fn last_message_at(&self) -> Duration {
+        self.last_message_at
+    }
+
     pub fn elapsed_time(&self) -> Duration {
-        self.elapsed_time
+        self.now - self.opened_at
     }
 
     pub fn elapsed_time_since_last_message(&self) -> Duration {
-        self.elapsed_time_since_last_message
+        self.now - self.last_message_at
     }
 
+    /// The stored messages in the order of their arrival, after `normalize()`.
     pub fn messages(&self) -> &[E] {
-        &self.messages
+        &self.messages[self.first..]
+    }
+
+    /// Returns the message which was stored last, even if the messages aren't in order.
+    pub fn last_message(&self) -> Option<&E> {
+        match self.ring {
+            Some((index, rotation)) if rotation > 0 => self.messages.get(self.first + index + rotation - 1),
+            _ => self.messages.last(),
+        }
+    }
+
+    /// Puts the messages replaced by `replace_message()` in order. The
+    /// contexts call it before their actions can see the messages.
+    pub fn normalize(&mut self) {
+        if let Some((index, rotation)) = self.ring.take() {
+            let start = self.first + index;
+            rotate_left(&mut self.messages[start..], rotation);
+            rotate_left(&mut self.arrivals[start..], rotation);
+        }
+    }
+
+    /// Returns the number of events received by the context, including the
+    /// ones which weren't stored.
+    pub fn event_count(&self) -> usize {
+        self.event_count
+    }
+
+    /// Returns the number of events stored in the sequence of the state since
+    /// it was opened, including the ones which were dropped by the overflow
+    /// policies. It's the position of the next event in an ordered context.
+    pub fn position(&self) -> usize {
+        self.position
     }
 
     pub fn add_message(&mut self, message: E) {
+        self.push_message(message);
+        self.position += 1;
+    }
+
+    fn push_message(&mut self, message: E) {
+        self.normalize();
         self.messages.push(message);
-        self.elapsed_time_since_last_message = Duration::from_secs(0);
+        self.arrivals.push(self.now);
+        self.event_count += 1;
+        self.last_message_at = self.now;
+    }
+
+    /// Counts a message without storing it.
+    pub fn skip_message(&mut self) {
+        self.event_count += 1;
+        self.position += 1;
+        self.last_message_at = self.now;
+    }
+
+    /// Adds a message and removes the stored one at `index`. Removing the
+    /// first message (e.g. by a ring buffer) takes constant amortized time.
+    /// Removing the same `index` repeatedly (e.g. to keep the first and the
+    /// last messages) takes constant time: the messages from `index` are
+    /// kept in a ring until `normalize()` is called.
+    pub fn replace_message(&mut self, index: usize, message: E) {
+        if index == 0 {
+            self.normalize();
+            self.drop_first_messages(1);
+            self.add_message(message);
+            return;
+        }
+        let rotation = match self.ring {
+            Some((ring_index, rotation)) if ring_index == index => rotation,
+            _ => {
+                self.normalize();
+                0
+            }
+        };
+        let slot = self.first + index + rotation;
+        self.messages[slot] = message;
+        self.arrivals[slot] = self.now;
+        let len = self.messages.len() - self.first - index;
+        self.ring = Some((index, (rotation + 1) % len));
+        self.event_count += 1;
+        self.position += 1;
+        self.last_message_at = self.now;
+    }
+
+    /// Counts a suppressed message. Only the first and the last messages are kept.
+    pub fn suppress_message(&mut self, message: E) {
+        self.normalize();
+        if self.messages().len() >= 2 {
+            let _ = self.messages.pop();
+            let _ = self.arrivals.pop();
+        }
+        self.push_message(message);
+    }
+
+    /// Returns the number of messages which were added at least `window` time
+    /// ago. The messages have to be in order (see `normalize()`).
+    pub fn expired_messages(&self, window: Duration) -> usize {
+        self.arrivals[self.first..].iter().take_while(|arrival| self.now - **arrival >= window).count()
+    }
+
+    /// Removes the `count` oldest messages.
+    pub fn remove_messages(&mut self, count: usize) {
+        self.drop_first_messages(count);
+        self.event_count -= count;
     }
 
-    pub fn update_timers(&mut self, event: &Duration) {
-        let delta = *event;
-        self.elapsed_time = self.elapsed_time + delta;
-        self.elapsed_time_since_last_message = self.elapsed_time_since_last_message + delta;
+    fn drop_first_messages(&mut self, count: usize) {
+        self.normalize();
+        self.first += count;
+        // the removed messages are dropped when they are the majority, so
+        // the remaining ones are moved only once per many removals
+        if self.first * 2 >= self.messages.len() {
+            let _ = self.messages.drain(..self.first);
+            let _ = self.arrivals.drain(..self.first);
+            self.first = 0;
+        }
+    }
+
+    /// Updates the aggregates with an event received by the context.
+    pub fn update_aggregates(&mut self, aggregates: &BTreeMap<String, Aggregate>, message: &E) {
+        if self.accumulators.len() != aggregates.len() {
+            self.accumulators = vec![Accumulator::default(); aggregates.len()];
+        }
+        for (aggregate, accumulator) in aggregates.values().zip(self.accumulators.iter_mut()) {
+            aggregate.update(accumulator, message);
+        }
+    }
+
+    /// Recomputes the aggregates from the stored messages, e.g. after some of them expired.
+    pub fn rebuild_aggregates(&mut self, aggregates: &BTreeMap<String, Aggregate>) {
+        self.accumulators = vec![Accumulator::default(); aggregates.len()];
+        for message in &self.messages[self.first..] {
+            for (aggregate, accumulator) in aggregates.values().zip(self.accumulators.iter_mut()) {
+                aggregate.update(accumulator, message);
+            }
+        }
+    }
+
+    /// Returns the intermediate results of the aggregates in the order of their names.
+    pub fn accumulators(&self) -> &[Accumulator] {
+        &self.accumulators
+    }
+
+    /// Moves the state to the time `now` of the context clock. The timeouts are
+    /// measured from the timestamps of the state to this time, it never goes backwards.
+    pub fn set_time(&mut self, now: Duration) {
+        if now > self.now {
+            self.now = now;
+        }
     }
 
     fn reset(&mut self) {
-        self.elapsed_time = Duration::from_secs(0);
-        self.elapsed_time_since_last_message = Duration::from_secs(0);
+        self.opened_at = self.now;
+        self.last_message_at = self.now;
         self.messages.clear();
+        self.arrivals.clear();
+        self.first = 0;
+        self.ring = None;
+        self.event_count = 0;
+        self.position = 0;
         self.opened = false;
+        self.threshold_reached = false;
+        self.accumulators.clear();
     }
 }
+
+/// Rotates `slice` to the left by `mid` elements.
+fn rotate_left<T>(slice: &mut [T], mid: usize) {
+    slice[..mid].reverse();
+    slice[mid..].reverse();
+    slice.reverse();
+}
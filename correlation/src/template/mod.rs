@@ -0,0 +1,109 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::io::Write;
+
+use CompileError;
+use Event;
+use Message;
+use Template;
+use TemplateContext;
+use TemplateFactory;
+
+use self::parser::{Function, Selector, Token};
+
+mod parser;
+#[cfg(test)]
+mod test;
+
+/// The field which refers to the message of the event if it's not among its values.
+pub const MESSAGE: &'static str = "MESSAGE";
+
+/// A `Template` which can be used without syslog-ng. Its syntax:
+///
+/// * `${field}`: a field of the event which triggered the action or of the last message of the context,
+/// * `${field@first}`, `${field@last}`, `${field@N}`: a field of the first, the last or the Nth (from 1) message,
+/// * `$(context_uuid)`, `$(context_name)`, `$(context_len)`, `$(event_count)`, `$(close_reason)`: the context metadata,
+/// * `$(aggregate name)`: the value of an aggregate of the context,
+/// * `$$`: a literal `$`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageTemplate {
+    tokens: Vec<Token>,
+}
+
+impl MessageTemplate {
+    pub fn compile(template: &[u8]) -> Result<MessageTemplate, CompileError> {
+        parser::parse(template).map(|tokens| MessageTemplate { tokens: tokens })
+    }
+
+    fn select<'a>(context: &TemplateContext<'a, Message>, selector: &Selector) -> Option<&'a Message> {
+        match *selector {
+            Selector::Current => context.event.or_else(|| context.messages.last()),
+            Selector::First => context.messages.first(),
+            Selector::Last => context.messages.last(),
+            Selector::Nth(index) => context.messages.get(index),
+        }
+    }
+
+    fn format_function(context: &TemplateContext<Message>, function: &Function, buffer: &mut Write) {
+        let _ = match *function {
+            Function::ContextUuid => buffer.write_all(context.context_id.as_bytes()),
+            Function::ContextName => buffer.write_all(context.context_name.unwrap_or("").as_bytes()),
+            Function::ContextLen => buffer.write_fmt(format_args!("{}", context.messages.len())),
+            Function::EventCount => buffer.write_fmt(format_args!("{}", context.event_count)),
+            Function::CloseReason => buffer.write_all(context.close_reason.map_or("", |reason| reason.as_str()).as_bytes()),
+            Function::Aggregate(ref name) => {
+                buffer.write_all(context.aggregates.get(&name[..]).map_or("", |value| &value[..]).as_bytes())
+            }
+        };
+    }
+}
+
+impl Template for MessageTemplate {
+    type Event = Message;
+
+    fn format_with_context(&self, messages: &[Message], context_id: &str, buffer: &mut Write) {
+        self.format(&TemplateContext::new(messages, context_id), buffer)
+    }
+
+    fn format(&self, context: &TemplateContext<Message>, buffer: &mut Write) {
+        for token in &self.tokens {
+            match *token {
+                Token::Literal(ref literal) => {
+                    let _ = buffer.write_all(literal);
+                }
+                Token::Field(ref name, ref selector) => {
+                    if let Some(message) = MessageTemplate::select(context, selector) {
+                        let value = message.get(name).or_else(|| {
+                            if name == MESSAGE.as_bytes() {
+                                Some(message.message())
+                            } else {
+                                None
+                            }
+                        });
+                        if let Some(value) = value {
+                            let _ = buffer.write_all(value);
+                        }
+                    }
+                }
+                Token::Function(ref function) => MessageTemplate::format_function(context, function, buffer),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessageTemplateFactory;
+
+impl TemplateFactory<Message> for MessageTemplateFactory {
+    type Template = MessageTemplate;
+
+    fn compile(&self, value: &[u8]) -> Result<MessageTemplate, CompileError> {
+        MessageTemplate::compile(value)
+    }
+}
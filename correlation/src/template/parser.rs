@@ -0,0 +1,158 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::str::{self, FromStr};
+
+use CompileError;
+
+/// Selects the message of the context a field is read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selector {
+    /// The event which triggered the action or the last message.
+    Current,
+    First,
+    Last,
+    /// Indexed from 0.
+    Nth(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Function {
+    ContextUuid,
+    ContextName,
+    ContextLen,
+    EventCount,
+    CloseReason,
+    Aggregate(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Literal(Vec<u8>),
+    Field(Vec<u8>, Selector),
+    Function(Function),
+}
+
+fn error(message: String) -> CompileError {
+    CompileError(message.into_bytes())
+}
+
+pub fn parse(template: &[u8]) -> Result<Vec<Token>, CompileError> {
+    let mut tokens = Vec::new();
+    let mut literal = Vec::new();
+    let mut pos = 0;
+
+    while pos < template.len() {
+        if template[pos] != b'$' {
+            literal.push(template[pos]);
+            pos += 1;
+            continue;
+        }
+        let token = match template.get(pos + 1) {
+            Some(&b'$') => {
+                literal.push(b'$');
+                pos += 2;
+                continue;
+            }
+            Some(&b'{') => {
+                let (content, end) = try!(enclosed(template, pos, b'}'));
+                let token = try!(parse_field(content, pos));
+                pos = end;
+                token
+            }
+            Some(&b'(') => {
+                let (content, end) = try!(enclosed(template, pos, b')'));
+                let token = try!(parse_function(content, pos));
+                pos = end;
+                token
+            }
+            Some(&c) => {
+                return Err(error(format!("Unexpected character after '$' at position {}: '{}', use '$$' for a literal '$'",
+                                         pos,
+                                         c as char)))
+            }
+            None => return Err(error(format!("Unexpected end of template after '$' at position {}, use '$$' for a literal '$'", pos))),
+        };
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+            literal = Vec::new();
+        }
+        tokens.push(token);
+    }
+
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+    Ok(tokens)
+}
+
+/// Returns the content between the opening bracket after the `$` at `start`
+/// and `closing`, and the position after `closing`.
+fn enclosed(template: &[u8], start: usize, closing: u8) -> Result<(&str, usize), CompileError> {
+    let content_start = start + 2;
+    let length = match template[content_start..].iter().position(|&c| c == closing) {
+        Some(length) => length,
+        None => return Err(error(format!("Missing '{}' for the reference at position {}", closing as char, start))),
+    };
+    let content = &template[content_start..content_start + length];
+    match str::from_utf8(content) {
+        Ok(content) => Ok((content.trim(), content_start + length + 1)),
+        Err(_) => Err(error(format!("The reference at position {} is not a valid UTF-8 string", start))),
+    }
+}
+
+fn parse_field(content: &str, start: usize) -> Result<Token, CompileError> {
+    let (name, selector) = match content.rfind('@') {
+        Some(at) => (&content[..at], try!(parse_selector(&content[at + 1..], start))),
+        None => (content, Selector::Current),
+    };
+    if name.is_empty() {
+        return Err(error(format!("Missing field name in the reference at position {}", start)));
+    }
+    Ok(Token::Field(name.as_bytes().to_vec(), selector))
+}
+
+fn parse_selector(selector: &str, start: usize) -> Result<Selector, CompileError> {
+    match selector {
+        "first" => Ok(Selector::First),
+        "last" => Ok(Selector::Last),
+        _ => {
+            match usize::from_str(selector) {
+                Ok(index) if index > 0 => Ok(Selector::Nth(index - 1)),
+                _ => {
+                    Err(error(format!("Invalid message selector in the reference at position {}: '{}', expected first, last or a positive number",
+                                      start,
+                                      selector)))
+                }
+            }
+        }
+    }
+}
+
+fn parse_function(content: &str, start: usize) -> Result<Token, CompileError> {
+    let mut words = content.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let function = match name {
+        "context_uuid" => Function::ContextUuid,
+        "context_name" => Function::ContextName,
+        "context_len" => Function::ContextLen,
+        "event_count" => Function::EventCount,
+        "close_reason" => Function::CloseReason,
+        "aggregate" => {
+            match words.next() {
+                Some(aggregate) => Function::Aggregate(aggregate.to_owned()),
+                None => return Err(error(format!("Missing aggregate name in the function at position {}", start))),
+            }
+        }
+        _ => return Err(error(format!("Unknown function at position {}: '{}'", start, name))),
+    };
+    if let Some(argument) = words.next() {
+        return Err(error(format!("Unexpected argument of '{}' in the function at position {}: '{}'", name, start, argument)));
+    }
+    Ok(Token::Function(function))
+}
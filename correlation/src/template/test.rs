@@ -0,0 +1,113 @@
+// Copyright (c) 2016 Tibor Benke <ihrwein@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use template::{MessageTemplate, MessageTemplateFactory};
+use conditions::{CloseReason, ConditionsBuilder};
+use config::action::message::MessageActionBuilder;
+use action::Action;
+use state::State;
+use message::MessageBuilder;
+use test_utils::BaseContextBuilder;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use uuid::Uuid;
+use CompileError;
+use Event;
+use Message;
+use Template;
+use TemplateContext;
+use TemplateFactory;
+
+fn messages() -> Vec<Message> {
+    vec![MessageBuilder::new("uuid1", "login").pair(b"user_name", b"alice").pair(b"host", b"host1").build(),
+         MessageBuilder::new("uuid2", "read").pair(b"user_name", b"bob").build(),
+         MessageBuilder::new("uuid3", "logout").pair(b"user_name", b"carol").build()]
+}
+
+fn render(template: &str, context: &TemplateContext<Message>) -> String {
+    let template = MessageTemplate::compile(template.as_bytes()).expect("Failed to compile a valid template");
+    let mut buffer = Vec::new();
+    template.format(context, &mut buffer);
+    String::from_utf8(buffer).unwrap()
+}
+
+fn compile_error(template: &str) -> String {
+    let CompileError(error) = MessageTemplate::compile(template.as_bytes()).err().expect("Successfully compiled an invalid template");
+    String::from_utf8(error).unwrap()
+}
+
+#[test]
+fn test_given_template_with_field_references_when_it_is_formatted_then_the_fields_of_the_selected_messages_are_inserted() {
+    let messages = messages();
+    let context = TemplateContext::new(&messages, "context");
+    assert_eq!("carol logged out", render("${user_name} logged out", &context));
+    assert_eq!("alice,bob,carol", render("${user_name@first},${user_name@2},${user_name@last}", &context));
+    assert_eq!("host1@", render("${host@1}@${host}", &context));
+    assert_eq!("login", render("${MESSAGE@first}", &context));
+    assert_eq!("", render("${user_name@4}", &context));
+}
+
+#[test]
+fn test_given_template_when_it_is_triggered_by_an_event_then_the_field_references_refer_to_it() {
+    let messages = messages();
+    let mut context = TemplateContext::new(&messages[..2], "context");
+    context.event = Some(&messages[2]);
+    assert_eq!("carol after alice", render("${user_name} after ${user_name@first}", &context));
+}
+
+#[test]
+fn test_given_template_with_functions_when_it_is_formatted_then_the_context_metadata_is_inserted() {
+    let messages = messages();
+    let mut context = TemplateContext::new(&messages, "2f34112c-6fc8-406b-a6f0-78158ca724b6");
+    context.context_name = Some("LOGIN");
+    context.close_reason = Some(CloseReason::Timeout);
+    context.event_count = 5;
+    context.aggregates.insert("users", "3".to_owned());
+    assert_eq!("LOGIN 2f34112c-6fc8-406b-a6f0-78158ca724b6 3/5 timeout 3",
+               render("$(context_name) $( context_uuid ) $(context_len)/$(event_count) $(close_reason) $(aggregate users)", &context));
+}
+
+#[test]
+fn test_given_template_with_escaped_dollar_signs_when_it_is_formatted_then_they_are_literals() {
+    let messages = messages();
+    let context = TemplateContext::new(&messages, "context");
+    assert_eq!("costs $5 ${user_name}", render("costs $$5 $${user_name}", &context));
+}
+
+#[test]
+fn test_given_invalid_templates_when_they_are_compiled_then_the_errors_are_reported() {
+    assert_eq!("Missing '}' for the reference at position 6", compile_error("login ${user_name"));
+    assert_eq!("Missing field name in the reference at position 0", compile_error("${@last}"));
+    assert_eq!("Invalid message selector in the reference at position 0: '0', expected first, last or a positive number",
+               compile_error("${user_name@0}"));
+    assert_eq!("Unknown function at position 0: 'uptime'", compile_error("$(uptime)"));
+    assert_eq!("Missing aggregate name in the function at position 0", compile_error("$(aggregate)"));
+    assert_eq!("Unexpected argument of 'context_len' in the function at position 4: 'users'", compile_error("len $(context_len users)"));
+    assert_eq!("Unexpected character after '$' at position 6: '5', use '$$' for a literal '$'", compile_error("costs $5"));
+    assert_eq!("Unexpected end of template after '$' at position 5, use '$$' for a literal '$'", compile_error("cost $"));
+}
+
+#[test]
+fn test_given_message_action_compiled_by_the_factory_when_it_is_executed_then_the_native_templates_are_rendered() {
+    let factory = MessageTemplateFactory;
+    let action = MessageActionBuilder::<MessageTemplate>::new("uuid", factory.compile(b"${user_name@first} logged in").unwrap())
+                     .pair("context", factory.compile(b"$(context_name) $(context_len)").unwrap())
+                     .build();
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    let base_context = BaseContextBuilder::<Message, MessageTemplate>::new(Uuid::new_v4(), conditions)
+                           .name(Some("LOGIN".to_owned()))
+                           .build();
+    let state = State::with_messages(messages());
+    let mut responder = VecDeque::default();
+
+    action.on_closed(&state, &base_context, CloseReason::Completed, &mut responder);
+
+    assert_eq!(b"alice logged in", responder[0].message.message());
+    assert_eq!(Some(&b"LOGIN 3"[..]), responder[0].message.get(b"context"));
+}
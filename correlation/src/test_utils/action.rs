@@ -0,0 +1,34 @@
+use uuid::Uuid;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use Alert;
+use Message;
+use action::Action;
+use conditions::{CloseReason, ConditionsBuilder};
+use context::BaseContext;
+use message::MessageBuilder;
+use state::State;
+use super::{BaseContextBuilder, MockTemplate};
+
+/// Returns the builder of the `LOGIN` context, which executes the actions in their tests.
+pub fn login_context_builder() -> BaseContextBuilder<Message, MockTemplate> {
+    let conditions = ConditionsBuilder::new(Duration::from_millis(100)).build();
+    BaseContextBuilder::new(Uuid::parse_str("2f34112c-6fc8-406b-a6f0-78158ca724b6").unwrap(), conditions)
+        .name(Some("LOGIN".to_owned()))
+}
+
+pub fn base_context() -> BaseContext<Message, MockTemplate> {
+    login_context_builder().build()
+}
+
+pub fn state() -> State<Message> {
+    State::with_messages(vec![MessageBuilder::new("uuid1", "message1").build()])
+}
+
+/// Executes `action` as if the `LOGIN` context was closed by its timeout.
+pub fn close<A: Action<Message, MockTemplate>>(action: &A) -> VecDeque<Alert<Message>> {
+    let mut responder = VecDeque::default();
+    action.on_closed(&state(), &base_context(), CloseReason::Timeout, &mut responder);
+    responder
+}
@@ -1,7 +1,10 @@
 use uuid::Uuid;
+use std::collections::BTreeMap;
 
 use Event;
+use Aggregate;
 use Template;
+use Pattern;
 use Conditions;
 use ActionType;
 use context::BaseContext;
@@ -11,7 +14,8 @@ pub struct BaseContextBuilder<E, T> where E: Event, T: Template<Event=E> {
     uuid: Uuid,
     conditions: Conditions,
     actions: Vec<ActionType<T>>,
-    patterns: Vec<String>
+    patterns: Vec<Pattern>,
+    aggregates: BTreeMap<String, Aggregate>,
 }
 
 impl<E, T> BaseContextBuilder<E, T> where E: Event, T: Template<Event=E> {
@@ -21,7 +25,8 @@ impl<E, T> BaseContextBuilder<E, T> where E: Event, T: Template<Event=E> {
             uuid: uuid,
             conditions: conditions,
             actions: Vec::new(),
-            patterns: Vec::new()
+            patterns: Vec::new(),
+            aggregates: BTreeMap::new(),
         }
     }
 
@@ -35,18 +40,26 @@ impl<E, T> BaseContextBuilder<E, T> where E: Event, T: Template<Event=E> {
         self
     }
 
-    pub fn patterns(mut self, patterns: Vec<String>) -> BaseContextBuilder<E, T> {
-        self.patterns = patterns;
+    pub fn patterns<P: Into<Pattern>>(mut self, patterns: Vec<P>) -> BaseContextBuilder<E, T> {
+        self.patterns = patterns.into_iter().map(Into::into).collect();
         self
     }
+
+    pub fn aggregates(mut self, aggregates: BTreeMap<String, Aggregate>) -> BaseContextBuilder<E, T> {
+        self.aggregates = aggregates;
+        self
+    }
+
     pub fn build(self) -> BaseContext<E, T> {
-        let BaseContextBuilder {name, uuid, conditions, actions, patterns} = self;
+        let BaseContextBuilder {name, uuid, conditions, actions, patterns, aggregates} = self;
         BaseContext {
             name: name,
             uuid: uuid,
+            hyphenated_uuid: uuid.to_hyphenated_string(),
             conditions: conditions,
             actions: actions,
-            patterns: patterns
+            patterns: patterns,
+            aggregates: aggregates,
         }
     }
 }
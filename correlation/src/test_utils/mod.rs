@@ -8,6 +8,8 @@
 
 pub use self::template::{MockTemplate, MockTemplateFactory};
 pub use self::base_context::BaseContextBuilder;
+pub use self::action::{base_context, close, login_context_builder, state};
 
 mod template;
 mod base_context;
+mod action;
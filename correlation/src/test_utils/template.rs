@@ -1,4 +1,5 @@
 use Template;
+use TemplateContext;
 use Event;
 use TemplateFactory;
 use Message;
@@ -11,30 +12,60 @@ pub struct MockTemplate {
 }
 
 pub trait Mock: Send {
-    fn call(&self, messages: &[Message], context_id: &str, &mut Write);
+    fn call(&self, context: &TemplateContext<Message>, &mut Write);
 }
 
 /// implement Mock for bare fns
-impl<F: Send + for<'a, 'b, 'c> Fn(&'a [Message], &'b str, &'c mut Write)> Mock for F {
-    fn call(&self, messages: &[Message], context_id: &str, buffer: &mut Write) {
-        (*self)(messages, context_id, buffer)
+impl<F: Send + for<'a, 'b, 'c> Fn(&'a TemplateContext<'b, Message>, &'c mut Write)> Mock for F {
+    fn call(&self, context: &TemplateContext<Message>, buffer: &mut Write) {
+        (*self)(context, buffer)
     }
 }
 
 struct LiteralMockTemplate(Vec<u8>);
 
 impl Mock for LiteralMockTemplate {
-    fn call(&self, _: &[Message], _: &str, buffer: &mut Write) {
+    fn call(&self, _: &TemplateContext<Message>, buffer: &mut Write) {
         let _ = buffer.write(&self.0);
     }
 }
 
-fn context_id(_: &[Message], context_id: &str, buffer: &mut Write) {
-    let _ = buffer.write(context_id.as_bytes());
+fn context_id(context: &TemplateContext<Message>, buffer: &mut Write) {
+    let _ = buffer.write(context.context_id.as_bytes());
 }
 
-fn context_len(messages: &[Message], _: &str, buffer: &mut Write) {
-    let _ = buffer.write_fmt(format_args!("{}", messages.len()));
+fn context_len(context: &TemplateContext<Message>, buffer: &mut Write) {
+    let _ = buffer.write_fmt(format_args!("{}", context.messages.len()));
+}
+
+fn event_count(context: &TemplateContext<Message>, buffer: &mut Write) {
+    let _ = buffer.write_fmt(format_args!("{}", context.event_count));
+}
+
+fn close_reason(context: &TemplateContext<Message>, buffer: &mut Write) {
+    if let Some(reason) = context.close_reason {
+        let _ = buffer.write(reason.as_str().as_bytes());
+    }
+}
+
+struct AggregateMockTemplate(String);
+
+impl Mock for AggregateMockTemplate {
+    fn call(&self, context: &TemplateContext<Message>, buffer: &mut Write) {
+        if let Some(value) = context.aggregates.get(&self.0[..]) {
+            let _ = buffer.write(value.as_bytes());
+        }
+    }
+}
+
+struct EventMockTemplate(Vec<u8>);
+
+impl Mock for EventMockTemplate {
+    fn call(&self, context: &TemplateContext<Message>, buffer: &mut Write) {
+        if let Some(value) = context.event.and_then(|event| event.get(&self.0)) {
+            let _ = buffer.write(value);
+        }
+    }
 }
 
 impl MockTemplate {
@@ -54,12 +85,37 @@ impl MockTemplate {
             with_context: Box::new(context_len),
         }
     }
+    pub fn event_count() -> MockTemplate {
+        MockTemplate {
+            with_context: Box::new(event_count),
+        }
+    }
+    // return the value of an aggregate of the context
+    pub fn aggregate(name: &str) -> MockTemplate {
+        MockTemplate {
+            with_context: Box::new(AggregateMockTemplate(name.to_owned())),
+        }
+    }
+    // return a value of the event which triggered the action
+    pub fn event_value(key: &[u8]) -> MockTemplate {
+        MockTemplate {
+            with_context: Box::new(EventMockTemplate(key.to_vec())),
+        }
+    }
+    pub fn close_reason() -> MockTemplate {
+        MockTemplate {
+            with_context: Box::new(close_reason),
+        }
+    }
 }
 
 impl Template for MockTemplate {
     type Event = Message;
     fn format_with_context(&self, messages: &[Self::Event], context_id: &str, buffer: &mut Write) {
-        self.with_context.call(messages, context_id, buffer)
+        self.format(&TemplateContext::new(messages, context_id), buffer)
+    }
+    fn format(&self, context: &TemplateContext<Self::Event>, buffer: &mut Write) {
+        self.with_context.call(context, buffer)
     }
 }
 
@@ -121,6 +177,17 @@ fn test_mock_template_can_return_context_id() {
     assert_eq!(context_id.as_bytes(), &actual[..]);
 }
 
+#[test]
+fn test_mock_template_can_return_close_reason() {
+    use CloseReason;
+    let template = MockTemplate::close_reason();
+    let mut context = TemplateContext::new(&[], "doesn't matter");
+    context.close_reason = Some(CloseReason::RenewTimeout);
+    let mut actual = Vec::new();
+    template.format(&context, &mut actual);
+    assert_eq!(b"renew_timeout", &actual[..]);
+}
+
 #[test]
 fn test_mock_template_can_return_context_length() {
     let expected = b"0";
@@ -129,3 +196,13 @@ fn test_mock_template_can_return_context_length() {
     template.format_with_context(&[], "doesn't matter", &mut actual);
     assert_eq!(expected, &actual[..]);
 }
+
+#[test]
+fn test_mock_template_can_return_event_count() {
+    let template = MockTemplate::event_count();
+    let mut context = TemplateContext::new(&[], "doesn't matter");
+    context.event_count = 42;
+    let mut actual = Vec::new();
+    template.format(&context, &mut actual);
+    assert_eq!(b"42", &actual[..]);
+}
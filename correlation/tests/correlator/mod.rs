@@ -81,3 +81,18 @@ fn test_name() {
         unreachable!();
     }
 }
+
+#[test]
+fn test_given_correlator_factory_when_a_context_loops_back_to_itself_then_it_returns_loopback_cycle_error() {
+    let _ = env_logger::init();
+    let contexts_file = "tests/correlator/loopback_cycle.json";
+    let template_factory = MockTemplateFactory::compile_value();
+    let result: Result<Correlator<Message, MockTemplate>, _> = CorrelatorFactory::from_path::<MockTemplate, &str, Message, MockTemplateFactory>(contexts_file, &template_factory);
+    match result.err().unwrap() {
+        Error::LoopbackCycle(contexts) => {
+            let contexts = contexts.iter().map(|uuid| uuid.to_hyphenated_string()).collect::<Vec<String>>();
+            assert_eq!(vec!["185e96da-c00e-454b-b4fe-9d0a14a86335", "185e96da-c00e-454b-b4fe-9d0a14a86335"], contexts);
+        }
+        _ => unreachable!(),
+    }
+}
@@ -6,7 +6,7 @@ extern crate correlation;
 
 use correlation::{Alert, Event, Template, TemplateFactory};
 use correlation::config::action::message::InjectMode;
-use correlation::correlator::{Correlator, CorrelatorFactory};
+use correlation::correlator::{Correlator, CorrelatorFactory, EventTime, IDLE_TIMEOUT_DEFAULT_MS};
 use std::borrow::Borrow;
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex, MutexGuard};
@@ -38,6 +38,11 @@ pub struct CorrelationParserBuilder<P, E, T, TF, TM> where P: Pipe, E: 'static +
     formatter: MessageFormatter,
     template_factory: TF,
     delta: Option<Duration>,
+    max_instances: Option<usize>,
+    event_time_field: Option<String>,
+    allowed_lateness: Duration,
+    idle_timeout: Option<Duration>,
+    max_loopback_depth: Option<usize>,
     _marker: PhantomData<(P, E, T, TF, TM)>
 }
 
@@ -70,6 +75,55 @@ impl<P, E, T, TF, TM> CorrelationParserBuilder<P, E, T, TF, TM> where P: Pipe, E
             Err(err) => error!("{}", err)
         }
     }
+
+    pub fn set_max_instances(&mut self, max_instances: String) {
+        match usize::from_str(&max_instances) {
+            Ok(max_instances) => {
+                info!("correlation-parser: keeping at most {} context instances", &max_instances);
+                self.max_instances = Some(max_instances);
+            },
+            Err(err) => error!("{}", err)
+        }
+    }
+
+    pub fn set_max_loopback_depth(&mut self, max_loopback_depth: String) {
+        match usize::from_str(&max_loopback_depth) {
+            Ok(max_loopback_depth) => {
+                info!("correlation-parser: sending messages back at most {} times in a row", &max_loopback_depth);
+                self.max_loopback_depth = Some(max_loopback_depth);
+            },
+            Err(err) => error!("{}", err)
+        }
+    }
+
+    pub fn set_event_time_field(&mut self, field: String) {
+        info!("correlation-parser: using the timestamps in {} to drive the timers", &field);
+        self.event_time_field = Some(field);
+    }
+
+    pub fn set_allowed_lateness(&mut self, allowed_lateness: String) {
+        match u64::from_str(&allowed_lateness) {
+            Ok(allowed_lateness) => {
+                info!("correlation-parser: accepting messages at most {} ms late", &allowed_lateness);
+                self.allowed_lateness = Duration::from_millis(allowed_lateness);
+            },
+            Err(err) => error!("{}", err)
+        }
+    }
+
+    pub fn set_idle_timeout(&mut self, idle_timeout: String) {
+        match u64::from_str(&idle_timeout) {
+            Ok(0) => {
+                info!("correlation-parser: only the timestamps of the messages advance the timers");
+                self.idle_timeout = None;
+            },
+            Ok(idle_timeout) => {
+                info!("correlation-parser: advancing the timers by the wall clock after {} ms without messages", &idle_timeout);
+                self.idle_timeout = Some(Duration::from_millis(idle_timeout));
+            },
+            Err(err) => error!("{}", err)
+        }
+    }
 }
 
 impl<P, E, T, TF, TM> ParserBuilder<P> for CorrelationParserBuilder<P, E, T, TF, TM> where P: Pipe, E: 'static + Event + Into<LogMessage> + Send, T: 'static + Template<Event=E>, TF: TemplateFactory<E, Template=T> + From<GlobalConfig>, TM: Timer<E, T> {
@@ -80,6 +134,11 @@ impl<P, E, T, TF, TM> ParserBuilder<P> for CorrelationParserBuilder<P, E, T, TF,
             formatter: MessageFormatter::new(),
             template_factory: TF::from(cfg),
             delta: Some(Duration::from_millis(1000)),
+            max_instances: None,
+            event_time_field: None,
+            allowed_lateness: Duration::from_millis(0),
+            idle_timeout: Some(Duration::from_millis(IDLE_TIMEOUT_DEFAULT_MS)),
+            max_loopback_depth: None,
             _marker: PhantomData
         }
     }
@@ -90,14 +149,24 @@ impl<P, E, T, TF, TM> ParserBuilder<P> for CorrelationParserBuilder<P, E, T, TF,
             options::CONTEXTS_FILE => self.set_file(&value),
             options::PREFIX => self.set_prefix(value),
             options::DELTA => self.set_delta(value),
+            options::MAX_INSTANCES => self.set_max_instances(value),
+            options::EVENT_TIME_FIELD => self.set_event_time_field(value),
+            options::ALLOWED_LATENESS => self.set_allowed_lateness(value),
+            options::IDLE_TIMEOUT => self.set_idle_timeout(value),
+            options::MAX_LOOPBACK_DEPTH => self.set_max_loopback_depth(value),
             _ => debug!("CorrelationParser: not supported key: {:?}", name)
         };
     }
     fn build(self) -> Result<Self::Parser, OptionError> {
         debug!("Building CorrelationParser");
-        let CorrelationParserBuilder {contexts, template_factory, formatter, delta, _marker } = self;
+        let CorrelationParserBuilder {contexts, template_factory, formatter, delta, max_instances, event_time_field, allowed_lateness, idle_timeout, max_loopback_depth, _marker } = self;
         let _ = template_factory;
-        let contexts = try!(contexts.ok_or(OptionError::missing_required_option(options::CONTEXTS_FILE)));
+        let mut contexts = try!(contexts.ok_or(OptionError::missing_required_option(options::CONTEXTS_FILE)));
+        contexts.set_max_instances(max_instances);
+        contexts.set_event_time(event_time_field.map(|field| EventTime::new(field, allowed_lateness).idle_timeout(idle_timeout)));
+        if let Some(max_loopback_depth) = max_loopback_depth {
+            contexts.set_max_loopback_depth(max_loopback_depth);
+        }
         let delta = try!(delta.ok_or(OptionError::missing_required_option(options::DELTA)));
         let correlator = Arc::new(Mutex::new(contexts));
         let timer = Arc::new(TM::new(delta, correlator.clone()));
@@ -147,6 +216,26 @@ impl<E, T, TM> CorrelationParser<E, T, TM> where E: Event + Send, T: Template<Ev
                 debug!("LOOPBACK: {}", String::from_utf8_lossy(alert.message.message()));
                 guard.push_message(alert.message);
             },
+            InjectMode::Tag => {
+                // the messages pushed back by loopback alerts don't continue in the pipeline
+                debug!("TAG: dropping the tags of a loopback message");
+            },
+        }
+    }
+
+    /// Writes the values of a tag alert into the message which triggered it.
+    fn tag(formatter: &mut MessageFormatter, msg: &mut LogMessage, alert: Alert<E>)
+        where E: Into<LogMessage> {
+        let tags: LogMessage = alert.message.into();
+        for (key, value) in tags.values() {
+            let key = String::from_utf8_lossy(&key).into_owned();
+            if key.as_bytes() == CLASSIFIER_UUID || key == "MESSAGE" {
+                continue;
+            }
+            let value = String::from_utf8_lossy(&value).into_owned();
+            debug!("TAG: {}={}", &key, &value);
+            let (key, value) = formatter.format(&key, &value);
+            msg.insert(key, value.as_bytes());
         }
     }
 }
@@ -171,11 +260,19 @@ impl<P, E, T, TM> Parser<P> for CorrelationParser<E, T, TM> where P: Pipe, E: Ev
 
         match self.correlator.lock() {
             Ok(mut guard) => {
-                guard.push_message(message);
+                // suppressed messages are dropped by syslog-ng
+                let forwarded = guard.push_message(message);
+                // the alerts of the loopback messages are queued after the ones of this message
+                let mut own_alerts = guard.responses.len();
                 while let Some(alert) = guard.responses.pop_front() {
-                    CorrelationParser::<E, T, TM>::on_alert(&mut guard, alert, parent);
+                    if alert.inject_mode == InjectMode::Tag && own_alerts > 0 {
+                        CorrelationParser::<E, T, TM>::tag(&mut self.formatter, msg, alert);
+                    } else {
+                        CorrelationParser::<E, T, TM>::on_alert(&mut guard, alert, parent);
+                    }
+                    own_alerts = own_alerts.saturating_sub(1);
                 }
-                true
+                forwarded
             },
             Err(err) => {
                 error!("{}", err);
@@ -4,7 +4,10 @@ use std::cell::RefCell;
 use syslog_ng_common::{self, GlobalConfig, LogTimeZone};
 
 use logevent::LogEvent;
-use correlation::{Template, TemplateFactory, CompileError};
+use correlation::{Template, TemplateContext, TemplateFactory, CompileError};
+
+pub const CLOSE_REASON: &'static str = "close_reason";
+pub const EVENT_COUNT: &'static str = "event_count";
 
 unsafe impl Send for LogTemplate {}
 
@@ -18,6 +21,32 @@ impl Template for LogTemplate {
         let formatted_bytes = template.format_with_context(&messages, None, LogTimeZone::Send, 0, context_id);
         let _ = buffer.write(formatted_bytes);
     }
+    fn format(&self, context: &TemplateContext<Self::Event>, buffer: &mut Write) {
+        let mut messages: Vec<syslog_ng_common::LogMessage> = context.messages.iter().map(|event| event.0.clone()).collect();
+        // the macros without a context reference are resolved on the last message,
+        // it should be the event which triggered the action
+        if let Some(event) = context.event {
+            if !context.messages.last().map_or(false, |last| last as *const LogEvent == event as *const LogEvent) {
+                messages.push(event.0.clone());
+            }
+        }
+        if messages.is_empty() {
+            messages.push(syslog_ng_common::LogMessage::new());
+        }
+        // the values of the context are name-value pairs of the (cloned) last message
+        if let Some(last) = messages.last_mut() {
+            for (name, value) in &context.aggregates {
+                last.insert(*name, value.as_bytes());
+            }
+            if let Some(reason) = context.close_reason {
+                last.insert(CLOSE_REASON, reason.as_str().as_bytes());
+            }
+            last.insert(EVENT_COUNT, context.event_count.to_string().as_bytes());
+        }
+        let mut template = self.0.borrow_mut();
+        let formatted_bytes = template.format_with_context(&messages, None, LogTimeZone::Send, 0, context.context_id);
+        let _ = buffer.write(formatted_bytes);
+    }
 }
 
 pub struct LogTemplateFactory(GlobalConfig);
@@ -1,3 +1,8 @@
 pub const CONTEXTS_FILE: &'static str = "contexts_file";
 pub const PREFIX: &'static str = "prefix";
 pub const DELTA: &'static str = "delta";
+pub const MAX_INSTANCES: &'static str = "max_instances";
+pub const EVENT_TIME_FIELD: &'static str = "event_time_field";
+pub const ALLOWED_LATENESS: &'static str = "allowed_lateness";
+pub const IDLE_TIMEOUT: &'static str = "idle_timeout";
+pub const MAX_LOOPBACK_DEPTH: &'static str = "max_loopback_depth";
@@ -0,0 +1,44 @@
+extern crate correlation_parser;
+extern crate correlation;
+extern crate syslog_ng_common;
+
+use correlation_parser::{LogEvent, LogTemplateFactory};
+use correlation::{CloseReason, Event, TemplateContext, TemplateFactory, Template};
+use syslog_ng_common::{SYSLOG_NG_INITIALIZED, syslog_ng_global_init, GlobalConfig};
+
+#[test]
+fn test_given_log_template_when_it_is_formatted_with_a_context_then_the_values_of_the_context_are_available() {
+    SYSLOG_NG_INITIALIZED.call_once(|| {
+        unsafe { syslog_ng_global_init(); }
+    });
+    let factory = LogTemplateFactory::from(GlobalConfig::new(0x0308));
+    let template = factory.compile(b"${MESSAGE} ${event_count} ${close_reason} ${bytes}")
+                          .ok()
+                          .expect("Failed to compile a valid LogTemplate");
+    let messages = vec![LogEvent::new(b"uuid", b"first"), LogEvent::new(b"uuid", b"last")];
+    let mut context = TemplateContext::new(&messages, "context_id");
+    context.event_count = 3;
+    context.close_reason = Some(CloseReason::Timeout);
+    context.aggregates.insert("bytes", "1024".to_owned());
+    let mut buffer = Vec::new();
+    template.format(&context, &mut buffer);
+    assert_eq!(&b"last 3 timeout 1024"[..], &buffer[..]);
+}
+
+#[test]
+fn test_given_log_template_when_the_triggering_event_is_not_stored_then_it_is_the_last_message() {
+    SYSLOG_NG_INITIALIZED.call_once(|| {
+        unsafe { syslog_ng_global_init(); }
+    });
+    let factory = LogTemplateFactory::from(GlobalConfig::new(0x0308));
+    let template = factory.compile(b"${MESSAGE}")
+                          .ok()
+                          .expect("Failed to compile a valid LogTemplate");
+    let messages = vec![LogEvent::new(b"uuid", b"stored")];
+    let event = LogEvent::new(b"uuid", b"discarded");
+    let mut context = TemplateContext::new(&messages, "context_id");
+    context.event = Some(&event);
+    let mut buffer = Vec::new();
+    template.format(&context, &mut buffer);
+    assert_eq!(&b"discarded"[..], &buffer[..]);
+}